@@ -0,0 +1,189 @@
+use amethyst::ecs::prelude::Entity;
+use amethyst::shrev::EventChannel;
+
+use crate::components::npc::{CanTarget, Downed};
+use crate::headless::{health, Headless};
+use crate::resources::archetypes::ArchetypeRegistry;
+use crate::resources::events::CombatEvent;
+use crate::resources::replay::RecordedOrder;
+use crate::resources::run::Run;
+use crate::resources::simulation::TICK_SECONDS;
+
+use std::collections::HashMap;
+
+// A battle that goes on for longer than this is called a loss.
+const TIME_LIMIT: f32 = 120.0;
+const DEFAULT_BATTLES: u32 = 1000;
+
+// Which archetypes fight which, and how many times.
+pub struct Matchup {
+    pub party: Vec<String>,
+    pub enemies: Vec<String>,
+    pub battles: u32,
+}
+
+// The matchup to simulate instead of starting the game, passed as
+// `--simulate <party> <enemies> [battles]` with the archetypes
+// separated by commas, e.g. `--simulate warrior,cleric orc_grunt,orc_grunt 500`.
+pub fn simulate_arg() -> Option<Matchup> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--simulate");
+    args.next()?;
+    let party = archetypes(&args.next()?);
+    let enemies = archetypes(&args.next()?);
+    let battles = args.next().and_then(|b| b.parse().ok()).unwrap_or(DEFAULT_BATTLES);
+    Some(Matchup { party, enemies, battles })
+}
+
+fn archetypes(arg: &str) -> Vec<String> {
+    arg.split(',').filter(|id| !id.is_empty()).map(String::from).collect()
+}
+
+struct Outcome {
+    won: bool,
+    // How long each enemy that went down lasted once fighting it started.
+    kill_seconds: Vec<f32>,
+    damage_taken: f32,
+    // Which party members were still standing, in party order.
+    survivors: Vec<bool>,
+}
+
+// Plays the matchup out `battles` times, each from its own seed, and
+// prints how it went.
+pub fn simulate(matchup: &Matchup) -> amethyst::Result<()> {
+    if matchup.party.is_empty() || matchup.enemies.is_empty() || matchup.battles == 0 {
+        return Err(amethyst::Error::from_string("nothing to simulate"));
+    }
+
+    let mut game = Headless::new(0);
+    let unknown: Vec<&str> = {
+        let registry = game.world.read_resource::<ArchetypeRegistry>();
+        matchup.party
+            .iter()
+            .chain(&matchup.enemies)
+            .filter(|id| registry.get(id).is_none())
+            .map(|id| id.as_str())
+            .collect()
+    };
+    if !unknown.is_empty() {
+        return Err(amethyst::Error::from_string(format!("unknown archetype : {}", unknown.join(", "))));
+    }
+
+    println!(
+        "simulating {} against {}, {} battles",
+        matchup.party.join(", "),
+        matchup.enemies.join(", "),
+        matchup.battles
+    );
+    let outcomes: Vec<Outcome> = (0..matchup.battles)
+        .map(|seed| {
+            game.reset(seed);
+            battle(&mut game, matchup)
+        })
+        .collect();
+    report(matchup, &outcomes);
+    Ok(())
+}
+
+// The party lined up on one side of a room and the enemies on the
+// other, close enough to be seen. Party members whenever they have
+// nothing to fight go after the first enemy still standing, the way
+// a player focusing them down would.
+fn battle(game: &mut Headless, matchup: &Matchup) -> Outcome {
+    game.floor(&[[10, 10, 30, 20]]);
+    let party = line_up(game, &matchup.party, 22);
+    let enemies = line_up(game, &matchup.enemies, 26);
+
+    let mut reader = game.world.write_resource::<EventChannel<CombatEvent>>().register_reader();
+    // The tick each enemy was first fought on.
+    let mut engaged: HashMap<Entity, u64> = HashMap::new();
+    let mut kill_seconds = Vec::new();
+
+    let max_ticks = (TIME_LIMIT / TICK_SECONDS) as u64;
+    let mut won = false;
+    for _ in 0..max_ticks {
+        let standing_enemies: Vec<Entity> = enemies.iter().cloned().filter(|e| standing(game, *e)).collect();
+        if standing_enemies.is_empty() {
+            won = true;
+            break;
+        }
+        let standing_party: Vec<Entity> = party.iter().cloned().filter(|e| standing(game, *e)).collect();
+        if standing_party.is_empty() {
+            break;
+        }
+
+        for member in standing_party {
+            let target = game.world.read_storage::<CanTarget>().get(member).and_then(|t| t.target);
+            if !target.map_or(false, |target| standing(game, target)) {
+                game.order(&[member], RecordedOrder::Attack(game.marker(standing_enemies[0])));
+            }
+        }
+        game.step(1);
+
+        let tick = game.tick();
+        for event in game.world.read_resource::<EventChannel<CombatEvent>>().read(&mut reader) {
+            match event {
+                CombatEvent::AttackStarted { target, .. }
+                | CombatEvent::Hit { target, .. }
+                | CombatEvent::Miss { target, .. }
+                | CombatEvent::Damage { target, .. } => {
+                    if enemies.contains(target) {
+                        engaged.entry(*target).or_insert(tick);
+                    }
+                }
+                CombatEvent::Death { entity, .. } => {
+                    if let Some(start) = engaged.get(entity) {
+                        kill_seconds.push((tick - start) as f32 * TICK_SECONDS);
+                    }
+                }
+                CombatEvent::Heal { .. } => {}
+            }
+        }
+    }
+
+    let damage_taken = game.world.read_resource::<Run>().stats.damage_taken;
+    Outcome {
+        won,
+        kill_seconds,
+        damage_taken,
+        survivors: party.iter().map(|member| standing(game, *member)).collect(),
+    }
+}
+
+// Spawns the units in a column, two tiles apart, at tile column `x`.
+fn line_up(game: &mut Headless, ids: &[String], x: usize) -> Vec<Entity> {
+    let top = 20 + ids.len();
+    ids.iter()
+        .enumerate()
+        .map(|(i, id)| game.spawn(id, [x, top - i * 2]))
+        .collect()
+}
+
+fn standing(game: &Headless, entity: Entity) -> bool {
+    health(&game.world, entity) > 0.0 && !game.world.read_storage::<Downed>().contains(entity)
+}
+
+fn report(matchup: &Matchup, outcomes: &[Outcome]) {
+    let battles = outcomes.len() as f32;
+    let wins: Vec<&Outcome> = outcomes.iter().filter(|o| o.won).collect();
+    let average = |total: f32, count: f32| if count > 0.0 { total / count } else { 0.0 };
+
+    println!("win rate : {:.1}%", 100.0 * wins.len() as f32 / battles);
+    let kills: Vec<f32> = outcomes.iter().flat_map(|o| o.kill_seconds.iter().cloned()).collect();
+    println!(
+        "time to kill : {:.1}s",
+        average(kills.iter().sum(), kills.len() as f32)
+    );
+    println!(
+        "damage taken : {:.1}",
+        average(outcomes.iter().map(|o| o.damage_taken).sum(), battles)
+    );
+    println!(
+        "survivors : {:.2} of {}",
+        average(outcomes.iter().map(|o| o.survivors.iter().filter(|s| **s).count() as f32).sum(), battles),
+        matchup.party.len()
+    );
+    for (i, id) in matchup.party.iter().enumerate() {
+        let survived = outcomes.iter().filter(|o| o.survivors[i]).count() as f32;
+        println!("  {} survived : {:.1}%", id, 100.0 * survived / battles);
+    }
+}
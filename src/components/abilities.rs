@@ -0,0 +1,220 @@
+use amethyst::ecs::prelude::{Component, DenseVecStorage, Entity};
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::stats::Damage;
+use crate::components::status::{StatusEffect, StatusIcon};
+
+pub const HOTBAR_SLOTS: usize = 6;
+
+// How an ability picks what it affects.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum AbilityShape {
+    // A single unit, which has to be within range.
+    Targeted,
+    // Only the caster.
+    OnSelf,
+    // Every hostile unit within `radius` of a point in range.
+    Area {
+        radius: f32,
+    },
+    // Every hostile unit within range, in front of the caster,
+    // at most `half_angle` radians off the aimed direction.
+    Cone {
+        half_angle: f32,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AbilityEffect {
+    Damage(Damage),
+    // Fires a projectile at each affected unit instead of
+    // hurting it outright.
+    Projectile {
+        damage: Damage,
+        speed: f32,
+    },
+    Status(StatusEffect),
+    Heal(f32),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ability {
+    pub name: String,
+    pub shape: AbilityShape,
+    pub effect: AbilityEffect,
+    #[serde(default)]
+    pub range: f32,
+    #[serde(default)]
+    pub cooldown: f32,
+    #[serde(skip)]
+    pub cooldown_remaining: f32,
+    #[serde(default)]
+    pub mana_cost: f32,
+    #[serde(default)]
+    pub stamina_cost: f32,
+    #[serde(default)]
+    pub cast_time: f32,
+}
+
+impl Ability {
+    pub fn new(name: &str, shape: AbilityShape, effect: AbilityEffect, range: f32) -> Ability {
+        Ability {
+            name: name.to_string(),
+            shape: shape,
+            effect: effect,
+            range: range,
+            cooldown: 0.0,
+            cooldown_remaining: 0.0,
+            mana_cost: 0.0,
+            stamina_cost: 0.0,
+            cast_time: 0.0,
+        }
+    }
+
+    pub fn with_cooldown(mut self, cooldown: f32) -> Ability {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn with_costs(mut self, mana_cost: f32, stamina_cost: f32) -> Ability {
+        self.mana_cost = mana_cost;
+        self.stamina_cost = stamina_cost;
+        self
+    }
+
+    pub fn with_cast_time(mut self, cast_time: f32) -> Ability {
+        self.cast_time = cast_time;
+        self
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.cooldown_remaining <= 0.0
+    }
+
+    // Does this ability need a target or point picked
+    // before it can be used?
+    pub fn needs_aim(&self) -> bool {
+        self.shape != AbilityShape::OnSelf
+    }
+
+    // Heals and buffs land on the caster's allies
+    // rather than its enemies.
+    pub fn is_beneficial(&self) -> bool {
+        match &self.effect {
+            AbilityEffect::Heal(_) => true,
+            AbilityEffect::Status(effect) => match effect.icon() {
+                StatusIcon::Regeneration | StatusIcon::Shield | StatusIcon::Buff => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    // Cones are aimed in a direction rather than at a spot,
+    // so there is nothing to walk into range of.
+    pub fn needs_range(&self) -> bool {
+        match self.shape {
+            AbilityShape::Targeted | AbilityShape::Area { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AbilityAim {
+    Entity(Entity),
+    Point([f32; 2]),
+    Caster,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AbilityOrder {
+    pub slot: usize,
+    pub aim: AbilityAim,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Cast {
+    pub order: AbilityOrder,
+    pub elapsed: f32,
+}
+
+#[derive(Default)]
+pub struct Abilities {
+    pub slots: Vec<Ability>,
+    // Ordered but not started yet, usually because the
+    // caster is still walking into range.
+    pub queued: Option<AbilityOrder>,
+    pub casting: Option<Cast>,
+}
+
+impl Abilities {
+    pub fn new(slots: Vec<Ability>) -> Abilities {
+        Abilities {
+            slots: slots,
+            queued: None,
+            casting: None,
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.queued.is_some() || self.casting.is_some()
+    }
+
+    pub fn cancel(&mut self) {
+        self.queued = None;
+        self.casting = None;
+    }
+}
+
+impl Component for Abilities {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// Mana and stamina pools that abilities are paid from.
+pub struct Energy {
+    pub mana: f32,
+    pub max_mana: f32,
+    pub mana_regen: f32,
+    pub stamina: f32,
+    pub max_stamina: f32,
+    pub stamina_regen: f32,
+}
+
+impl Energy {
+    pub fn new(max_mana: f32, max_stamina: f32) -> Energy {
+        Energy {
+            mana: max_mana,
+            max_mana: max_mana,
+            mana_regen: 1.0,
+            stamina: max_stamina,
+            max_stamina: max_stamina,
+            stamina_regen: 5.0,
+        }
+    }
+
+    pub fn can_afford(&self, ability: &Ability) -> bool {
+        self.mana >= ability.mana_cost && self.stamina >= ability.stamina_cost
+    }
+
+    pub fn pay(&mut self, ability: &Ability) {
+        self.mana -= ability.mana_cost;
+        self.stamina -= ability.stamina_cost;
+    }
+
+    pub fn regenerate(&mut self, elapsed: f32) {
+        self.mana = (self.mana + self.mana_regen * elapsed).min(self.max_mana);
+        self.stamina = (self.stamina + self.stamina_regen * elapsed).min(self.max_stamina);
+    }
+}
+
+impl Component for Energy {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// The hotbar slot picked for the next click, if any.
+#[derive(Default)]
+pub struct PendingAbility {
+    pub slot: Option<usize>,
+}
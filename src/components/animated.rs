@@ -1,3 +1,5 @@
+use std::cmp;
+
 use amethyst::ecs::prelude::{Component, DenseVecStorage};
 
 use amethyst::renderer::SpriteRender;
@@ -22,6 +24,19 @@ impl Animation {
         }
     }
 
+    // Plays the animation through a single time, holding on the
+    // last frame rather than looping back to the start.
+    pub fn animate_once(&mut self, elapsed_time: f32, render: &mut SpriteRender) {
+        self.elapsed_time += elapsed_time;
+        let elapsed_frames = (self.elapsed_time / self.time_per_frame) as usize;
+        self.current_frame = cmp::min(elapsed_frames, self.frames - 1);
+        render.sprite_number = self.current_frame + self.start_sprite_index;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed_time >= self.time_per_frame * self.frames as f32
+    }
+
     pub fn reset(&mut self) {
         self.current_frame = 0;
         self.elapsed_time = 0.0;
@@ -80,6 +95,9 @@ impl Component for WalkAnimation {
 
 pub struct FightAnimation {
     pub anim: Animation,
+    // The frame of the swing on which the blow actually lands.
+    // Frames before it are the wind-up, frames after it the recovery.
+    pub impact_frame: usize,
 }
 
 impl Component for FightAnimation {
@@ -87,8 +105,36 @@ impl Component for FightAnimation {
 }
 
 impl FightAnimation {
-    pub fn new(start_sprite_index: usize, frames: usize, time_per_frame: f32) -> FightAnimation {
+    pub fn new(start_sprite_index: usize, frames: usize, time_per_frame: f32, impact_frame: usize) -> FightAnimation {
         FightAnimation {
+            anim: Animation {
+                start_sprite_index: start_sprite_index,
+                frames: frames,
+                current_frame: 0,
+                time_per_frame: time_per_frame,
+                elapsed_time: 0.0,
+                offset: 0,
+            },
+            impact_frame: impact_frame,
+        }
+    }
+
+    pub fn is_past_impact(&self) -> bool {
+        self.anim.current_frame >= self.impact_frame
+    }
+}
+
+pub struct DeathAnimation {
+    pub anim: Animation,
+}
+
+impl Component for DeathAnimation {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl DeathAnimation {
+    pub fn new(start_sprite_index: usize, frames: usize, time_per_frame: f32) -> DeathAnimation {
+        DeathAnimation {
             anim: Animation {
                 start_sprite_index: start_sprite_index,
                 frames: frames,
@@ -99,4 +145,4 @@ impl FightAnimation {
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,31 @@
+use amethyst::ecs::prelude::{Component, DenseVecStorage};
+
+// Where an entity was at the end of the last two simulation ticks.
+// Its transform is drawn somewhere between the two, so movement looks
+// smooth whatever the frame rate, while the simulation itself only
+// ever sees `current`.
+#[derive(Clone, Copy, Debug)]
+pub struct Interpolation {
+    pub previous: [f32; 2],
+    pub current: [f32; 2],
+}
+
+impl Interpolation {
+    pub fn at(coords: [f32; 2]) -> Self {
+        Interpolation {
+            previous: coords,
+            current: coords,
+        }
+    }
+
+    pub fn lerp(&self, alpha: f32) -> [f32; 2] {
+        [
+            self.previous[0] + (self.current[0] - self.previous[0]) * alpha,
+            self.previous[1] + (self.current[1] - self.previous[1]) * alpha,
+        ]
+    }
+}
+
+impl Component for Interpolation {
+    type Storage = DenseVecStorage<Self>;
+}
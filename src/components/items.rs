@@ -0,0 +1,269 @@
+use amethyst::ecs::prelude::{Component, DenseVecStorage};
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::components::npc::{Attackable, Attacker};
+use crate::components::stats::{Resistances, Stats};
+use crate::components::status::{StatModifier, StatusEffect};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum EquipSlot {
+    Weapon,
+    Offhand,
+    Head,
+    Body,
+    Feet,
+    Ring,
+}
+
+pub const EQUIP_SLOTS: [EquipSlot; 6] = [
+    EquipSlot::Weapon,
+    EquipSlot::Offhand,
+    EquipSlot::Head,
+    EquipSlot::Body,
+    EquipSlot::Feet,
+    EquipSlot::Ring,
+];
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Default for Rarity {
+    fn default() -> Rarity {
+        Rarity::Common
+    }
+}
+
+impl Rarity {
+    pub fn color(&self) -> [f32; 4] {
+        match self {
+            Rarity::Common => [0.9, 0.9, 0.9, 1.0],
+            Rarity::Uncommon => [0.3, 0.9, 0.3, 1.0],
+            Rarity::Rare => [0.3, 0.5, 1.0, 1.0],
+            Rarity::Epic => [0.7, 0.3, 0.9, 1.0],
+            Rarity::Legendary => [1.0, 0.6, 0.1, 1.0],
+        }
+    }
+
+    // How many affixes a generated item of this rarity rolls.
+    pub fn affix_count(&self) -> usize {
+        match self {
+            Rarity::Common => 0,
+            Rarity::Uncommon => 1,
+            _ => 2,
+        }
+    }
+
+    // How much stronger affixes roll on items of this rarity.
+    pub fn multiplier(&self) -> f32 {
+        match self {
+            Rarity::Common => 1.0,
+            Rarity::Uncommon => 1.0,
+            Rarity::Rare => 1.25,
+            Rarity::Epic => 1.6,
+            Rarity::Legendary => 2.0,
+        }
+    }
+
+    // How much more an item of this rarity is worth.
+    pub fn price_multiplier(&self) -> f32 {
+        match self {
+            Rarity::Common => 1.0,
+            Rarity::Uncommon => 1.5,
+            Rarity::Rare => 2.5,
+            Rarity::Epic => 4.0,
+            Rarity::Legendary => 7.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Consumable {
+    Heal(f32),
+    RestoreMana(f32),
+    Status(StatusEffect),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ItemKind {
+    Equipment(EquipSlot),
+    Consumable(Consumable),
+    // Opens whatever is locked with the same id.
+    Key(String),
+}
+
+// What an item adds to whoever has it equipped.
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ItemBonus {
+    pub attack: f32,
+    pub health: f32,
+    pub stats: StatModifier,
+    pub resistances: Resistances,
+}
+
+impl ItemBonus {
+    pub fn add(&mut self, other: &ItemBonus) {
+        self.attack += other.attack;
+        self.health += other.health;
+        self.stats.strength += other.stats.strength;
+        self.stats.dexterity += other.stats.dexterity;
+        self.stats.armor += other.stats.armor;
+        self.stats.evasion += other.stats.evasion;
+        self.stats.crit_chance += other.stats.crit_chance;
+        self.resistances.physical += other.resistances.physical;
+        self.resistances.fire += other.resistances.fire;
+        self.resistances.cold += other.resistances.cold;
+        self.resistances.lightning += other.resistances.lightning;
+        self.resistances.poison += other.resistances.poison;
+        self.resistances.holy += other.resistances.holy;
+    }
+
+    // Rough worth of the bonus in gold, used to price affixes.
+    pub fn value(&self) -> f32 {
+        let stats = &self.stats;
+        let resistances = &self.resistances;
+        self.attack.max(0.0) * 3.0
+            + self.health.max(0.0) * 0.5
+            + (stats.strength + stats.dexterity + stats.armor + stats.evasion).max(0.0) * 2.0
+            + stats.crit_chance.max(0.0) * 200.0
+            + (resistances.physical + resistances.fire + resistances.cold
+                + resistances.lightning + resistances.poison + resistances.holy).max(0.0) * 100.0
+    }
+
+    pub fn scaled(&self, factor: f32) -> ItemBonus {
+        let mut bonus = *self;
+        bonus.attack *= factor;
+        bonus.health *= factor;
+        bonus.stats.strength *= factor;
+        bonus.stats.dexterity *= factor;
+        bonus.stats.armor *= factor;
+        bonus.stats.evasion *= factor;
+        bonus.stats.crit_chance *= factor;
+        bonus.resistances.physical *= factor;
+        bonus.resistances.fire *= factor;
+        bonus.resistances.cold *= factor;
+        bonus.resistances.lightning *= factor;
+        bonus.resistances.poison *= factor;
+        bonus.resistances.holy *= factor;
+        bonus
+    }
+
+    // Adds the bonus to a unit, or takes it away again
+    // when `sign` is negative.
+    pub fn apply(
+        &self,
+        sign: f32,
+        attacker: Option<&mut Attacker>,
+        attackable: Option<&mut Attackable>,
+        stats: Option<&mut Stats>,
+        resistances: Option<&mut Resistances>,
+    ) {
+        if let Some(attacker) = attacker {
+            attacker.attack += self.attack * sign;
+        }
+        if let Some(attackable) = attackable {
+            attackable.total_health += self.health * sign;
+            attackable.health = attackable.health.min(attackable.total_health);
+        }
+        if let Some(stats) = stats {
+            self.stats.apply(stats, sign);
+        }
+        if let Some(resistances) = resistances {
+            resistances.physical += self.resistances.physical * sign;
+            resistances.fire += self.resistances.fire * sign;
+            resistances.cold += self.resistances.cold * sign;
+            resistances.lightning += self.resistances.lightning * sign;
+            resistances.poison += self.resistances.poison * sign;
+            resistances.holy += self.resistances.holy * sign;
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum AffixPosition {
+    Prefix,
+    Suffix,
+}
+
+// An extra property rolled onto an item, e.g. the "Flaming"
+// in "Flaming Axe" or the "of the Bear" in "Axe of the Bear".
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Affix {
+    pub name: String,
+    pub position: AffixPosition,
+    #[serde(default)]
+    pub bonus: ItemBonus,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Item {
+    pub name: String,
+    pub kind: ItemKind,
+    #[serde(default)]
+    pub rarity: Rarity,
+    #[serde(default)]
+    pub bonus: ItemBonus,
+    #[serde(default)]
+    pub affixes: Vec<Affix>,
+    // Price in gold before any markup.
+    #[serde(default)]
+    pub value: u32,
+}
+
+impl Item {
+    // The item's name with its affixes, e.g. "Flaming Axe of the Bear".
+    pub fn display_name(&self) -> String {
+        let mut name = String::new();
+        for affix in self.affixes.iter().filter(|a| a.position == AffixPosition::Prefix) {
+            name.push_str(&affix.name);
+            name.push(' ');
+        }
+        name.push_str(&self.name);
+        for affix in self.affixes.iter().filter(|a| a.position == AffixPosition::Suffix) {
+            name.push(' ');
+            name.push_str(&affix.name);
+        }
+        name
+    }
+
+    // The item's own bonus plus all of its affixes.
+    pub fn total_bonus(&self) -> ItemBonus {
+        let mut bonus = self.bonus;
+        for affix in self.affixes.iter() {
+            bonus.add(&affix.bonus);
+        }
+        bonus
+    }
+
+    // What a merchant charges for the item: its base value plus
+    // whatever its affixes are worth, marked up by rarity.
+    pub fn price(&self) -> u32 {
+        let affixes: f32 = self.affixes.iter().map(|a| a.bonus.value()).sum();
+        ((self.value as f32 + affixes) * self.rarity.price_multiplier()).round() as u32
+    }
+
+    pub fn equip_slot(&self) -> Option<EquipSlot> {
+        match self.kind {
+            ItemKind::Equipment(slot) => Some(slot),
+            _ => None,
+        }
+    }
+}
+
+// What a character is wearing and wielding.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct Equipment {
+    pub slots: HashMap<EquipSlot, Item>,
+}
+
+impl Component for Equipment {
+    type Storage = DenseVecStorage<Self>;
+}
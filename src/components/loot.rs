@@ -0,0 +1,99 @@
+use amethyst::{
+    assets::Handle,
+    core::transform::Transform,
+    ecs::prelude::{Component, DenseVecStorage, Entity},
+    ecs::saveload::MarkedBuilder,
+    prelude::*,
+    renderer::{SpriteRender, SpriteSheet},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::items::{EquipSlot, Item, ItemKind};
+use crate::components::Layered;
+use crate::components::save::Persistent;
+
+// Sprite sheet shared by everything lying on the floor.
+pub struct LootSheet(pub Handle<SpriteSheet>);
+
+// Frames in the loot sprite sheet.
+pub const GOLD_SPRITE: usize = 0;
+pub const WEAPON_SPRITE: usize = 1;
+pub const ARMOR_SPRITE: usize = 2;
+pub const CONSUMABLE_SPRITE: usize = 3;
+pub const KEY_SPRITE: usize = 4;
+pub const CHEST_CLOSED_SPRITE: usize = 5;
+pub const CHEST_OPEN_SPRITE: usize = 6;
+pub const MERCHANT_SPRITE: usize = 7;
+pub const STAIRS_SPRITE: usize = 8;
+
+// Something on the floor the party can be sent to pick up.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Pickup {
+    pub item: Option<Item>,
+    pub gold: u32,
+}
+
+impl Pickup {
+    pub fn sprite_number(&self) -> usize {
+        match &self.item {
+            None => GOLD_SPRITE,
+            Some(item) => match &item.kind {
+                ItemKind::Equipment(EquipSlot::Weapon) => WEAPON_SPRITE,
+                ItemKind::Equipment(_) => ARMOR_SPRITE,
+                ItemKind::Consumable(_) => CONSUMABLE_SPRITE,
+                ItemKind::Key(_) => KEY_SPRITE,
+            },
+        }
+    }
+}
+
+impl Component for Pickup {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A party member on their way to pick something up.
+pub struct Collecting {
+    pub pickup: Entity,
+}
+
+impl Component for Collecting {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A chest that spills out loot from `loot_table` when the party
+// walks up to it. Locked chests need the matching key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chest {
+    pub loot_table: String,
+    pub key: Option<String>,
+    pub opened: bool,
+}
+
+impl Component for Chest {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn initialize_chest(
+    world: &mut World,
+    sprite_sheet_handle: Handle<SpriteSheet>,
+    chest: Chest,
+    coords: [f32; 2],
+) -> Entity {
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(coords[0], coords[1], 0.75);
+
+    let render = SpriteRender {
+        sprite_sheet: sprite_sheet_handle,
+        sprite_number: CHEST_CLOSED_SPRITE,
+    };
+
+    world
+        .create_entity()
+        .with(render)
+        .with(transform)
+        .with(chest)
+        .with(Layered)
+        .marked::<Persistent>()
+        .build()
+}
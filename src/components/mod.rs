@@ -4,6 +4,15 @@ pub mod npc;
 pub mod tile;
 pub mod animated;
 pub mod scenary;
+pub mod stats;
+pub mod projectile;
+pub mod abilities;
+pub mod status;
+pub mod progression;
+pub mod items;
+pub mod loot;
+pub mod save;
+pub mod interpolation;
 
 // component for determining if entities are layered
 // across each other
@@ -1,15 +1,26 @@
 use amethyst::{
     assets::{Handle},
     core::transform::{Parent, Transform},
+    core::Hidden,
     ecs::prelude::{Entity, Component, DenseVecStorage},
+    ecs::saveload::MarkedBuilder,
     prelude::*,
     renderer::{SpriteRender, SpriteSheet},
 };
 
 use rand::Rng;
 
-use crate::components::animated::{IdleAnimation, WalkAnimation, FightAnimation};
+use crate::components::animated::{IdleAnimation, WalkAnimation, FightAnimation, DeathAnimation};
 use crate::components::Layered;
+use crate::components::interpolation::Interpolation;
+use crate::components::stats::DamageType;
+use crate::components::projectile::AttackKind;
+use crate::components::abilities::{Abilities, Energy};
+use crate::components::items::Equipment;
+use crate::components::progression::Experience;
+use crate::components::status::{StatusEffects, StatusIconSlot, STATUS_ICON_SLOTS};
+use crate::resources::archetypes::{AiProfile, Archetype};
+use crate::components::save::Persistent;
 
 pub struct HealthBar;
 
@@ -32,16 +43,95 @@ impl Component for Attackable {
     type Storage = DenseVecStorage<Self>;
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AttackPhase {
+    // Not swinging, waiting for the cooldown to run out.
+    Ready,
+    // Swinging, but the blow has not landed yet.
+    WindUp,
+    // The blow has landed and the swing is playing out.
+    Recovery,
+}
+
 pub struct Attacker {
     pub attack: f32,
-    pub attack_speed: u64,
+    pub damage_type: DamageType,
+    pub kind: AttackKind,
+    // Seconds to wait between the end of one swing
+    // and the start of the next.
+    pub attack_cooldown: f32,
     pub attack_range: f32,
+    pub cooldown_remaining: f32,
+    pub phase: AttackPhase,
+}
+
+impl Attacker {
+    pub fn new(attack: f32, attack_cooldown: f32, attack_range: f32) -> Attacker {
+        Attacker {
+            attack: attack,
+            damage_type: DamageType::Physical,
+            kind: AttackKind::Melee,
+            attack_cooldown: attack_cooldown,
+            attack_range: attack_range,
+            cooldown_remaining: 0.0,
+            phase: AttackPhase::Ready,
+        }
+    }
+
+    pub fn with_damage_type(mut self, damage_type: DamageType) -> Attacker {
+        self.damage_type = damage_type;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: AttackKind) -> Attacker {
+        self.kind = kind;
+        self
+    }
+
+    pub fn is_swinging(&self) -> bool {
+        self.phase != AttackPhase::Ready
+    }
+
+    // Abandons a swing that has not finished, e.g. because
+    // the target walked out of range. The cooldown is not
+    // started for a swing that never landed.
+    pub fn interrupt(&mut self) {
+        if self.phase == AttackPhase::Recovery {
+            self.cooldown_remaining = self.attack_cooldown;
+        }
+        self.phase = AttackPhase::Ready;
+    }
 }
 
 impl Component for Attacker {
     type Storage = DenseVecStorage<Self>;
 }
 
+// Slowly heals a unit once it has been out of combat for a while.
+pub struct Recovery {
+    pub per_second: f32,
+    // Seconds without attacking or being attacked
+    // before recovery kicks in.
+    pub delay: f32,
+    pub since_combat: f32,
+    pub tick_timer: f32,
+}
+
+impl Recovery {
+    pub fn new(per_second: f32, delay: f32) -> Recovery {
+        Recovery {
+            per_second: per_second,
+            delay: delay,
+            since_combat: 0.0,
+            tick_timer: 0.0,
+        }
+    }
+}
+
+impl Component for Recovery {
+    type Storage = DenseVecStorage<Self>;
+}
+
 #[derive(Default)]
 pub struct CanTarget {
     pub target: Option<Entity>,
@@ -51,6 +141,70 @@ impl Component for CanTarget {
     type Storage = DenseVecStorage<Self>;
 }
 
+// A unit that has been killed and is now a corpse.
+// Corpses stay on the floor for a while before being cleaned up.
+#[derive(Default)]
+pub struct Dead {
+    pub elapsed: f32,
+}
+
+impl Component for Dead {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A party member at zero health. Unlike enemies they are not
+// removed from the world, they stay down until revived.
+#[derive(Default)]
+pub struct Downed;
+
+impl Component for Downed {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A unit in a turn-based encounter whose turn it is not. The
+// real-time systems leave it be until its turn comes around.
+#[derive(Default)]
+pub struct Waiting;
+
+impl Component for Waiting {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// The leader of a floor. Killing them opens the stairs down,
+// or wins the run on the last floor.
+pub struct Boss;
+
+impl Component for Boss {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// Which archetype a unit was spawned from.
+pub struct ArchetypeId(pub String);
+
+impl Component for ArchetypeId {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A would-be party member found in the dungeon, waiting
+// for the party to come and talk to them.
+#[derive(Default)]
+pub struct Recruitable;
+
+impl Component for Recruitable {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A party member on their way to recruit someone.
+pub struct Recruiting {
+    pub target: Entity,
+    // Where the recruiter was sent, so a new move order can be told apart.
+    pub move_coords: [f32; 2],
+}
+
+impl Component for Recruiting {
+    type Storage = DenseVecStorage<Self>;
+}
+
 #[derive(Default)]
 pub struct PlayerControlled;
 
@@ -74,11 +228,6 @@ impl Component for Enemy {
     type Storage = DenseVecStorage<Self>;
 }
 
-pub enum NpcVariant {
-    Normal,
-    Orc
-}
-
 #[derive(Clone, Copy)]
 pub struct Npc {
     pub move_coords: [f32; 2],
@@ -102,124 +251,110 @@ impl Default for Npc {
     }
 }
 
-impl From<&NpcVariant> for Npc {
-    fn from(variant: &NpcVariant) -> Npc {
-        match variant {
-            NpcVariant::Normal => {
-                Npc {
-                    move_coords: [0.0, 0.0],
-                    velocity: [0.0, 0.0],
-                    move_speed: 100.0,
-                    selected: true,
-                }
-            },
-            _ => {
-                Npc {
-                    move_coords: [0.0, 0.0],
-                    velocity: [0.0, 0.0],
-                    move_speed: 50.0,
-                    selected: false,
-                }
-            }
-        }
-    }
-}
-
 pub fn initialize_npc(
     world: &mut World, 
-    variant: NpcVariant, 
+    archetype: &Archetype, 
     sprite_sheet_handle: Handle<SpriteSheet>, 
     aura_handle: Handle<SpriteSheet>,
     health_bar_handle: Handle<SpriteSheet>,
+    status_icons_handle: Handle<SpriteSheet>,
     coords: [f32; 2]
-) {
+) -> Entity {
     let mut transform = Transform::default();
     transform.set_translation_xyz(coords[0], coords[1], 0.5);
 
-    let npc = Npc::from(&variant);
+    let npc = Npc {
+        move_coords: [0.0, 0.0],
+        velocity: [0.0, 0.0],
+        move_speed: archetype.move_speed,
+        // Party members start out selected.
+        selected: archetype.ai == AiProfile::Party,
+    };
 
-    let frame_start = rand::thread_rng().gen_range(0, 20);
+    let mut rng = rand::thread_rng();
+    let frame_start = rng.gen_range(0, archetype.idle.frames);
+    let sprite_index = match archetype.sprite_variants.len() {
+        0 => 0,
+        n => archetype.sprite_variants[rng.gen_range(0, n)],
+    };
 
-    let entity = match variant {
-        NpcVariant::Normal => {
-            let sprite_render = SpriteRender {
-                sprite_sheet: sprite_sheet_handle,
-                sprite_number: 0,
-            };
+    let sprite_render = SpriteRender {
+        sprite_sheet: sprite_sheet_handle,
+        sprite_number: sprite_index + archetype.idle.start,
+    };
 
-            world
-                .create_entity()
-                .with(sprite_render)
-                .with(npc.clone())
+    let attack = &archetype.attack;
+    let (idle, walk, fight, death) = (&archetype.idle, &archetype.walk, &archetype.fight, &archetype.death);
+
+    let mut builder = world
+        .create_entity()
+        .with(sprite_render)
+        .with(npc.clone())
+        .with(ArchetypeId(archetype.id.clone()))
+        .with(CanTarget::default())
+        .with(
+            Attacker::new(attack.damage, attack.cooldown, attack.range)
+                .with_damage_type(attack.damage_type)
+                .with_kind(attack.kind)
+        )
+        .with(archetype.stats)
+        .with(archetype.resistances)
+        .with(Attackable {
+            health: archetype.health,
+            total_health: archetype.health,
+        })
+        .with(IdleAnimation::new(sprite_index + idle.start, idle.frames, idle.time_per_frame, frame_start))
+        .with(WalkAnimation::new(sprite_index + walk.start, walk.frames, walk.time_per_frame))
+        .with(FightAnimation::new(sprite_index + fight.start, fight.frames, fight.time_per_frame, fight.impact_frame))
+        .with(DeathAnimation::new(sprite_index + death.start, death.frames, death.time_per_frame))
+        .with(transform)
+        .with(Interpolation::at(coords))
+        .with(StatusEffects::default())
+        .with(Layered)
+        .marked::<Persistent>();
+
+    builder = match archetype.ai {
+        AiProfile::Party => {
+            builder
                 .with(PlayerControlled::default())
-                .with(CanTarget::default())
-                .with(Attacker {
-                    attack: 20.0,
-                    attack_speed: 30,
-                    attack_range: 25.0,
-                })
-                .with(Attackable {
-                    health: 150.0,
-                    total_health: 150.0,
-                })
-                .with(IdleAnimation::new(0, 20, 0.3, frame_start))
-                .with(WalkAnimation::new(20, 10, 0.1))
-                .with(FightAnimation::new(30, 10, 0.1))
-                .with(transform.clone())
-                .with(Layered)
-                .build()
+                .with(Experience::default())
+                .with(Equipment::default())
         },
-        NpcVariant::Orc => {
-            let mut sprite_index = 0;
-
-            if rand::random() {
-                sprite_index += 50;
-            }
-
-            let sprite_render = SpriteRender {
-                sprite_sheet: sprite_sheet_handle,
-                sprite_number: sprite_index,
-            };
-
-            world
-                .create_entity()
-                .with(sprite_render)
-                .with(npc.clone())
-                .with(Enemy::default())
-                .with(CanTarget::default())
-                .with(Attacker {
-                    attack: 5.0,
-                    attack_speed: 30,
-                    attack_range: 25.0,
-                })
-                .with(Attackable {
-                    health: 50.0,
-                    total_health: 50.0,
-                })
-                .with(IdleAnimation::new(sprite_index + 0, 20, 0.3, frame_start))
-                .with(WalkAnimation::new(sprite_index + 20, 10, 0.1))
-                .with(FightAnimation::new(sprite_index + 30, 10, 0.1))
-                .with(transform.clone())
-                .with(Layered)
-                .build()
-        }
+        AiProfile::Hostile { fov_radius } => builder.with(Enemy { fov_radius }),
     };
+    if !archetype.abilities.is_empty() {
+        builder = builder.with(Abilities::new(archetype.abilities.clone()));
+    }
+    if let Some(energy) = &archetype.energy {
+        builder = builder.with(Energy::new(energy.mana, energy.stamina));
+    }
+    if let Some(recovery) = &archetype.recovery {
+        builder = builder.with(Recovery::new(recovery.per_second, recovery.delay));
+    }
+
+    let entity = builder.build();
 
     let aura_sprite = SpriteRender {
         sprite_sheet: aura_handle,
         sprite_number: 0,
     };
 
-    if npc.selected {
+    // Anyone who can join the party gets a selection aura,
+    // shown only while they are selected.
+    if archetype.ai == AiProfile::Party {
         let mut aura_transform = Transform::default();
         aura_transform.prepend_translation_y(-14.0);
         aura_transform.prepend_translation_z(-0.1);
 
-        world.create_entity()
+        let mut aura = world.create_entity()
+            .with(SelectAura)
             .with(aura_sprite)
             .with(aura_transform)
-            .with(Parent { entity })
-            .build();
+            .with(Parent { entity });
+        if !npc.selected {
+            aura = aura.with(Hidden);
+        }
+        aura.build();
     }
 
     let health_bar_outer_sprite = SpriteRender {
@@ -248,4 +383,25 @@ pub fn initialize_npc(
         .with(health_bar_transform)
         .with(Parent { entity })
         .build();
-}
\ No newline at end of file
+
+    // Status effect icons sit in a row above the health bar,
+    // hidden until there is something to show.
+    for slot in 0..STATUS_ICON_SLOTS {
+        let mut icon_transform = Transform::default();
+        let x = (slot as f32 - (STATUS_ICON_SLOTS as f32 - 1.0) * 0.5) * 9.0;
+        icon_transform.set_translation_xyz(x, 28.0, 0.02);
+
+        world.create_entity()
+            .with(StatusIconSlot { slot })
+            .with(SpriteRender {
+                sprite_sheet: status_icons_handle.clone(),
+                sprite_number: 0,
+            })
+            .with(icon_transform)
+            .with(Hidden)
+            .with(Parent { entity })
+            .build();
+    }
+
+    entity
+}
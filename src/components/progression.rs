@@ -0,0 +1,141 @@
+use amethyst::ecs::prelude::{Component, DenseVecStorage};
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::abilities::Ability;
+use crate::components::status::StatModifier;
+
+// Experience needed to go from level 1 to 2. Every level after
+// that takes this much more than the one before.
+pub const BASE_LEVEL_XP: u32 = 100;
+pub const MAX_LEVEL: u32 = 20;
+
+// Experience needed to go from `level` to the next one.
+pub fn xp_to_next_level(level: u32) -> u32 {
+    BASE_LEVEL_XP * level
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Experience {
+    pub level: u32,
+    // Experience towards the next level.
+    pub xp: u32,
+    pub talent_points: u32,
+    // Ids of the talents that have been picked.
+    pub talents: Vec<String>,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Experience {
+            level: 1,
+            xp: 0,
+            talent_points: 0,
+            talents: vec![],
+        }
+    }
+}
+
+impl Experience {
+    // Adds experience, levelling up as many times as it pays for.
+    // Returns how many levels were gained.
+    pub fn add_xp(&mut self, amount: u32) -> u32 {
+        if self.level >= MAX_LEVEL {
+            return 0;
+        }
+
+        self.xp += amount;
+        let mut gained = 0;
+        while self.level < MAX_LEVEL && self.xp >= xp_to_next_level(self.level) {
+            self.xp -= xp_to_next_level(self.level);
+            self.level += 1;
+            self.talent_points += 1;
+            gained += 1;
+        }
+        if self.level >= MAX_LEVEL {
+            self.xp = 0;
+        }
+        gained
+    }
+
+    pub fn has_talent(&self, id: &str) -> bool {
+        self.talents.iter().any(|t| t == id)
+    }
+
+    // Can `talent` be picked right now?
+    pub fn can_unlock(&self, talent: &Talent) -> bool {
+        self.talent_points > 0
+            && self.level >= talent.level
+            && !self.has_talent(&talent.id)
+            && talent.requires.iter().all(|r| self.has_talent(r))
+    }
+}
+
+impl Component for Experience {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// What a class gains every time it levels up.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct StatGrowth {
+    pub health: f32,
+    pub stats: StatModifier,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum TalentEffect {
+    // Adds an ability to the hotbar.
+    Ability(Ability),
+    // Permanent bonuses to the unit's stats.
+    Passive(StatModifier),
+    MaxHealth(f32),
+}
+
+// A node in a class's talent tree.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Talent {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    // Level the unit has to be before the talent can be picked.
+    #[serde(default = "default_talent_level")]
+    pub level: u32,
+    // Talents that have to be picked first.
+    #[serde(default)]
+    pub requires: Vec<String>,
+    pub effect: TalentEffect,
+}
+
+fn default_talent_level() -> u32 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_take_more_xp_each_time() {
+        let mut experience = Experience::default();
+        assert_eq!(experience.add_xp(BASE_LEVEL_XP - 1), 0);
+        assert_eq!(experience.add_xp(1), 1);
+        assert_eq!(experience.level, 2);
+        assert_eq!(experience.xp, 0);
+
+        // Enough for levels 2 -> 3 -> 4 with some left over.
+        assert_eq!(experience.add_xp(xp_to_next_level(2) + xp_to_next_level(3) + 5), 2);
+        assert_eq!(experience.level, 4);
+        assert_eq!(experience.xp, 5);
+        assert_eq!(experience.talent_points, 3);
+    }
+
+    #[test]
+    fn no_levels_past_the_cap() {
+        let mut experience = Experience { level: MAX_LEVEL, ..Experience::default() };
+        assert_eq!(experience.add_xp(100_000), 0);
+        assert_eq!(experience.level, MAX_LEVEL);
+        assert_eq!(experience.xp, 0);
+    }
+}
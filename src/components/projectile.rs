@@ -0,0 +1,82 @@
+use amethyst::{
+    assets::Handle,
+    ecs::prelude::{Component, DenseVecStorage, Entity},
+    renderer::SpriteSheet,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::stats::{Damage, DamageType, Stats};
+use crate::systems::commands::calc_velocity_vec;
+
+// How close a projectile has to get to a unit to hit it.
+pub const PROJECTILE_HIT_RADIUS: f32 = 10.0;
+
+// Sprite sheet shared by every projectile in flight.
+pub struct ProjectileSheet(pub Handle<SpriteSheet>);
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum AttackKind {
+    // Damage lands directly on the target at the impact frame.
+    Melee,
+    // A projectile is fired at the impact frame and has to
+    // travel to the target before doing any damage.
+    Ranged {
+        projectile_speed: f32,
+    },
+}
+
+impl Default for AttackKind {
+    fn default() -> AttackKind {
+        AttackKind::Melee
+    }
+}
+
+pub struct Projectile {
+    pub source: Entity,
+    // Snapshot of the shooter's stats when it fired, so the
+    // projectile still resolves if the shooter dies mid-flight.
+    pub source_stats: Stats,
+    pub damage: Damage,
+    // Projectiles fired by enemies only hit party members
+    // and the other way around.
+    pub fired_by_enemy: bool,
+    pub velocity: [f32; 2],
+    pub time_to_live: f32,
+}
+
+impl Component for Projectile {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Projectile {
+    pub fn new(source: Entity, source_stats: Stats, damage: Damage, fired_by_enemy: bool) -> Projectile {
+        Projectile {
+            source: source,
+            source_stats: source_stats,
+            damage: damage,
+            fired_by_enemy: fired_by_enemy,
+            velocity: [0.0, 0.0],
+            time_to_live: 0.0,
+        }
+    }
+
+    // Sends the projectile from `from` towards `to`. It keeps flying in a
+    // straight line past the aim point, giving up a little beyond `range`.
+    pub fn aimed(mut self, from: [f32; 2], to: [f32; 2], speed: f32, range: f32) -> Projectile {
+        self.velocity = calc_velocity_vec(from, to, speed);
+        self.time_to_live = (range * 1.5) / speed;
+        self
+    }
+
+    pub fn angle(&self) -> f32 {
+        self.velocity[1].atan2(self.velocity[0])
+    }
+
+    pub fn sprite_number(&self) -> usize {
+        match self.damage.damage_type {
+            DamageType::Physical => 0,
+            _ => 1,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use amethyst::ecs::{
+    error::NoError,
+    prelude::{Component, DenseVecStorage, Entity, ReadStorage},
+    saveload::{ConvertSaveload, Marker, SimpleMarker},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::loot::{Chest, Pickup};
+use crate::resources::party::SavedMember;
+
+// Tags everything on a floor that goes into a save file. Entities are
+// written out by marker rather than by id, which is what lets references
+// between them, like a unit's target, be put back together on load.
+pub struct SaveMarker;
+
+pub type Persistent = SimpleMarker<SaveMarker>;
+
+// What `entity` is known as in saves and replays, or 0 if it is
+// not saved. Unlike the entity itself it is the same every time
+// a run is played from its seed.
+pub fn marker_id(markers: &ReadStorage<Persistent>, entity: Entity) -> u64 {
+    markers.get(entity).map_or(0, |marker| marker.id())
+}
+
+// A unit as it was when the game was saved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UnitState {
+    pub archetype: String,
+    pub member: SavedMember,
+    pub selected: bool,
+    pub player_controlled: bool,
+    pub recruitable: bool,
+    pub downed: bool,
+    #[serde(default)]
+    pub boss: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PersistedKind {
+    Unit(UnitState),
+    Chest(Chest),
+    Pickup(Pickup),
+    CampFire {
+        rested: bool,
+    },
+    Merchant,
+    Stairs,
+}
+
+// What is saved of a marked entity. It only exists while a game is being
+// saved or loaded, everything else about the entity is rebuilt from it.
+pub struct Persisted {
+    pub position: [f32; 2],
+    pub kind: PersistedKind,
+    // Who the unit was attacking.
+    pub target: Option<Entity>,
+    // Who the unit was on its way to recruit, and where it was sent.
+    pub recruiting: Option<(Entity, [f32; 2])>,
+}
+
+impl Component for Persisted {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// `Persisted` as it is written to the file, with markers in place of entities.
+#[derive(Serialize, Deserialize)]
+pub struct PersistedData<M> {
+    pub position: [f32; 2],
+    pub kind: PersistedKind,
+    pub target: Option<M>,
+    pub recruiting: Option<(M, [f32; 2])>,
+}
+
+impl<M: Marker> ConvertSaveload<M> for Persisted {
+    type Data = PersistedData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        Ok(PersistedData {
+            position: self.position,
+            kind: self.kind.clone(),
+            target: self.target.and_then(|target| ids(target)),
+            recruiting: self.recruiting.and_then(|(target, coords)| ids(target).map(|m| (m, coords))),
+        })
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Ok(Persisted {
+            position: data.position,
+            kind: data.kind,
+            target: data.target.and_then(|target| ids(target)),
+            recruiting: data.recruiting.and_then(|(target, coords)| ids(target).map(|e| (e, coords))),
+        })
+    }
+}
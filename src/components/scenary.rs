@@ -2,7 +2,8 @@ use amethyst::{
     assets::{Handle},
     core::transform::Transform,
     core::math::Vector3,
-    ecs::prelude::{Component, DenseVecStorage},
+    ecs::prelude::{Component, DenseVecStorage, Entity},
+    ecs::saveload::MarkedBuilder,
     prelude::*,
     renderer::{SpriteRender, SpriteSheet},
 };
@@ -10,9 +11,16 @@ use amethyst::{
 use rand::Rng;
 
 use crate::components::animated::IdleAnimation;
+use crate::components::loot::{MERCHANT_SPRITE, STAIRS_SPRITE};
 use crate::components::Layered;
+use crate::components::save::Persistent;
 
-pub struct CampFire;
+// A campfire the party can rest at once the enemies
+// gathered around it have been dealt with.
+#[derive(Default)]
+pub struct CampFire {
+    pub rested: bool,
+}
 
 impl Component for CampFire {
     type Storage = DenseVecStorage<Self>;
@@ -22,7 +30,7 @@ pub fn initialize_campfire(
     world: &mut World,
     sprite_sheet_handle: Handle<SpriteSheet>,
     coords: [f32; 2],
-) {
+) -> Entity {
     let mut transform = Transform::default();
     transform.set_translation_xyz(coords[0], coords[1], 0.75);
     transform.set_scale(Vector3::new(0.5, 0.5, 0.0));
@@ -39,6 +47,68 @@ pub fn initialize_campfire(
         .with(render)
         .with(transform)
         .with(IdleAnimation::new(0, 4, 0.2, frame_start))
+        .with(CampFire::default())
+        .with(Layered)
+        .marked::<Persistent>()
+        .build()
+}
+// A travelling merchant who sets up shop in the safe
+// room of every floor.
+pub struct Merchant;
+
+impl Component for Merchant {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn initialize_merchant(
+    world: &mut World,
+    sprite_sheet_handle: Handle<SpriteSheet>,
+    coords: [f32; 2],
+) -> Entity {
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(coords[0], coords[1], 0.75);
+
+    let render = SpriteRender {
+        sprite_sheet: sprite_sheet_handle,
+        sprite_number: MERCHANT_SPRITE,
+    };
+
+    world
+        .create_entity()
+        .with(render)
+        .with(transform)
+        .with(Merchant)
         .with(Layered)
-        .build();
-}
\ No newline at end of file
+        .marked::<Persistent>()
+        .build()
+}
+
+// The way down to the next floor, open once the floor's boss is dead.
+pub struct Stairs;
+
+impl Component for Stairs {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn initialize_stairs(
+    world: &mut World,
+    sprite_sheet_handle: Handle<SpriteSheet>,
+    coords: [f32; 2],
+) -> Entity {
+    let mut transform = Transform::default();
+    // Flat on the floor, under everything that walks over it.
+    transform.set_translation_xyz(coords[0], coords[1], 0.4);
+
+    let render = SpriteRender {
+        sprite_sheet: sprite_sheet_handle,
+        sprite_number: STAIRS_SPRITE,
+    };
+
+    world
+        .create_entity()
+        .with(render)
+        .with(transform)
+        .with(Stairs)
+        .marked::<Persistent>()
+        .build()
+}
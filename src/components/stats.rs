@@ -0,0 +1,310 @@
+use amethyst::ecs::prelude::{Component, DenseVecStorage};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Chance to land a blow between two units of equal
+// dexterity and evasion.
+pub const BASE_HIT_CHANCE: f32 = 0.9;
+pub const MIN_HIT_CHANCE: f32 = 0.05;
+pub const MAX_HIT_CHANCE: f32 = 0.95;
+// How much each point of dexterity over the defender's
+// evasion shifts the hit chance.
+pub const HIT_CHANCE_PER_POINT: f32 = 0.01;
+// Physical damage bonus per point of strength.
+pub const STRENGTH_BONUS_PER_POINT: f32 = 0.02;
+// Armor gives diminishing returns: this much armor halves
+// incoming physical damage.
+pub const ARMOR_HALVING_POINT: f32 = 100.0;
+// Resistances can be negative (a weakness) but never
+// make a unit fully immune.
+pub const MIN_RESISTANCE: f32 = -1.0;
+pub const MAX_RESISTANCE: f32 = 0.75;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Cold,
+    Lightning,
+    Poison,
+    Holy,
+}
+
+impl Default for DamageType {
+    fn default() -> DamageType {
+        DamageType::Physical
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub strength: f32,
+    pub dexterity: f32,
+    pub armor: f32,
+    pub evasion: f32,
+    // 0.0 - 1.0 chance for a landed hit to be critical.
+    pub crit_chance: f32,
+    pub crit_multiplier: f32,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            strength: 0.0,
+            dexterity: 0.0,
+            armor: 0.0,
+            evasion: 0.0,
+            crit_chance: 0.0,
+            crit_multiplier: 1.5,
+        }
+    }
+}
+
+impl Component for Stats {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// Fraction of incoming damage of each type that is ignored.
+#[derive(Default, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Resistances {
+    pub physical: f32,
+    pub fire: f32,
+    pub cold: f32,
+    pub lightning: f32,
+    pub poison: f32,
+    pub holy: f32,
+}
+
+impl Resistances {
+    pub fn against(&self, damage_type: DamageType) -> f32 {
+        let resistance = match damage_type {
+            DamageType::Physical => self.physical,
+            DamageType::Fire => self.fire,
+            DamageType::Cold => self.cold,
+            DamageType::Lightning => self.lightning,
+            DamageType::Poison => self.poison,
+            DamageType::Holy => self.holy,
+        };
+        clamp(resistance, MIN_RESISTANCE, MAX_RESISTANCE)
+    }
+}
+
+impl Component for Resistances {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Damage {
+    pub amount: f32,
+    pub damage_type: DamageType,
+}
+
+impl Damage {
+    pub fn new(amount: f32, damage_type: DamageType) -> Damage {
+        Damage {
+            amount: amount,
+            damage_type: damage_type,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DamageOutcome {
+    Miss,
+    Hit {
+        amount: f32,
+        critical: bool,
+    },
+}
+
+pub fn hit_chance(attacker: &Stats, defender: &Stats) -> f32 {
+    let chance = BASE_HIT_CHANCE + (attacker.dexterity - defender.evasion) * HIT_CHANCE_PER_POINT;
+    clamp(chance, MIN_HIT_CHANCE, MAX_HIT_CHANCE)
+}
+
+// Fraction of physical damage absorbed by a given amount of armor.
+pub fn armor_reduction(armor: f32) -> f32 {
+    let armor = armor.max(0.0);
+    armor / (armor + ARMOR_HALVING_POINT)
+}
+
+// Damage after the attacker's bonuses and the defender's mitigation,
+// before any hit or crit roll.
+pub fn mitigated_damage(
+    damage: Damage,
+    attacker: &Stats,
+    defender: &Stats,
+    resistances: &Resistances,
+) -> f32 {
+    let mut amount = damage.amount;
+    if damage.damage_type == DamageType::Physical {
+        amount *= 1.0 + attacker.strength.max(0.0) * STRENGTH_BONUS_PER_POINT;
+        amount *= 1.0 - armor_reduction(defender.armor);
+    }
+    amount *= 1.0 - resistances.against(damage.damage_type);
+    amount.max(0.0)
+}
+
+// The single place damage gets worked out. Every attack source
+// (melee swings, projectiles, abilities, ticking effects) should
+// go through here so that the same rules apply everywhere.
+pub fn resolve_damage<R: Rng>(
+    rng: &mut R,
+    damage: Damage,
+    attacker: &Stats,
+    defender: &Stats,
+    resistances: &Resistances,
+) -> DamageOutcome {
+    if rng.next_f32() >= hit_chance(attacker, defender) {
+        return DamageOutcome::Miss;
+    }
+
+    let mut amount = mitigated_damage(damage, attacker, defender, resistances);
+    let critical = rng.next_f32() < attacker.crit_chance;
+    if critical {
+        amount *= attacker.crit_multiplier;
+    }
+
+    DamageOutcome::Hit {
+        amount: amount,
+        critical: critical,
+    }
+}
+
+// How much of a heal actually lands. Heals go through here for the
+// same reason damage goes through `resolve_damage`: a unit can never
+// be healed past its maximum health, and the dead can't be healed.
+pub fn resolve_heal(amount: f32, health: f32, total_health: f32) -> f32 {
+    if health <= 0.0 {
+        return 0.0;
+    }
+    clamp(amount, 0.0, (total_health - health).max(0.0))
+}
+
+fn clamp(value: f32, min: f32, max: f32) -> f32 {
+    value.max(min).min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Always rolls the same value, so tests can force
+    // hits, misses and crits.
+    struct FixedRng(u32);
+
+    impl Rng for FixedRng {
+        fn next_u32(&mut self) -> u32 {
+            self.0
+        }
+    }
+
+    const LOW_ROLL: u32 = 0;
+    const HIGH_ROLL: u32 = ::std::u32::MAX;
+
+    fn approx_eq(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    #[test]
+    fn hit_chance_is_clamped() {
+        let mut attacker = Stats::default();
+        let mut defender = Stats::default();
+        assert!(approx_eq(hit_chance(&attacker, &defender), BASE_HIT_CHANCE));
+
+        attacker.dexterity = 1000.0;
+        assert!(approx_eq(hit_chance(&attacker, &defender), MAX_HIT_CHANCE));
+
+        attacker.dexterity = 0.0;
+        defender.evasion = 1000.0;
+        assert!(approx_eq(hit_chance(&attacker, &defender), MIN_HIT_CHANCE));
+    }
+
+    #[test]
+    fn armor_has_diminishing_returns() {
+        assert!(approx_eq(armor_reduction(0.0), 0.0));
+        assert!(approx_eq(armor_reduction(ARMOR_HALVING_POINT), 0.5));
+        assert!(armor_reduction(1000.0) < 1.0);
+        assert!(approx_eq(armor_reduction(-10.0), 0.0));
+    }
+
+    #[test]
+    fn strength_and_armor_only_affect_physical() {
+        let attacker = Stats { strength: 50.0, ..Stats::default() };
+        let defender = Stats { armor: ARMOR_HALVING_POINT, ..Stats::default() };
+        let resistances = Resistances::default();
+
+        let physical = mitigated_damage(Damage::new(10.0, DamageType::Physical), &attacker, &defender, &resistances);
+        assert!(approx_eq(physical, 10.0));
+
+        let fire = mitigated_damage(Damage::new(10.0, DamageType::Fire), &attacker, &defender, &resistances);
+        assert!(approx_eq(fire, 10.0));
+    }
+
+    #[test]
+    fn resistances_are_clamped() {
+        let stats = Stats::default();
+        let resistances = Resistances { fire: 2.0, cold: -5.0, ..Resistances::default() };
+
+        let fire = mitigated_damage(Damage::new(100.0, DamageType::Fire), &stats, &stats, &resistances);
+        assert!(approx_eq(fire, 100.0 * (1.0 - MAX_RESISTANCE)));
+
+        let cold = mitigated_damage(Damage::new(100.0, DamageType::Cold), &stats, &stats, &resistances);
+        assert!(approx_eq(cold, 200.0));
+    }
+
+    #[test]
+    fn high_roll_misses() {
+        let stats = Stats::default();
+        let outcome = resolve_damage(
+            &mut FixedRng(HIGH_ROLL),
+            Damage::new(10.0, DamageType::Physical),
+            &stats,
+            &stats,
+            &Resistances::default(),
+        );
+        assert_eq!(outcome, DamageOutcome::Miss);
+    }
+
+    #[test]
+    fn low_roll_hits_and_crits() {
+        let attacker = Stats { crit_chance: 0.5, crit_multiplier: 2.0, ..Stats::default() };
+        let outcome = resolve_damage(
+            &mut FixedRng(LOW_ROLL),
+            Damage::new(10.0, DamageType::Physical),
+            &attacker,
+            &Stats::default(),
+            &Resistances::default(),
+        );
+        assert_eq!(outcome, DamageOutcome::Hit { amount: 20.0, critical: true });
+    }
+
+    #[test]
+    fn heals_respect_max_health() {
+        assert!(approx_eq(resolve_heal(30.0, 50.0, 100.0), 30.0));
+        assert!(approx_eq(resolve_heal(80.0, 50.0, 100.0), 50.0));
+        assert!(approx_eq(resolve_heal(10.0, 100.0, 100.0), 0.0));
+        assert!(approx_eq(resolve_heal(-10.0, 50.0, 100.0), 0.0));
+    }
+
+    #[test]
+    fn the_dead_are_not_healed() {
+        assert!(approx_eq(resolve_heal(30.0, 0.0, 100.0), 0.0));
+    }
+
+    #[test]
+    fn no_crit_without_crit_chance() {
+        let stats = Stats::default();
+        let outcome = resolve_damage(
+            &mut FixedRng(LOW_ROLL),
+            Damage::new(10.0, DamageType::Poison),
+            &stats,
+            &stats,
+            &Resistances::default(),
+        );
+        assert_eq!(outcome, DamageOutcome::Hit { amount: 10.0, critical: false });
+    }
+}
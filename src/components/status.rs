@@ -0,0 +1,312 @@
+use amethyst::ecs::prelude::{Component, DenseVecStorage, Entity};
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::stats::{Damage, DamageType, Stats};
+
+// Number of icon slots shown above a unit's health bar.
+pub const STATUS_ICON_SLOTS: usize = 4;
+
+// Flat bonuses (or penalties, when negative) to a unit's stats.
+#[derive(Default, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatModifier {
+    pub strength: f32,
+    pub dexterity: f32,
+    pub armor: f32,
+    pub evasion: f32,
+    pub crit_chance: f32,
+}
+
+impl StatModifier {
+    // Adds the bonuses to `stats`, `times` over.
+    pub fn apply(&self, stats: &mut Stats, times: f32) {
+        stats.strength += self.strength * times;
+        stats.dexterity += self.dexterity * times;
+        stats.armor += self.armor * times;
+        stats.evasion += self.evasion * times;
+        stats.crit_chance += self.crit_chance * times;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum StatusKind {
+    // Can't move, attack or cast.
+    Stun,
+    // Movement speed is multiplied by `factor`.
+    Slow {
+        factor: f32,
+    },
+    // Deals `damage` every `interval` seconds, e.g. poison or burning.
+    DamageOverTime {
+        damage: Damage,
+        interval: f32,
+    },
+    Regeneration {
+        per_second: f32,
+    },
+    // Soaks up `amount` damage before health is touched.
+    Shield {
+        amount: f32,
+    },
+    StatBuff(StatModifier),
+}
+
+// What happens when an effect is applied to a unit
+// that already has an effect of the same name.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Stacking {
+    // Only the duration is reset.
+    Refresh,
+    // Adds a stack, up to `max_stacks`, and resets the duration.
+    // The effect gets stronger with every stack.
+    Intensify {
+        max_stacks: u32,
+    },
+    // Every application runs on its own.
+    Independent,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StatusIcon {
+    Stun = 0,
+    Slow = 1,
+    Poison = 2,
+    Burn = 3,
+    Regeneration = 4,
+    Shield = 5,
+    Buff = 6,
+    Debuff = 7,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub name: String,
+    pub kind: StatusKind,
+    #[serde(default = "default_stacking")]
+    pub stacking: Stacking,
+    pub duration: f32,
+    #[serde(skip)]
+    pub remaining: f32,
+    #[serde(skip, default = "default_stacks")]
+    pub stacks: u32,
+    #[serde(skip)]
+    pub source: Option<Entity>,
+    #[serde(skip)]
+    pub tick_timer: f32,
+}
+
+fn default_stacking() -> Stacking {
+    Stacking::Refresh
+}
+
+fn default_stacks() -> u32 {
+    1
+}
+
+impl StatusEffect {
+    pub fn new(name: &str, kind: StatusKind, duration: f32) -> StatusEffect {
+        StatusEffect {
+            name: name.to_string(),
+            kind: kind,
+            stacking: Stacking::Refresh,
+            duration: duration,
+            remaining: duration,
+            stacks: 1,
+            source: None,
+            tick_timer: 0.0,
+        }
+    }
+
+    pub fn with_stacking(mut self, stacking: Stacking) -> StatusEffect {
+        self.stacking = stacking;
+        self
+    }
+
+    pub fn from_source(mut self, source: Entity) -> StatusEffect {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn stun(duration: f32) -> StatusEffect {
+        StatusEffect::new("stun", StatusKind::Stun, duration)
+    }
+
+    pub fn slow(factor: f32, duration: f32) -> StatusEffect {
+        StatusEffect::new("slow", StatusKind::Slow { factor }, duration)
+    }
+
+    pub fn poison(damage_per_tick: f32, duration: f32) -> StatusEffect {
+        StatusEffect::new(
+            "poison",
+            StatusKind::DamageOverTime {
+                damage: Damage::new(damage_per_tick, DamageType::Poison),
+                interval: 1.0,
+            },
+            duration,
+        )
+            .with_stacking(Stacking::Intensify { max_stacks: 5 })
+    }
+
+    pub fn burn(damage_per_tick: f32, duration: f32) -> StatusEffect {
+        StatusEffect::new(
+            "burn",
+            StatusKind::DamageOverTime {
+                damage: Damage::new(damage_per_tick, DamageType::Fire),
+                interval: 0.5,
+            },
+            duration,
+        )
+    }
+
+    pub fn regeneration(per_second: f32, duration: f32) -> StatusEffect {
+        StatusEffect::new("regeneration", StatusKind::Regeneration { per_second }, duration)
+    }
+
+    pub fn shield(amount: f32, duration: f32) -> StatusEffect {
+        StatusEffect::new("shield", StatusKind::Shield { amount }, duration)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining <= 0.0
+    }
+
+    pub fn icon(&self) -> StatusIcon {
+        match self.kind {
+            StatusKind::Stun => StatusIcon::Stun,
+            StatusKind::Slow { .. } => StatusIcon::Slow,
+            StatusKind::DamageOverTime { damage, .. } => match damage.damage_type {
+                DamageType::Poison => StatusIcon::Poison,
+                _ => StatusIcon::Burn,
+            },
+            StatusKind::Regeneration { .. } => StatusIcon::Regeneration,
+            StatusKind::Shield { .. } => StatusIcon::Shield,
+            StatusKind::StatBuff(modifier) => {
+                let total = modifier.strength + modifier.dexterity + modifier.armor + modifier.evasion
+                    + modifier.crit_chance * 100.0;
+                if total >= 0.0 {
+                    StatusIcon::Buff
+                } else {
+                    StatusIcon::Debuff
+                }
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn apply(&mut self, mut effect: StatusEffect) {
+        if effect.stacking != Stacking::Independent {
+            if let Some(existing) = self.effects.iter_mut().find(|e| e.name == effect.name) {
+                existing.remaining = effect.duration;
+                existing.duration = effect.duration;
+                existing.source = effect.source.or(existing.source);
+                match existing.stacking {
+                    Stacking::Intensify { max_stacks } => {
+                        existing.stacks = (existing.stacks + 1).min(max_stacks);
+                    },
+                    _ => {
+                        // A fresh shield replaces whatever is left of the old one.
+                        existing.kind = effect.kind;
+                    },
+                }
+                return;
+            }
+        }
+        // Effects read from data files start out with no time left.
+        effect.remaining = effect.duration;
+        self.effects.push(effect);
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.effects.iter().any(|e| match e.kind {
+            StatusKind::Stun => true,
+            _ => false,
+        })
+    }
+
+    // Only the strongest slow counts, slows do not multiply together.
+    pub fn move_speed_multiplier(&self) -> f32 {
+        if self.is_stunned() {
+            return 0.0;
+        }
+        self.effects
+            .iter()
+            .filter_map(|e| match e.kind {
+                StatusKind::Slow { factor } => Some(factor),
+                _ => None,
+            })
+            .fold(1.0, f32::min)
+            .max(0.0)
+    }
+
+    pub fn modify_stats(&self, base: &Stats) -> Stats {
+        let mut stats = *base;
+        for effect in self.effects.iter() {
+            if let StatusKind::StatBuff(modifier) = effect.kind {
+                modifier.apply(&mut stats, effect.stacks as f32);
+            }
+        }
+        stats
+    }
+
+    // Lets shields soak up incoming damage, oldest shield first.
+    // Returns the damage left over for health.
+    pub fn absorb(&mut self, amount: f32) -> f32 {
+        let mut remaining = amount;
+        for effect in self.effects.iter_mut() {
+            if remaining <= 0.0 {
+                break;
+            }
+            if let StatusKind::Shield { amount: ref mut shield } = effect.kind {
+                let absorbed = remaining.min(*shield);
+                *shield -= absorbed;
+                remaining -= absorbed;
+                if *shield <= 0.0 {
+                    effect.remaining = 0.0;
+                }
+            }
+        }
+        remaining
+    }
+
+    // Distinct icons for the active effects, in the order they
+    // were applied, at most one per kind.
+    pub fn icons(&self) -> Vec<StatusIcon> {
+        let mut icons: Vec<StatusIcon> = vec![];
+        for effect in self.effects.iter() {
+            let icon = effect.icon();
+            if !icons.contains(&icon) {
+                icons.push(icon);
+            }
+        }
+        icons
+    }
+}
+
+impl Component for StatusEffects {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A unit's stats with all of its active buffs and debuffs applied.
+pub fn effective_stats(stats: Option<&Stats>, statuses: Option<&StatusEffects>) -> Stats {
+    let base = stats.cloned().unwrap_or_default();
+    match statuses {
+        Some(statuses) => statuses.modify_stats(&base),
+        None => base,
+    }
+}
+
+// An icon slot above a unit's health bar.
+pub struct StatusIconSlot {
+    pub slot: usize,
+}
+
+impl Component for StatusIconSlot {
+    type Storage = DenseVecStorage<Self>;
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::TILE_WIDTH;
 
@@ -11,7 +12,7 @@ use amethyst::{
     renderer::{SpriteRender, SpriteSheet},
 };
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum WallDecoration {
     RedFlag1,
     BlackFlag1,
@@ -26,7 +27,7 @@ impl WallDecoration {
     }
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum FloorVariant {
     TLCorner,
     TRCorner,
@@ -73,7 +74,7 @@ impl Default for FloorVariant {
     }
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum TileVariant {
     Ceiling,
     Floor(FloorVariant),
@@ -153,6 +154,13 @@ impl Component for Tile {
     type Storage = DenseVecStorage<Self>;
 }
 
+// The flags and such hung on walls, drawn over their tile.
+pub struct TileDecoration;
+
+impl Component for TileDecoration {
+    type Storage = DenseVecStorage<Self>;
+}
+
 pub fn initialize_tile(world: &mut World, variant: TileVariant, sprite_sheet_handle: Handle<SpriteSheet>, center: [f32; 2]) {
     let z = match variant {
         TileVariant::Ceiling => 0.0,
@@ -197,6 +205,7 @@ pub fn initialize_tile(world: &mut World, variant: TileVariant, sprite_sheet_han
             transform.set_translation_z(z + 0.1);
             world.create_entity()
                 .with(dec_sprite_render)
+                .with(TileDecoration)
                 .with(transform)
                 .build();
         },
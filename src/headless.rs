@@ -0,0 +1,458 @@
+use std::sync::Arc;
+
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    core::{ArcThreadPool, Hidden, Parent, Transform},
+    ecs::{
+        prelude::Entity,
+        rayon::ThreadPoolBuilder,
+        saveload::{Marker, SimpleMarkerAllocator},
+        Dispatcher,
+    },
+    prelude::*,
+    renderer::{SpriteRender, SpriteSheet, Texture},
+    ui::FontAsset,
+    utils::application_root_dir,
+    window::ScreenDimensions,
+};
+
+use crate::components::npc::{Attackable, HealthBar, PlayerControlled};
+use crate::components::save::{Persistent, SaveMarker};
+use crate::components::status::StatusIconSlot;
+use crate::components::tile::{FloorVariant, TileVariant};
+use crate::components::Layered;
+use crate::resources::dungeon::Dungeon;
+use crate::resources::items::Inventory;
+use crate::resources::map::{clear_floor, MapBuilder, UNIT_HEIGHT, UNIT_WIDTH};
+use crate::resources::orders::PartyOrders;
+use crate::resources::party::PartyRoster;
+use crate::resources::replay::{RecordedCommand, RecordedOrder, Recording, Replay};
+use crate::resources::run::{MetaProgression, Run};
+#[cfg(test)]
+use crate::resources::run::descend;
+use crate::resources::simulation::{FixedStep, PlayerInput, Simulation};
+use crate::resources::supplies::HealingPotions;
+use crate::resources::turns::Encounter;
+use crate::states::loading::load_resources;
+use crate::systems::simulation::{build_simulation, run_tick};
+#[cfg(test)]
+use crate::systems::simulation::run_ticks;
+use crate::{ARENA_HEIGHT, ARENA_WIDTH, calc_tile_center};
+
+// The game without a window or renderer: the gameplay systems and what
+// they load, stepped one tick at a time. Used by the gameplay tests and
+// the balance simulator.
+pub struct Headless {
+    pub world: World,
+    simulation: Dispatcher<'static, 'static>,
+    builder: MapBuilder,
+}
+
+impl Headless {
+    // A world with nothing on the floor yet, rolling from `seed`.
+    pub fn new(seed: u32) -> Headless {
+        let mut world = World::new();
+
+        let app_root = application_root_dir().expect("failed to find the application root");
+        let pool: ArcThreadPool = Arc::new(ThreadPoolBuilder::new().build().expect("failed to start the thread pool"));
+        world.insert(Loader::new(app_root.join("assets"), pool.clone()));
+        world.insert(pool);
+        world.insert(AssetStorage::<Texture>::default());
+        world.insert(AssetStorage::<SpriteSheet>::default());
+        world.insert(AssetStorage::<FontAsset>::default());
+        world.insert(ScreenDimensions::new(ARENA_WIDTH as u32, ARENA_HEIGHT as u32, 1.0));
+
+        // Usually registered by the transform bundle and the systems
+        // that only run alongside the renderer.
+        world.register::<Transform>();
+        world.register::<Parent>();
+        world.register::<Hidden>();
+        world.register::<SpriteRender>();
+        world.register::<HealthBar>();
+        world.register::<StatusIconSlot>();
+        world.register::<Layered>();
+
+        load_resources(&mut world);
+        // Tests shouldn't depend on whoever last played on this machine.
+        world.insert(MetaProgression::default());
+        world.insert(Simulation::Running);
+        world.insert(PlayerInput::Enabled);
+
+        let simulation = build_simulation(&mut world);
+        let builder = MapBuilder::initialize(&mut world);
+        let mut game = Headless {
+            world,
+            simulation,
+            builder,
+        };
+        game.reset(seed);
+        game
+    }
+
+    // Starts over with nothing on the floor, rolling from `seed`.
+    // Everything loaded from the assets is kept.
+    pub fn reset(&mut self, seed: u32) {
+        clear_floor(&mut self.world);
+        self.world.insert(SimpleMarkerAllocator::<SaveMarker>::default());
+        self.world.insert(Dungeon::new(seed));
+        self.world.insert(FixedStep::default());
+        self.world.insert(PartyRoster::default());
+        self.world.insert(Run::default());
+        self.world.insert(Encounter::default());
+        self.world.insert(Replay::Off);
+        self.world.insert(PartyOrders::default());
+        self.world.insert(HealingPotions::default());
+        self.world.insert(Inventory::default());
+    }
+
+    // Lays out a floor where only the given rooms are open,
+    // each as `[x, y, width, height]` in tiles.
+    pub fn floor(&mut self, rooms: &[[usize; 4]]) {
+        let mut tiles = vec![TileVariant::Ceiling; UNIT_WIDTH * UNIT_HEIGHT];
+        for room in rooms {
+            for x in room[0]..room[0] + room[2] {
+                for y in room[1]..room[1] + room[3] {
+                    tiles[x * UNIT_HEIGHT + y] = TileVariant::Floor(FloorVariant::default());
+                }
+            }
+        }
+        self.builder.restore(&mut self.world, &tiles).expect("failed to lay out the floor");
+    }
+
+    // Spawns a unit in the middle of tile `at`. Party classes join the party.
+    pub fn spawn(&mut self, archetype: &str, at: [usize; 2]) -> Entity {
+        let coords = [calc_tile_center(at[0]), calc_tile_center(at[1])];
+        let entity = self.builder
+            .spawn_npc(&mut self.world, archetype, coords)
+            .expect("unknown archetype");
+        if self.world.read_storage::<PlayerControlled>().contains(entity) {
+            self.world.write_resource::<PartyRoster>().recruit(archetype, entity);
+        }
+        entity
+    }
+
+    // Gives an order the way the player would, through `CommandSystem`.
+    // It is carried out on the next tick.
+    pub fn order(&mut self, units: &[Entity], order: RecordedOrder) {
+        let command = RecordedCommand {
+            tick: self.tick(),
+            units: units.iter().map(|unit| self.marker(*unit)).collect(),
+            order,
+        };
+        let mut replay = self.world.write_resource::<Replay>();
+        match &mut *replay {
+            Replay::Playing { recording, .. } => recording.commands.push(command),
+            _ => {
+                *replay = Replay::Playing {
+                    recording: Recording {
+                        commands: vec![command],
+                        ..Recording::default()
+                    },
+                    next: 0,
+                };
+            },
+        }
+    }
+
+    // What orders and replays call `entity`.
+    pub fn marker(&self, entity: Entity) -> u64 {
+        self.world.read_storage::<Persistent>().get(entity).map(|m| m.id()).expect("unit isn't marked")
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.world.read_resource::<FixedStep>().tick
+    }
+
+    pub fn step(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            run_tick(&mut self.simulation, &mut self.world);
+        }
+    }
+}
+
+#[cfg(test)]
+impl Headless {
+    // Steps until `done` or `max_ticks` have passed, returning whether it got done.
+    pub fn run_until<F: FnMut(&World) -> bool>(&mut self, max_ticks: u64, mut done: F) -> bool {
+        for _ in 0..max_ticks {
+            if done(&self.world) {
+                return true;
+            }
+            self.step(1);
+        }
+        done(&self.world)
+    }
+
+    // Plays a frame that took `delta` seconds the way `GameplayState`
+    // does, taking the party down the stairs if it got there.
+    pub fn frame(&mut self, delta: f32) {
+        let ticks = self.world.write_resource::<FixedStep>().advance(delta);
+        run_ticks(&mut self.simulation, &mut self.world, ticks);
+        if self.world.read_resource::<Run>().descend {
+            descend(&mut self.world);
+        }
+    }
+
+    pub fn health(&self, entity: Entity) -> f32 {
+        health(&self.world, entity)
+    }
+}
+
+// 0 once the unit is dead and gone.
+pub fn health(world: &World, entity: Entity) -> f32 {
+    world.read_storage::<Attackable>().get(entity).map_or(0.0, |a| a.health)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::items::{EquipSlot, Equipment};
+    use crate::components::loot::{LootSheet, Pickup};
+    use crate::components::npc::{Enemy, Npc};
+    use crate::components::scenary::initialize_stairs;
+    use crate::resources::events::CombatEvent;
+    use crate::resources::items::ItemDatabase;
+    use crate::resources::map::MapGrid;
+    use crate::resources::orders::GearChange;
+    use crate::resources::simulation::TICK_SECONDS;
+    use crate::resources::turns::{CombatMode, ACTION_POINTS};
+    use crate::systems::commands::distance;
+    use crate::systems::loot::spawn_pickup;
+    use crate::TILE_WIDTH;
+    use amethyst::ecs::{prelude::LazyUpdate, Join};
+    use amethyst::shrev::EventChannel;
+
+    // Ticks in a second.
+    const SECONDS: u64 = 60;
+
+    fn position(world: &World, entity: Entity) -> [f32; 2] {
+        let transforms = world.read_storage::<Transform>();
+        let transform = transforms.get(entity).expect("unit has no transform");
+        [transform.translation().x, transform.translation().y]
+    }
+
+    // How far apart two points are, in tiles.
+    fn tiles_between(a: [f32; 2], b: [f32; 2]) -> f32 {
+        distance(a, b) / TILE_WIDTH
+    }
+
+    // A warrior in the middle of a room, with three orcs around them.
+    // `padding` entities are made first, to number everything differently.
+    fn orc_fight(seed: u32, padding: usize) -> (Headless, Entity, Vec<Entity>) {
+        let mut game = Headless::new(seed);
+        for _ in 0..padding {
+            game.world.create_entity().build();
+        }
+        game.floor(&[[10, 10, 30, 20]]);
+        let warrior = game.spawn("warrior", [25, 20]);
+        let orcs = vec![
+            game.spawn("orc_grunt", [28, 20]),
+            game.spawn("orc_grunt", [25, 23]),
+            game.spawn("orc_grunt", [22, 20]),
+        ];
+        (game, warrior, orcs)
+    }
+
+    fn fight_it_out(game: &mut Headless, warrior: Entity, orcs: &[Entity]) {
+        for orc in orcs {
+            game.order(&[warrior], RecordedOrder::Attack(game.marker(*orc)));
+            let orc = *orc;
+            assert!(game.run_until(20 * SECONDS, |world| health(world, orc) <= 0.0));
+        }
+    }
+
+    // Kills `units` all on the next tick, in the order given.
+    fn kill_together(game: &mut Headless, units: &[Entity]) {
+        for unit in units {
+            if let Some(attackable) = game.world.write_storage::<Attackable>().get_mut(*unit) {
+                attackable.health = 0.0;
+            }
+            game.world
+                .write_resource::<EventChannel<CombatEvent>>()
+                .single_write(CombatEvent::Death { entity: *unit, killer: None });
+        }
+        game.step(1);
+    }
+
+    // Gold, item and where it lies of everything dropped, sorted by where.
+    fn drops(world: &World) -> Vec<(u32, Option<String>, [f32; 2])> {
+        let mut drops: Vec<(u32, Option<String>, [f32; 2])> = (&world.entities(), &world.read_storage::<Pickup>())
+            .join()
+            .map(|(e, p)| (p.gold, p.item.as_ref().map(|i| i.display_name()), position(world, e)))
+            .collect();
+        drops.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+        drops
+    }
+
+    #[test]
+    fn warrior_kills_three_orcs() {
+        let (mut game, warrior, orcs) = orc_fight(7, 0);
+        fight_it_out(&mut game, warrior, &orcs);
+
+        let world = &game.world;
+        let standing = (&world.read_storage::<Enemy>(), &world.read_storage::<Attackable>())
+            .join()
+            .filter(|(_, a)| a.health > 0.0)
+            .count();
+        assert_eq!(standing, 0);
+        assert!(game.health(warrior) > 0.0);
+        assert_eq!(game.world.read_resource::<Run>().stats.kills, 3);
+    }
+
+    #[test]
+    fn unit_reaches_clicked_point() {
+        let mut game = Headless::new(1);
+        game.floor(&[[10, 10, 30, 20]]);
+        let warrior = game.spawn("warrior", [12, 12]);
+        let target = [calc_tile_center(35), calc_tile_center(25)];
+
+        game.order(&[warrior], RecordedOrder::Move(target));
+        assert!(game.run_until(10 * SECONDS, |world| tiles_between(position(world, warrior), target) < 1.0));
+    }
+
+    #[test]
+    fn unit_reaches_clicked_point_around_a_corner() {
+        let mut game = Headless::new(1);
+        // Two rooms, joined by a corridor that runs right then turns up.
+        game.floor(&[[10, 10, 10, 8], [20, 12, 18, 4], [34, 16, 4, 12], [30, 28, 10, 8]]);
+        let warrior = game.spawn("warrior", [12, 14]);
+        let target = [calc_tile_center(35), calc_tile_center(32)];
+
+        game.order(&[warrior], RecordedOrder::Move(target));
+        assert!(game.run_until(20 * SECONDS, |world| tiles_between(position(world, warrior), target) < 1.0));
+    }
+
+    #[test]
+    fn same_seed_and_orders_play_out_the_same() {
+        let (mut first, warrior, orcs) = orc_fight(42, 0);
+        fight_it_out(&mut first, warrior, &orcs);
+
+        // Entities being numbered differently mustn't change any rolls.
+        let (mut second, warrior_again, orcs_again) = orc_fight(42, 3);
+        fight_it_out(&mut second, warrior_again, &orcs_again);
+
+        // Nor must which of two units killed on the same tick is gone
+        // through first change what they drop.
+        let pair = [first.spawn("orc_grunt", [30, 25]), first.spawn("orc_grunt", [32, 25])];
+        kill_together(&mut first, &pair);
+        let pair_again = [second.spawn("orc_grunt", [30, 25]), second.spawn("orc_grunt", [32, 25])];
+        kill_together(&mut second, &[pair_again[1], pair_again[0]]);
+
+        assert_eq!(first.tick(), second.tick());
+        assert_eq!(first.health(warrior), second.health(warrior_again));
+        assert_eq!(position(&first.world, warrior), position(&second.world, warrior_again));
+        assert!(!drops(&first.world).is_empty());
+        assert_eq!(drops(&first.world), drops(&second.world));
+    }
+
+    #[test]
+    fn frame_rate_does_not_change_when_the_party_descends() {
+        // Where the party is a second after arriving on the next floor,
+        // and on which tick they got there.
+        let play = |ticks_per_frame: f32| {
+            let mut game = Headless::new(5);
+            game.floor(&[[10, 10, 30, 20]]);
+            let warrior = game.spawn("warrior", [12, 20]);
+            let stairs = [calc_tile_center(30), calc_tile_center(20)];
+            let handle = game.world.read_resource::<LootSheet>().0.clone();
+            initialize_stairs(&mut game.world, handle, stairs);
+
+            game.order(&[warrior], RecordedOrder::Move(stairs));
+            while game.world.read_resource::<Dungeon>().depth == 1 && game.tick() < 20 * SECONDS {
+                game.frame(ticks_per_frame * TICK_SECONDS);
+            }
+            let arrived = game.tick();
+            for _ in 0..SECONDS {
+                game.frame(ticks_per_frame * TICK_SECONDS);
+            }
+            let member = game.world.read_resource::<PartyRoster>().members[0].entity.expect("warrior wasn't respawned");
+            (arrived, game.tick(), position(&game.world, member))
+        };
+
+        let (arrived, ticks, at) = play(1.0);
+        assert!(arrived < 20 * SECONDS);
+        // Ticks batched up the way a slow frame runs them.
+        let (arrived_slowly, ticks_slowly, at_slowly) = play(8.0);
+        assert_eq!(arrived, arrived_slowly);
+        assert_eq!(at, at_slowly);
+        assert!(ticks.min(ticks_slowly) > arrived);
+    }
+
+    #[test]
+    fn replayed_gear_changes_are_made() {
+        let mut game = Headless::new(3);
+        game.floor(&[[10, 10, 30, 20]]);
+        let warrior = game.spawn("warrior", [20, 20]);
+        let axe = game.world.read_resource::<ItemDatabase>().create("battle_axe").expect("no battle axe");
+        game.world.write_resource::<Inventory>().items.push(axe);
+
+        game.order(&[warrior], RecordedOrder::Gear(GearChange::Use(0)));
+        game.step(1);
+
+        let equipments = game.world.read_storage::<Equipment>();
+        let weapon = equipments.get(warrior).and_then(|e| e.slots.get(&EquipSlot::Weapon));
+        assert_eq!(weapon.map(|w| w.display_name()), Some("Battle Axe".to_string()));
+        assert!(game.world.read_resource::<Inventory>().items.iter().all(|i| i.display_name() != "Battle Axe"));
+    }
+
+    #[test]
+    fn party_explores_around_itself() {
+        let mut game = Headless::new(4);
+        game.floor(&[[10, 10, 40, 20]]);
+        game.spawn("warrior", [12, 20]);
+        game.step(1);
+
+        let grid = game.world.read_resource::<MapGrid>();
+        assert!(grid.is_explored(12, 20));
+        assert!(grid.is_explored(16, 20));
+        assert!(!grid.is_explored(40, 20));
+    }
+
+    #[test]
+    fn turn_moves_stop_on_the_last_tile_there_are_points_for() {
+        let mut game = Headless::new(5);
+        game.floor(&[[10, 10, 60, 20]]);
+        game.world.write_resource::<Run>().mode = CombatMode::TurnBased;
+        let warrior = game.spawn("warrior", [12, 20]);
+        // Too far away to notice anyone, but enough to keep the fight going.
+        let orc = game.spawn("orc", [65, 20]);
+        let mut encounter = Encounter { order: vec![warrior, orc], round: 1, ..Encounter::default() };
+        encounter.begin_turn(position(&game.world, warrior));
+        game.world.insert(encounter);
+
+        game.order(&[warrior], RecordedOrder::Move([calc_tile_center(40), calc_tile_center(20)]));
+        game.step(1);
+        let stopped = |world: &World| world.read_storage::<Npc>().get(warrior).map_or(true, |n| n.velocity == [0.0, 0.0]);
+        assert!(game.run_until(5 * SECONDS, stopped));
+
+        let expected = [calc_tile_center(12 + ACTION_POINTS as usize), calc_tile_center(20)];
+        assert!(tiles_between(position(&game.world, warrior), expected) < 0.2);
+    }
+
+    #[test]
+    fn pickups_are_collected_by_whoever_is_sent_for_them() {
+        let mut game = Headless::new(6);
+        game.floor(&[[10, 10, 30, 20]]);
+        let warrior = game.spawn("warrior", [12, 20]);
+        let gold = spawn_pickup(
+            &game.world.read_resource::<LazyUpdate>(),
+            &game.world.entities(),
+            &game.world.read_resource::<LootSheet>(),
+            Pickup { item: None, gold: 25 },
+            [calc_tile_center(20), calc_tile_center(20)],
+        );
+        game.world.maintain();
+        let before = game.world.read_resource::<Inventory>().gold;
+
+        // Walking over it isn't enough.
+        let past = [calc_tile_center(25), calc_tile_center(20)];
+        game.order(&[warrior], RecordedOrder::Move(past));
+        assert!(game.run_until(10 * SECONDS, |world| tiles_between(position(world, warrior), past) < 1.0));
+        assert_eq!(game.world.read_resource::<Inventory>().gold, before);
+
+        game.order(&[warrior], RecordedOrder::Pickup(game.marker(gold)));
+        assert!(game.run_until(10 * SECONDS, |world| world.read_resource::<Inventory>().gold == before + 25));
+        game.step(1);
+        assert!(!game.world.entities().is_alive(gold));
+    }
+}
@@ -1,12 +1,11 @@
 use amethyst::{
     assets::{AssetStorage, Loader, Handle},
-    core::transform::{Transform, TransformBundle},
+    core::transform::TransformBundle,
     prelude::*,
     renderer::{
         plugins::{RenderFlat2D, RenderToWindow},
         types::DefaultBackend,
         RenderingBundle,
-        Camera,
         SpriteSheet,
         SpriteSheetFormat,
         Texture,
@@ -20,15 +19,24 @@ use amethyst::{
 mod components;
 mod systems;
 mod resources;
+mod states;
+mod headless;
+mod balance;
 use systems::{
-    commands::CommandSystem,
-    movement::MovementSystem,
-    combat::{CombatSystem, HealthBarSystem},
-    enemy_targeting::EnemyTargetingSystem,
-    animation::IdleAnimationSystem,
+    combat::HealthBarSystem,
     layering::LayeringSystem,
+    hotbar::HotbarSystemDesc,
+    status::StatusIconSystem,
+    progression::TalentScreenSystemDesc,
+    inventory::InventoryScreenSystemDesc,
+    shop::ShopScreenSystemDesc,
+    save::SaveMenuSystemDesc,
+    speed::GameSpeedSystem,
+    interpolation::InterpolationSystem,
+    exploration::FogSystem,
+    feedback::{CombatFeedbackSystemDesc, FloatingTextSystem, HitFlashSystem, ScreenShakeSystem},
 };
-use resources::map::MapBuilder;
+use states::loading::LoadingState;
 
 // These are px dimensions used to
 // calc our tile dimensions.
@@ -38,34 +46,20 @@ pub const ARENA_HEIGHT: f32 = 640.0;
 pub const ARENA_WIDTH: f32 = 1280.0;
 pub const TILE_WIDTH: f32 = 16.0;
 
-struct MainState;
-
-impl MainState {
-    fn new() -> MainState {
-        MainState {}
-    }
-}
-
-impl SimpleState for MainState {
-    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
-        let world = data.world;
-
-        let mut builder = MapBuilder::initialize(world);
-
-        builder.build_map(world);
-
-        initialize_camera(world);
-    }
-}
-
 fn main() -> amethyst::Result<()> {
     amethyst::start_logger(Default::default());
 
+    // Balance runs don't need a window.
+    if let Some(matchup) = balance::simulate_arg() {
+        return balance::simulate(&matchup);
+    }
+
     let app_root = application_root_dir()?;
 
     let assets_dir = app_root.join("assets");
     let config_dir = app_root.join("config");
     let display_config_path = config_dir.join("display.ron");
+    let bindings_path = config_dir.join("bindings.ron");
 
     let game_data = GameDataBuilder::default()
         .with_bundle(
@@ -77,34 +71,34 @@ fn main() -> amethyst::Result<()> {
                 .with_plugin(RenderFlat2D::default())
                 .with_plugin(RenderUi::default()),
         )?
-        .with_bundle(TransformBundle::new())?
-        .with_bundle(InputBundle::<StringBindings>::new())?
+        // Where things are drawn is settled before the transforms are.
+        .with(InterpolationSystem, "interpolation_system", &[])
+        .with(LayeringSystem, "layering_system", &["interpolation_system"])
+        .with_system_desc(CombatFeedbackSystemDesc::default(), "combat_feedback_system", &[])
+        .with(FloatingTextSystem, "floating_text_system", &["combat_feedback_system"])
+        .with(HitFlashSystem, "hit_flash_system", &["combat_feedback_system"])
+        .with(ScreenShakeSystem, "screen_shake_system", &["combat_feedback_system"])
+        .with_bundle(TransformBundle::new().with_dep(&["interpolation_system", "layering_system", "screen_shake_system"]))?
+        .with_bundle(InputBundle::<StringBindings>::new().with_bindings_from_file(bindings_path)?)?
         .with_bundle(UiBundle::<StringBindings>::new())?
-        .with(CommandSystem::default(), "command_system", &["input_system"])
-        .with(MovementSystem, "movement_system", &["command_system"])
-        .with(EnemyTargetingSystem, "enemy_targeting_system", &["movement_system"])
-        .with(CombatSystem, "combat_system", &["movement_system", "enemy_targeting_system"])
-        .with(IdleAnimationSystem::default(), "anim_system", &["movement_system", "combat_system", "enemy_targeting_system"])
-        .with(LayeringSystem, "layering_system", &[])
-        .with(HealthBarSystem, "healthbar_system", &[]);
-
-    let mut game = Application::new(assets_dir, MainState::new(), game_data)?;
+        .with(GameSpeedSystem::default(), "game_speed_system", &["input_system"])
+        // The gameplay systems themselves are run by `GameplayState`, see
+        // `build_simulation`; these keep the screens and ui working.
+        .with_system_desc(TalentScreenSystemDesc::default(), "talent_screen_system", &["input_system"])
+        .with_system_desc(InventoryScreenSystemDesc::default(), "inventory_screen_system", &["input_system", "talent_screen_system"])
+        .with_system_desc(ShopScreenSystemDesc::default(), "shop_screen_system", &["input_system", "inventory_screen_system"])
+        .with_system_desc(SaveMenuSystemDesc::default(), "save_menu_system", &["input_system", "shop_screen_system"])
+        .with_system_desc(HotbarSystemDesc::default(), "hotbar_system", &[])
+        .with(HealthBarSystem, "healthbar_system", &[])
+        .with(StatusIconSystem, "status_icon_system", &[])
+        .with(FogSystem, "fog_system", &[]);
+
+    let mut game = Application::new(assets_dir, LoadingState, game_data)?;
     game.run();
 
     Ok(())
 }
 
-fn initialize_camera(world: &mut World) {
-    let mut transform = Transform::default();
-    transform.set_translation_xyz(ARENA_WIDTH * 0.5, ARENA_HEIGHT * 0.5, 100.0);
-
-    world
-        .create_entity()
-        .with(Camera::standard_2d(ARENA_WIDTH, ARENA_HEIGHT))
-        .with(transform)
-        .build();
-}
-
 pub fn load_sprite_sheet(world: &mut World, texture_file: &str, ron_file: &str) -> Handle<SpriteSheet> {
     let texture_handle = {
         let loader = world.read_resource::<Loader>();
@@ -0,0 +1,228 @@
+use rand::Rng;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::components::items::{Affix, AffixPosition, EquipSlot, Item, ItemBonus, Rarity};
+use crate::resources::items::ItemDatabase;
+
+// How much stronger affixes get with every floor below the first.
+pub const DEPTH_SCALING: f32 = 0.15;
+
+// An affix as written in `assets/items/affixes.ron`, before it is rolled.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AffixDef {
+    pub name: String,
+    pub position: AffixPosition,
+    // The slots it can roll on. Empty means any equipment.
+    #[serde(default)]
+    pub slots: Vec<EquipSlot>,
+    // The shallowest floor it can turn up on.
+    #[serde(default = "default_min_depth")]
+    pub min_depth: u32,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    // The bonus is rolled somewhere between `min` and `max`,
+    // then scaled by depth and rarity.
+    #[serde(default)]
+    pub min: ItemBonus,
+    #[serde(default)]
+    pub max: ItemBonus,
+}
+
+fn default_min_depth() -> u32 {
+    1
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl AffixDef {
+    pub fn can_roll_on(&self, slot: EquipSlot, depth: u32) -> bool {
+        self.min_depth <= depth && (self.slots.is_empty() || self.slots.contains(&slot))
+    }
+
+    pub fn roll<R: Rng>(&self, rng: &mut R, scale: f32) -> Affix {
+        let t = rng.next_f32();
+        let mut bonus = self.min.scaled(1.0 - t);
+        bonus.add(&self.max.scaled(t));
+
+        Affix {
+            name: self.name.clone(),
+            position: self.position,
+            bonus: bonus.scaled(scale),
+        }
+    }
+}
+
+pub fn depth_scale(depth: u32) -> f32 {
+    1.0 + DEPTH_SCALING * depth.saturating_sub(1) as f32
+}
+
+// Better items turn up more often the deeper the party goes.
+pub fn rarity_weights(depth: u32) -> [(Rarity, u32); 5] {
+    [
+        (Rarity::Common, 100),
+        (Rarity::Uncommon, 40 + 5 * depth),
+        (Rarity::Rare, 10 + 4 * depth),
+        (Rarity::Epic, 2 + 2 * depth),
+        (Rarity::Legendary, depth.saturating_sub(2)),
+    ]
+}
+
+pub fn roll_rarity<R: Rng>(rng: &mut R, depth: u32) -> Rarity {
+    let weights = rarity_weights(depth);
+    let index = pick_weighted(rng, &weights.iter().map(|(_, w)| *w).collect::<Vec<u32>>());
+    weights[index.unwrap_or(0)].0
+}
+
+// Rolls random equipment: a base item with affixes on top.
+#[derive(Default)]
+pub struct ItemGenerator {
+    // Every equipment base item, in a fixed order so that
+    // the same rolls always give the same item.
+    bases: Vec<Item>,
+    affixes: Vec<AffixDef>,
+}
+
+impl ItemGenerator {
+    pub fn new(bases: Vec<Item>, affixes: Vec<AffixDef>) -> ItemGenerator {
+        ItemGenerator { bases, affixes }
+    }
+
+    pub fn load(path: &Path, items: &ItemDatabase) -> amethyst::Result<ItemGenerator> {
+        let affixes: Vec<AffixDef> = ron::de::from_str(&fs::read_to_string(path)?)?;
+        Ok(ItemGenerator::new(items.equipment(), affixes))
+    }
+
+    // A random piece of equipment for the given floor.
+    pub fn generate<R: Rng>(&self, rng: &mut R, depth: u32) -> Option<Item> {
+        if self.bases.is_empty() {
+            return None;
+        }
+        let base = self.bases[rng.gen_range(0, self.bases.len())].clone();
+        Some(self.roll_affixes(rng, base, depth))
+    }
+
+    // Rolls a rarity for the item and affixes to go with it,
+    // at most one prefix and one suffix.
+    pub fn roll_affixes<R: Rng>(&self, rng: &mut R, mut item: Item, depth: u32) -> Item {
+        let slot = match item.equip_slot() {
+            Some(slot) => slot,
+            None => return item,
+        };
+
+        item.rarity = roll_rarity(rng, depth);
+        let scale = depth_scale(depth) * item.rarity.multiplier();
+
+        let mut positions = vec![AffixPosition::Prefix, AffixPosition::Suffix];
+        if rng.gen() {
+            positions.reverse();
+        }
+
+        for position in positions.into_iter().take(item.rarity.affix_count()) {
+            let candidates: Vec<&AffixDef> = self.affixes
+                .iter()
+                .filter(|a| a.position == position && a.can_roll_on(slot, depth))
+                .collect();
+            let weights: Vec<u32> = candidates.iter().map(|a| a.weight).collect();
+            if let Some(index) = pick_weighted(rng, &weights) {
+                item.affixes.push(candidates[index].roll(rng, scale));
+            }
+        }
+
+        item
+    }
+}
+
+fn pick_weighted<R: Rng>(rng: &mut R, weights: &[u32]) -> Option<usize> {
+    let total: u32 = weights.iter().sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut roll = rng.gen_range(0, total);
+    for (index, weight) in weights.iter().enumerate() {
+        if roll < *weight {
+            return Some(index);
+        }
+        roll -= weight;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::items::ItemKind;
+    use rand::{SeedableRng, XorShiftRng};
+
+    fn sword() -> Item {
+        Item {
+            name: "Sword".to_string(),
+            kind: ItemKind::Equipment(EquipSlot::Weapon),
+            rarity: Rarity::Common,
+            bonus: ItemBonus { attack: 3.0, ..ItemBonus::default() },
+            affixes: vec![],
+            value: 10,
+        }
+    }
+
+    fn affix(name: &str, position: AffixPosition) -> AffixDef {
+        AffixDef {
+            name: name.to_string(),
+            position: position,
+            slots: vec![],
+            min_depth: 1,
+            weight: 1,
+            min: ItemBonus { attack: 2.0, ..ItemBonus::default() },
+            max: ItemBonus { attack: 2.0, ..ItemBonus::default() },
+        }
+    }
+
+    fn generator() -> ItemGenerator {
+        ItemGenerator::new(
+            vec![sword()],
+            vec![affix("Flaming", AffixPosition::Prefix), affix("of the Bear", AffixPosition::Suffix)],
+        )
+    }
+
+    #[test]
+    fn same_seed_same_item() {
+        let generator = generator();
+        let first = generator.generate(&mut XorShiftRng::from_seed([1, 2, 3, 4]), 3).unwrap();
+        let second = generator.generate(&mut XorShiftRng::from_seed([1, 2, 3, 4]), 3).unwrap();
+        assert_eq!(first.display_name(), second.display_name());
+        assert_eq!(first.rarity, second.rarity);
+        assert_eq!(first.total_bonus().attack, second.total_bonus().attack);
+    }
+
+    #[test]
+    fn at_most_one_prefix_and_suffix() {
+        let generator = generator();
+        let mut rng = XorShiftRng::from_seed([5, 6, 7, 8]);
+        for _ in 0..100 {
+            let item = generator.generate(&mut rng, 10).unwrap();
+            assert_eq!(item.affixes.len(), item.rarity.affix_count());
+            let prefixes = item.affixes.iter().filter(|a| a.position == AffixPosition::Prefix).count();
+            assert!(prefixes <= 1);
+            assert!(item.affixes.len() - prefixes <= 1);
+        }
+    }
+
+    #[test]
+    fn affixes_scale_with_depth() {
+        let def = affix("Flaming", AffixPosition::Prefix);
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let shallow = def.roll(&mut rng, depth_scale(1)).bonus.attack;
+        let deep = def.roll(&mut rng, depth_scale(5)).bonus.attack;
+        assert!(deep > shallow);
+    }
+
+    #[test]
+    fn no_legendaries_on_the_first_floors() {
+        let legendary = rarity_weights(1).iter().find(|(r, _)| *r == Rarity::Legendary).unwrap().1;
+        assert_eq!(legendary, 0);
+    }
+}
@@ -0,0 +1,249 @@
+use amethyst::{
+    assets::Handle,
+    prelude::*,
+    renderer::SpriteSheet,
+};
+
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::load_sprite_sheet;
+use crate::components::abilities::{Ability, AbilityEffect};
+use crate::components::progression::{StatGrowth, Talent};
+use crate::components::projectile::AttackKind;
+use crate::components::stats::{DamageType, Resistances, Stats};
+use crate::resources::party::PartyRoster;
+
+// A run of frames in an archetype's sprite sheet.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AnimationDef {
+    pub start: usize,
+    pub frames: usize,
+    pub time_per_frame: f32,
+    // Only used by fight animations: the frame the blow lands on.
+    #[serde(default)]
+    pub impact_frame: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AttackDef {
+    pub damage: f32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    pub cooldown: f32,
+    pub range: f32,
+    #[serde(default)]
+    pub kind: AttackKind,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EnergyDef {
+    pub mana: f32,
+    pub stamina: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RecoveryDef {
+    pub per_second: f32,
+    pub delay: f32,
+}
+
+// Who a unit fights for and how it picks its fights.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+pub enum AiProfile {
+    // Joins the player's party and only acts on orders.
+    Party,
+    // Goes after any party member that comes within `fov_radius`.
+    Hostile {
+        fov_radius: f32,
+    },
+}
+
+// Everything needed to spawn a kind of unit, read from
+// `assets/archetypes/<id>.ron`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Archetype {
+    // The file name the archetype was read from.
+    #[serde(skip)]
+    pub id: String,
+    pub name: String,
+    pub texture: String,
+    pub sprite_sheet: String,
+    // Offsets into the sprite sheet of alternative looks,
+    // one of which is picked at random on spawn.
+    #[serde(default = "default_sprite_variants")]
+    pub sprite_variants: Vec<usize>,
+    pub idle: AnimationDef,
+    pub walk: AnimationDef,
+    pub fight: AnimationDef,
+    pub death: AnimationDef,
+    pub move_speed: f32,
+    pub health: f32,
+    pub attack: AttackDef,
+    #[serde(default)]
+    pub stats: Stats,
+    #[serde(default)]
+    pub resistances: Resistances,
+    #[serde(default)]
+    pub energy: Option<EnergyDef>,
+    #[serde(default)]
+    pub recovery: Option<RecoveryDef>,
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    pub ai: AiProfile,
+    // Experience split among the party for killing one of these.
+    #[serde(default)]
+    pub experience: u32,
+    #[serde(default)]
+    pub growth: StatGrowth,
+    #[serde(default)]
+    pub talents: Vec<Talent>,
+    // How likely this archetype is to turn up in a random
+    // enemy group. Zero means it is only ever placed on purpose.
+    #[serde(default)]
+    pub spawn_weight: u32,
+    // Loot table rolled when one of these dies.
+    #[serde(default)]
+    pub loot_table: Option<String>,
+}
+
+fn default_sprite_variants() -> Vec<usize> {
+    vec![0]
+}
+
+// Every archetype found in the archetypes directory, keyed by file name,
+// along with the sprite sheets they use.
+#[derive(Default)]
+pub struct ArchetypeRegistry {
+    archetypes: HashMap<String, Archetype>,
+    sprite_sheets: HashMap<String, Handle<SpriteSheet>>,
+}
+
+impl ArchetypeRegistry {
+    pub fn load(world: &mut World, dir: &Path) -> amethyst::Result<ArchetypeRegistry> {
+        let mut registry = ArchetypeRegistry::default();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |ext| ext != "ron") {
+                continue;
+            }
+            let id = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(id) => id.to_string(),
+                None => continue,
+            };
+
+            let mut archetype: Archetype = ron::de::from_str(&fs::read_to_string(&path)?).map_err(|e| {
+                amethyst::Error::from_string(format!("error reading archetype {} : {}", path.display(), e))
+            })?;
+
+            archetype.id = id.clone();
+
+            // Units start their idle animation on a random frame,
+            // and every animation is stepped through frame by frame.
+            let animations = [
+                ("idle", &archetype.idle),
+                ("walk", &archetype.walk),
+                ("fight", &archetype.fight),
+                ("death", &archetype.death),
+            ];
+            for (name, animation) in animations.iter() {
+                if animation.frames == 0 {
+                    return Err(amethyst::Error::from_string(format!(
+                        "archetype {} has no {} frames", id, name
+                    )));
+                }
+            }
+
+            // Projectiles live for as long as they take to cross their
+            // range, which never ends if they don't move.
+            let mut speeds: Vec<f32> = archetype.abilities.iter().filter_map(|ability| match ability.effect {
+                AbilityEffect::Projectile { speed, .. } => Some(speed),
+                _ => None,
+            }).collect();
+            if let AttackKind::Ranged { projectile_speed } = archetype.attack.kind {
+                speeds.push(projectile_speed);
+            }
+            if let Some(speed) = speeds.into_iter().find(|speed| speed.is_nan() || *speed <= 0.0) {
+                return Err(amethyst::Error::from_string(format!(
+                    "archetype {} has a projectile with a speed of {}", id, speed
+                )));
+            }
+
+            if !registry.sprite_sheets.contains_key(&archetype.texture) {
+                let handle = load_sprite_sheet(world, &archetype.texture, &archetype.sprite_sheet);
+                registry.sprite_sheets.insert(archetype.texture.clone(), handle);
+            }
+            registry.archetypes.insert(id, archetype);
+        }
+
+        Ok(registry)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Archetype> {
+        self.archetypes.get(id)
+    }
+
+    pub fn talent(&self, archetype: &str, talent: &str) -> Option<&Talent> {
+        self.get(archetype).and_then(|a| a.talents.iter().find(|t| t.id == talent))
+    }
+
+    pub fn sprite_sheet(&self, archetype: &Archetype) -> Handle<SpriteSheet> {
+        self.sprite_sheets[&archetype.texture].clone()
+    }
+
+    // Ids of the classes a party can be made of, in a stable order.
+    pub fn party_classes(&self) -> Vec<&str> {
+        let mut classes: Vec<&str> = self.archetypes
+            .iter()
+            .filter(|(_, a)| a.ai == AiProfile::Party)
+            .map(|(id, _)| id.as_str())
+            .collect();
+        classes.sort();
+        classes
+    }
+
+    // Picks a hostile archetype for a random enemy group,
+    // weighted by `spawn_weight`.
+    pub fn random_enemy<R: Rng>(&self, rng: &mut R) -> Option<&str> {
+        let candidates = self.archetypes
+            .iter()
+            .filter(|(_, a)| a.ai != AiProfile::Party && a.spawn_weight > 0)
+            .map(|(id, a)| (id, a.spawn_weight))
+            .collect();
+        pick_weighted(rng, candidates)
+    }
+
+    // Picks a class for someone waiting to be recruited,
+    // skipping any the party already has or that are still locked.
+    pub fn random_recruit<R: Rng>(&self, rng: &mut R, roster: &PartyRoster, locked: &[String]) -> Option<&str> {
+        let candidates = self.archetypes
+            .iter()
+            .filter(|(id, a)| a.ai == AiProfile::Party && !roster.contains(id) && !locked.contains(id))
+            .map(|(id, _)| (id, 1))
+            .collect();
+        pick_weighted(rng, candidates)
+    }
+}
+
+fn pick_weighted<'a, R: Rng>(rng: &mut R, mut candidates: Vec<(&'a String, u32)>) -> Option<&'a str> {
+    // HashMap order is random, keep the roll reproducible for a given rng.
+    candidates.sort();
+
+    let total: u32 = candidates.iter().map(|(_, w)| w).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut roll = rng.gen_range(0, total);
+    for (id, weight) in candidates {
+        if roll < weight {
+            return Some(id);
+        }
+        roll -= weight;
+    }
+    None
+}
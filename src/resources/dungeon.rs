@@ -0,0 +1,100 @@
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+// The run's seed and how deep the party has gone. Everything random
+// about a floor (its layout, what spawns on it and what they drop)
+// is drawn from `rng`, which starts over from the seed on every floor,
+// so the same seed always gives the same dungeon. Rolls made during
+// play (hits, crits, initiative) come from `roll_rng` instead, so they
+// don't shift what later floors look like.
+pub struct Dungeon {
+    pub seed: u32,
+    pub depth: u32,
+    pub rng: XorShiftRng,
+}
+
+impl Dungeon {
+    pub fn new(seed: u32) -> Dungeon {
+        Dungeon {
+            seed: seed,
+            depth: 1,
+            rng: floor_rng(seed, 1),
+        }
+    }
+
+    pub fn descend(&mut self) {
+        self.depth += 1;
+        self.rng = floor_rng(self.seed, self.depth);
+    }
+
+    // Picks up a saved game where it left off.
+    pub fn resume(seed: u32, depth: u32, rng_seed: [u32; 4]) -> Dungeon {
+        Dungeon {
+            seed: seed,
+            depth: depth,
+            rng: seeded(rng_seed).0,
+        }
+    }
+
+    // The rng's state can't be read back out, so to save it the rng
+    // starts over from a seed drawn from itself, which is returned.
+    pub fn reseed(&mut self) -> [u32; 4] {
+        let (rng, rng_seed) = seeded(self.rng.gen());
+        self.rng = rng;
+        rng_seed
+    }
+
+    // The rng for a roll made during play, by one unit against another
+    // (by save marker id, 0 for none) on a given tick. Rolls don't depend
+    // on the order units are gone through in, which changes with how the
+    // world's entities happen to be numbered, so replays come out the same.
+    pub fn roll_rng(&self, tick: u64, by: u64, against: u64) -> XorShiftRng {
+        let a = mix(u64::from(self.seed) << 32 | u64::from(self.depth));
+        let b = mix(a ^ tick);
+        let c = mix(b ^ by);
+        let d = mix(c ^ against);
+        seeded([d as u32, (d >> 32) as u32, c as u32, (c >> 32) as u32]).0
+    }
+
+    // The merchant's stock for this floor. Nothing else draws from it, so
+    // whether or when the shop is opened changes no other roll.
+    pub fn shop_rng(&self) -> XorShiftRng {
+        let a = mix(u64::from(self.seed) << 32 | u64::from(self.depth));
+        let b = mix(a ^ SHOP_STREAM);
+        seeded([b as u32, (b >> 32) as u32, a as u32, (a >> 32) as u32]).0
+    }
+
+    // A stream of its own for something that draws a lot of numbers
+    // up front, e.g. map generation, taken from the dungeon's rng.
+    pub fn fork(&mut self) -> XorShiftRng {
+        self.rng.gen()
+    }
+}
+
+impl Default for Dungeon {
+    fn default() -> Self {
+        Dungeon::new(rand::random())
+    }
+}
+
+// Set apart from the ticks `roll_rng` is keyed on.
+const SHOP_STREAM: u64 = u64::MAX;
+
+fn floor_rng(seed: u32, depth: u32) -> XorShiftRng {
+    XorShiftRng::from_seed([seed, depth, 0x9e37_79b9, 0x7f4a_7c15])
+}
+
+// An rng started from `seed`, and the seed it was actually started
+// from. XorShift can't be seeded with all zeroes, so one bit is set.
+fn seeded(mut seed: [u32; 4]) -> (XorShiftRng, [u32; 4]) {
+    seed[0] |= 1;
+    (XorShiftRng::from_seed(seed), seed)
+}
+
+// Scrambles the bits of `x`, so seeds that are close together
+// give rolls that have nothing to do with each other.
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,43 @@
+use amethyst::ecs::prelude::Entity;
+
+use crate::components::stats::DamageType;
+
+// Everything of note that happens during a fight. Combat code only
+// writes these to the `EventChannel<CombatEvent>` resource; anything
+// that wants to react to a fight (logs, sounds, floating text, loot,
+// quests) registers a reader on the channel instead.
+#[derive(Clone, Debug)]
+pub enum CombatEvent {
+    AttackStarted {
+        attacker: Entity,
+        target: Entity,
+    },
+    Hit {
+        attacker: Entity,
+        target: Entity,
+        critical: bool,
+    },
+    Miss {
+        attacker: Entity,
+        target: Entity,
+    },
+    // Health was actually taken away. `source` is None for damage
+    // that no unit is directly responsible for.
+    Damage {
+        source: Option<Entity>,
+        target: Entity,
+        amount: f32,
+        damage_type: DamageType,
+        critical: bool,
+        remaining_health: f32,
+    },
+    Heal {
+        source: Option<Entity>,
+        target: Entity,
+        amount: f32,
+    },
+    Death {
+        entity: Entity,
+        killer: Option<Entity>,
+    },
+}
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::components::items::{Item, ItemKind};
+
+// How many items the party can carry between them.
+pub const INVENTORY_CAPACITY: usize = 12;
+
+// Items carried by the party as a whole rather than by anyone in it.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+    pub gold: u32,
+}
+
+impl Inventory {
+    pub fn is_full(&self) -> bool {
+        self.items.len() >= INVENTORY_CAPACITY
+    }
+
+    // Hands the item back if there is no room for it.
+    pub fn add(&mut self, item: Item) -> Result<(), Item> {
+        if self.is_full() {
+            return Err(item);
+        }
+        self.items.push(item);
+        Ok(())
+    }
+
+    // Removes the first key that opens `lock`.
+    pub fn take_key(&mut self, lock: &str) -> Option<Item> {
+        let index = self.items.iter().position(|item| match &item.kind {
+            ItemKind::Key(key) => key == lock,
+            _ => false,
+        })?;
+        self.take(index)
+    }
+
+    pub fn take(&mut self, index: usize) -> Option<Item> {
+        if index < self.items.len() {
+            Some(self.items.remove(index))
+        } else {
+            None
+        }
+    }
+}
+
+// Every base item, read from `assets/items/base_items.ron` and keyed by id.
+#[derive(Default)]
+pub struct ItemDatabase {
+    items: HashMap<String, Item>,
+}
+
+impl ItemDatabase {
+    pub fn load(path: &Path) -> amethyst::Result<ItemDatabase> {
+        let items: HashMap<String, Item> = ron::de::from_str(&fs::read_to_string(path)?)?;
+        Ok(ItemDatabase { items })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Item> {
+        self.items.get(id)
+    }
+
+    // Every equipment base item, sorted by id.
+    pub fn equipment(&self) -> Vec<Item> {
+        let mut ids: Vec<&String> = self.items
+            .iter()
+            .filter(|(_, item)| item.equip_slot().is_some())
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort();
+        ids.into_iter().map(|id| self.items[id].clone()).collect()
+    }
+
+    // A fresh copy of the item with the given id.
+    pub fn create(&self, id: &str) -> Option<Item> {
+        self.items.get(id).cloned()
+    }
+}
@@ -0,0 +1,113 @@
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::components::items::Item;
+use crate::resources::affixes::{ItemGenerator, depth_scale};
+use crate::resources::items::ItemDatabase;
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum LootDrop {
+    Nothing,
+    // Id of a base item, dropped as it is.
+    Item(String),
+    // Random equipment from the item generator, scaled to the floor.
+    Generated,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LootEntry {
+    pub drop: LootDrop,
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LootTable {
+    // How many times `entries` is rolled.
+    #[serde(default = "default_rolls")]
+    pub rolls: u32,
+    #[serde(default)]
+    pub entries: Vec<LootEntry>,
+    // Items that always drop, on top of the rolls.
+    #[serde(default)]
+    pub guaranteed: Vec<String>,
+    // Inclusive range of gold dropped.
+    #[serde(default)]
+    pub gold: (u32, u32),
+}
+
+fn default_rolls() -> u32 {
+    1
+}
+
+impl LootTable {
+    // The items and gold dropped by one kill or chest on the given floor.
+    pub fn roll<R: Rng>(
+        &self,
+        rng: &mut R,
+        items: &ItemDatabase,
+        generator: &ItemGenerator,
+        depth: u32,
+    ) -> (Vec<Item>, u32) {
+        let mut ids: Vec<&String> = self.guaranteed.iter().collect();
+        let mut generated: Vec<Item> = vec![];
+
+        let total: u32 = self.entries.iter().map(|e| e.weight).sum();
+        if total > 0 {
+            for _ in 0..self.rolls {
+                let mut roll = rng.gen_range(0, total);
+                for entry in self.entries.iter() {
+                    if roll < entry.weight {
+                        match &entry.drop {
+                            LootDrop::Item(id) => ids.push(id),
+                            LootDrop::Generated => generated.extend(generator.generate(rng, depth)),
+                            LootDrop::Nothing => {},
+                        }
+                        break;
+                    }
+                    roll -= entry.weight;
+                }
+            }
+        }
+
+        let mut dropped: Vec<Item> = ids
+            .into_iter()
+            .filter_map(|id| {
+                let item = items.create(id);
+                if item.is_none() {
+                    println!("unknown item in loot table : {}", id);
+                }
+                item
+            })
+            .collect();
+        dropped.extend(generated);
+
+        let (min_gold, max_gold) = self.gold;
+        let gold = if max_gold > min_gold {
+            rng.gen_range(min_gold, max_gold + 1)
+        } else {
+            min_gold
+        };
+
+        (dropped, (gold as f32 * depth_scale(depth)) as u32)
+    }
+}
+
+// Every loot table, read from `assets/items/loot_tables.ron` and keyed by id.
+#[derive(Default)]
+pub struct LootTables {
+    tables: HashMap<String, LootTable>,
+}
+
+impl LootTables {
+    pub fn load(path: &Path) -> amethyst::Result<LootTables> {
+        let tables: HashMap<String, LootTable> = ron::de::from_str(&fs::read_to_string(path)?)?;
+        Ok(LootTables { tables })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&LootTable> {
+        self.tables.get(id)
+    }
+}
@@ -1,19 +1,33 @@
 use amethyst::{
     assets::Handle,
+    core::transform::Parent,
+    ecs::{prelude::Entity, Join},
     prelude::*,
     renderer::{
         SpriteSheet,
     },
 };
 
-use rand::Rng;
+use rand::{Rng, XorShiftRng};
 use std::cmp;
 
 use crate::{ARENA_WIDTH, ARENA_HEIGHT, TILE_WIDTH, calc_tile_center, load_sprite_sheet};
 
-use crate::components::npc::{NpcVariant, initialize_npc};
-use crate::components::tile::{TileVariant, FloorVariant, initialize_tile, WallDecoration};
-use crate::components::scenary::initialize_campfire;
+use crate::components::abilities::{Abilities, Ability, Energy};
+use crate::components::items::Equipment;
+use crate::components::loot::{Chest, LootSheet, initialize_chest};
+use crate::components::npc::{Npc, Attackable, Attacker, Boss, PlayerControlled, Recruitable, initialize_npc};
+use crate::components::progression::{Experience, TalentEffect};
+use crate::components::projectile::Projectile;
+use crate::components::save::Persistent;
+use crate::components::stats::{Resistances, Stats};
+use crate::components::tile::{Tile, TileDecoration, TileVariant, FloorVariant, initialize_tile, WallDecoration};
+use crate::components::scenary::{initialize_campfire, initialize_merchant, initialize_stairs};
+use crate::resources::archetypes::ArchetypeRegistry;
+use crate::resources::dungeon::Dungeon;
+use crate::resources::party::{PartyRoster, SavedMember};
+use crate::resources::run::{MetaProgression, RunConfig};
+use crate::resources::turns::Encounter;
 
 // Convert px dimensions to tile dimensions.
 pub const UNIT_WIDTH: usize = (ARENA_WIDTH / TILE_WIDTH) as usize;
@@ -32,19 +46,132 @@ pub const MIN_ROOM_Y: usize = 2;
 pub const MAX_ROOM_X: usize = UNIT_WIDTH - MIN_ROOM_X;
 pub const MAX_ROOM_Y: usize = UNIT_HEIGHT - MIN_ROOM_Y - 4;
 
+// Out of 10, on top of a room's enemy spawn chance.
+pub const RECRUIT_SPAWN_CHANCE: usize = 2;
+// Out of 10, the chance of an ordinary room having a chest in it.
+pub const CHEST_SPAWN_CHANCE: usize = 3;
+
 type Map = [[TileVariant; UNIT_HEIGHT]; UNIT_WIDTH];
 
+// Which tiles of the finished map block movement, kept around as a
+// resource so systems can do cheap grid lookups instead of joining
+// over every `Tile` entity.
+pub struct MapGrid {
+    blocking: Vec<bool>,
+    // The finished map, column by column, for saving.
+    tiles: Vec<TileVariant>,
+    // Which tiles the party has seen so far, in the same order.
+    explored: Vec<bool>,
+}
+
+impl Default for MapGrid {
+    fn default() -> Self {
+        MapGrid {
+            blocking: vec![true; UNIT_WIDTH * UNIT_HEIGHT],
+            tiles: vec![TileVariant::Ceiling; UNIT_WIDTH * UNIT_HEIGHT],
+            explored: vec![false; UNIT_WIDTH * UNIT_HEIGHT],
+        }
+    }
+}
+
+impl MapGrid {
+    fn from_map(map: &Map) -> Self {
+        let mut blocking = vec![true; UNIT_WIDTH * UNIT_HEIGHT];
+        let mut tiles = vec![TileVariant::Ceiling; UNIT_WIDTH * UNIT_HEIGHT];
+        for (x, row) in map.iter().enumerate() {
+            for (y, tile) in row.iter().enumerate() {
+                blocking[x * UNIT_HEIGHT + y] = match tile {
+                    TileVariant::Floor(_) => false,
+                    _ => true,
+                };
+                tiles[x * UNIT_HEIGHT + y] = *tile;
+            }
+        }
+        MapGrid { blocking, tiles, explored: vec![false; UNIT_WIDTH * UNIT_HEIGHT] }
+    }
+
+    pub fn tiles(&self) -> &[TileVariant] {
+        &self.tiles
+    }
+
+    pub fn explored(&self) -> &[bool] {
+        &self.explored
+    }
+
+    // Puts back what a save had explored. Saves from before it was
+    // kept, or for a different sized map, start out unexplored.
+    pub fn set_explored(&mut self, explored: Vec<bool>) {
+        if explored.len() == self.explored.len() {
+            self.explored = explored;
+        }
+    }
+
+    pub fn is_explored(&self, x: usize, y: usize) -> bool {
+        x < UNIT_WIDTH && y < UNIT_HEIGHT && self.explored[x * UNIT_HEIGHT + y]
+    }
+
+    // Marks every tile within `radius` tiles of the given px coordinates
+    // as explored.
+    pub fn explore(&mut self, coords: [f32; 2], radius: usize) {
+        let x = (coords[0].max(0.0) / TILE_WIDTH) as usize;
+        let y = (coords[1].max(0.0) / TILE_WIDTH) as usize;
+        let min_x = x.saturating_sub(radius);
+        let min_y = y.saturating_sub(radius);
+        let max_x = cmp::min(x + radius, UNIT_WIDTH - 1);
+        let max_y = cmp::min(y + radius, UNIT_HEIGHT - 1);
+        for tile_x in min_x..=max_x {
+            for tile_y in min_y..=max_y {
+                let dx = tile_x as i64 - x as i64;
+                let dy = tile_y as i64 - y as i64;
+                if dx * dx + dy * dy <= (radius * radius) as i64 {
+                    self.explored[tile_x * UNIT_HEIGHT + tile_y] = true;
+                }
+            }
+        }
+    }
+
+    pub fn is_blocking(&self, x: usize, y: usize) -> bool {
+        if x >= UNIT_WIDTH || y >= UNIT_HEIGHT {
+            return true;
+        }
+        self.blocking[x * UNIT_HEIGHT + y]
+    }
+
+    // Where a blocking tile is, in px, drawn the way `initialize_tile`
+    // draws it: tall tiles stick up into the tile above.
+    pub fn hit_box(&self, x: usize, y: usize) -> Option<[[f32; 2]; 2]> {
+        if x >= UNIT_WIDTH || y >= UNIT_HEIGHT || !self.is_blocking(x, y) {
+            return None;
+        }
+        let lift = (self.tiles[x * UNIT_HEIGHT + y].tile_dimensions()[1] - TILE_WIDTH) / 2.0;
+        let center = [calc_tile_center(x), calc_tile_center(y) + lift];
+        Some([
+            [center[0] - TILE_WIDTH / 2.0, center[1] - TILE_WIDTH / 2.0],
+            [center[0] + TILE_WIDTH / 2.0, center[1] + TILE_WIDTH / 2.0],
+        ])
+    }
+
+    // Is the tile under the given px coordinates blocking?
+    pub fn is_blocking_at(&self, coords: [f32; 2]) -> bool {
+        if coords[0] < 0.0 || coords[1] < 0.0 {
+            return true;
+        }
+        self.is_blocking((coords[0] / TILE_WIDTH) as usize, (coords[1] / TILE_WIDTH) as usize)
+    }
+}
+
 pub struct MapBuilder {
     auras_sheet_handle: Handle<SpriteSheet>,
     floor_sheet_handle: Handle<SpriteSheet>,
     ceiling_sheet_handle: Handle<SpriteSheet>,
     wall_sheet_handle: Handle<SpriteSheet>,
-    npc_sheet_handle: Handle<SpriteSheet>,
-    orc_sheet_handle: Handle<SpriteSheet>,
     scenary_sheet_handle: Handle<SpriteSheet>,
     health_bar_handle: Handle<SpriteSheet>,
+    status_icons_handle: Handle<SpriteSheet>,
     map: Map,
     rooms: Vec<Room>,
+    // Taken from the dungeon's rng, so a seed always builds the same floor.
+    rng: XorShiftRng,
 }
 
 impl MapBuilder {
@@ -62,12 +189,12 @@ impl MapBuilder {
             floor_sheet_handle: load_sprite_sheet(world, "texture/floor.png", "texture/floor.ron"),
             ceiling_sheet_handle: load_sprite_sheet(world, "texture/ceiling.png", "texture/ceiling.ron"),
             wall_sheet_handle: load_sprite_sheet(world, "texture/walls.png", "texture/walls.ron"),
-            npc_sheet_handle: load_sprite_sheet(world, "texture/warrior.png", "texture/warrior.ron"),
-            orc_sheet_handle: load_sprite_sheet(world, "texture/orc.png", "texture/orc.ron"),
             scenary_sheet_handle: load_sprite_sheet(world, "texture/campfire.png", "texture/campfire.ron"),
             health_bar_handle: load_sprite_sheet(world, "texture/healthbar.png", "texture/healthbar.ron"),
+            status_icons_handle: load_sprite_sheet(world, "texture/status_icons.png", "texture/status_icons.ron"),
             map: map,
             rooms: vec![],
+            rng: world.write_resource::<Dungeon>().fork(),
         }
 
         // world.insert(resource);
@@ -80,6 +207,7 @@ impl MapBuilder {
         self.carve_walls();
         self.detail_map();
         self.initialize_map(world);
+        world.insert(MapGrid::from_map(&self.map));
         self.spawn_npcs(world);
     } 
 
@@ -102,16 +230,18 @@ impl MapBuilder {
             potential_spawn_rooms.push(Room {
                 dimensions: Rect::new(s[0], s[1], spawn_room_size[0], spawn_room_size[1]),
                 safe: true,
+                boss: false,
                 enemy_spawn_chance: 0,
             });
         }
 
-        let index = rand::thread_rng().gen_range(0, potential_spawn_rooms.len());
+        let index = self.rng.gen_range(0, potential_spawn_rooms.len());
         let determined_spawn = potential_spawn_rooms[index];
         self.rooms.push(determined_spawn);
 
         for _ in 0..(MAX_ROOMS - 1) {
-            self.rooms.push(Room::random());
+            let room = Room::random(&mut self.rng);
+            self.rooms.push(room);
         }
 
         // Define boss room
@@ -119,6 +249,7 @@ impl MapBuilder {
         for room in potential_spawn_rooms.iter() {
             let mut clone = room.clone();
             clone.safe = false;
+            clone.boss = true;
             clone.enemy_spawn_chance = 10;
             let spawn_center = determined_spawn.center();
             let r_center = room.center();
@@ -143,7 +274,7 @@ impl MapBuilder {
             if i > 0 {
                 let prev_room = &self.rooms[i - 1];
                 let prev_center = prev_room.center();
-                if rand::random() {
+                if self.rng.gen() {
                     create_h_tunnel(prev_center[0], curr_center[0], prev_center[1], &mut self.map);
                     create_v_tunnel(prev_center[1], curr_center[1], curr_center[0], &mut self.map);
                 } else {
@@ -264,7 +395,7 @@ impl MapBuilder {
                         }
 
                         // Have some % chance to spawn a wall detail. Otherwise, leave it blank.
-                        let n = rand::thread_rng().gen_range(0, 20);
+                        let n = self.rng.gen_range(0, 20);
                         let detail = match n {
                             9 => Some(WallDecoration::BlackFlag1),
                             8 => Some(WallDecoration::RedFlag1),
@@ -304,52 +435,284 @@ impl MapBuilder {
         }
     }
 
-    fn spawn_npcs(&self, world: &mut World) {
-        for r in self.rooms.iter() {
+    // Lays out a saved floor instead of building a new one.
+    // Only the tiles are placed, everything on them is up to the caller.
+    pub fn restore(&mut self, world: &mut World, tiles: &[TileVariant]) -> amethyst::Result<()> {
+        if tiles.len() != UNIT_WIDTH * UNIT_HEIGHT {
+            return Err(amethyst::Error::from_string(format!(
+                "expected {} tiles, found {}", UNIT_WIDTH * UNIT_HEIGHT, tiles.len()
+            )));
+        }
+        for (x, row) in self.map.iter_mut().enumerate() {
+            for (y, tile) in row.iter_mut().enumerate() {
+                *tile = tiles[x * UNIT_HEIGHT + y];
+            }
+        }
+        self.initialize_map(world);
+        world.insert(MapGrid::from_map(&self.map));
+        Ok(())
+    }
+
+    fn spawn_npcs(&mut self, world: &mut World) {
+        let mut recruit_placed = false;
+        for r in self.rooms.clone().iter() {
             let center = r.center();
             let coords = [calc_tile_center(center[0]), calc_tile_center(center[1])];
             // Safe (non-enemy) spawn room?
             if r.safe {
-                self.spawn_npc(world, NpcVariant::Normal, coords);
+                self.spawn_party(world, coords);
+                self.spawn_merchant(world, coords);
+            } else if r.boss {
+                self.spawn_boss_group(world, coords);
+                // The boss drops the key to its own hoard.
+                self.spawn_chest(world, "boss_chest", Some("rusty"), coords);
+                // There is no way down from the last floor, only the boss.
+                let last_floor = world.read_resource::<RunConfig>().floors;
+                if world.read_resource::<Dungeon>().depth < last_floor {
+                    self.spawn_stairs(world, coords);
+                }
             } else {
-                let roll = rand::thread_rng().gen_range(0, 10);
+                if self.rng.gen_range(0, 10) < CHEST_SPAWN_CHANCE {
+                    self.spawn_chest(world, "chest", None, coords);
+                }
+
+                let roll = self.rng.gen_range(0, 10);
                 if roll < r.enemy_spawn_chance {
                     self.spawn_enemy_group(world, coords);
+                } else if !recruit_placed && roll < r.enemy_spawn_chance + RECRUIT_SPAWN_CHANCE {
+                    recruit_placed = self.spawn_recruit(world, coords);
                 }
             }
         }
     }
 
-    fn spawn_enemy_group(&self, world: &mut World, center: [f32; 2]) {
+    // Spawns everyone on the party roster around `center`, picking up
+    // where they left off if they have been on an earlier floor.
+    fn spawn_party(&self, world: &mut World, center: [f32; 2]) {
+        let members = world.read_resource::<PartyRoster>().members.clone();
+        let spacing = 25.0;
+
+        for (i, member) in members.iter().enumerate() {
+            let offset = (i as f32 - (members.len() as f32 - 1.0) * 0.5) * spacing;
+            let entity = match self.spawn_npc(world, &member.archetype, [center[0] + offset, center[1]]) {
+                Some(entity) => entity,
+                None => continue,
+            };
+
+            if let Some(saved) = &member.saved {
+                restore_member(world, entity, &member.archetype, saved);
+            }
+
+            world.write_resource::<PartyRoster>().members[i].entity = Some(entity);
+        }
+    }
+
+    // Places someone in the room who will join the party if talked to.
+    // Returns false if there are no classes left to recruit.
+    fn spawn_recruit(&mut self, world: &mut World, coords: [f32; 2]) -> bool {
+        let id = {
+            let registry = world.read_resource::<ArchetypeRegistry>();
+            let roster = world.read_resource::<PartyRoster>();
+            let locked = world.read_resource::<MetaProgression>().locked_classes(&world.read_resource::<RunConfig>());
+            registry
+                .random_recruit(&mut self.rng, &roster, &locked)
+                .map(|id| id.to_string())
+        };
+        let id = match id {
+            Some(id) => id,
+            None => return false,
+        };
+
+        let entity = match self.spawn_npc(world, &id, coords) {
+            Some(entity) => entity,
+            None => return false,
+        };
+
+        world.write_storage::<PlayerControlled>().remove(entity);
+        if let Some(npc) = world.write_storage::<Npc>().get_mut(entity) {
+            npc.selected = false;
+        }
+        if let Err(e) = world.write_storage::<Recruitable>().insert(entity, Recruitable) {
+            println!("error adding recruit : {}", e);
+        }
+        true
+    }
+
+    fn spawn_enemy_group(&mut self, world: &mut World, center: [f32; 2]) {
         // Spawn a random (with limit) of enemies around a scenary object,
         // centered at `center`.
         let group_radius = 25.0;
-        initialize_campfire(world, self.scenary_sheet_handle.clone(), center);
-        self.spawn_npc(world, NpcVariant::Orc, [center[0], center[1] + group_radius]);
-        if rand::random() {
-            self.spawn_npc(world, NpcVariant::Orc, [center[0] + group_radius, center[1]]);
+        self.spawn_campfire(world, center);
+        self.spawn_random_enemy(world, [center[0], center[1] + group_radius]);
+        if self.rng.gen() {
+            self.spawn_random_enemy(world, [center[0] + group_radius, center[1]]);
         }
 
-        if rand::random() {
-            self.spawn_npc(world, NpcVariant::Orc, [center[0] - group_radius, center[1]]);
+        if self.rng.gen() {
+            self.spawn_random_enemy(world, [center[0] - group_radius, center[1]]);
+        }
+    }
+
+    fn spawn_boss_group(&mut self, world: &mut World, center: [f32; 2]) {
+        let group_radius = 25.0;
+        self.spawn_campfire(world, center);
+        if let Some(boss) = self.spawn_npc(world, "orc_chief", [center[0], center[1] + group_radius]) {
+            if let Err(e) = world.write_storage::<Boss>().insert(boss, Boss) {
+                println!("error marking the boss : {}", e);
+            }
+        }
+        self.spawn_random_enemy(world, [center[0] + group_radius, center[1]]);
+        self.spawn_random_enemy(world, [center[0] - group_radius, center[1]]);
+    }
+
+    pub fn spawn_campfire(&self, world: &mut World, coords: [f32; 2]) -> Entity {
+        initialize_campfire(world, self.scenary_sheet_handle.clone(), coords)
+    }
+
+    // The merchant waits a little above where the party arrives.
+    fn spawn_merchant(&self, world: &mut World, center: [f32; 2]) {
+        let handle = world.read_resource::<LootSheet>().0.clone();
+        initialize_merchant(world, handle, [center[0], center[1] + 3.0 * TILE_WIDTH]);
+    }
+
+    // The stairs down wait above the boss's campfire.
+    fn spawn_stairs(&self, world: &mut World, center: [f32; 2]) {
+        let handle = world.read_resource::<LootSheet>().0.clone();
+        initialize_stairs(world, handle, [center[0], center[1] + 3.0 * TILE_WIDTH]);
+    }
+
+    // Chests sit just below the middle of the room, out of
+    // the way of whatever else is placed there.
+    fn spawn_chest(&self, world: &mut World, loot_table: &str, key: Option<&str>, center: [f32; 2]) {
+        let handle = world.read_resource::<LootSheet>().0.clone();
+        let chest = Chest {
+            loot_table: loot_table.to_string(),
+            key: key.map(|k| k.to_string()),
+            opened: false,
+        };
+        initialize_chest(world, handle, chest, [center[0], center[1] - 1.5 * TILE_WIDTH]);
+    }
+
+    fn spawn_random_enemy(&mut self, world: &mut World, coords: [f32; 2]) {
+        let id = world
+            .read_resource::<ArchetypeRegistry>()
+            .random_enemy(&mut self.rng)
+            .map(|id| id.to_string());
+
+        match id {
+            Some(id) => {
+                self.spawn_npc(world, &id, coords);
+            },
+            None => println!("no enemy archetypes to spawn from"),
         }
     }
 
-    fn spawn_npc(&self, world: &mut World, variant: NpcVariant, coords: [f32; 2]) {
-        let handle = match variant {
-            NpcVariant::Normal => self.npc_sheet_handle.clone(),
-            NpcVariant::Orc => self.orc_sheet_handle.clone(),
+    pub fn spawn_npc(&self, world: &mut World, id: &str, coords: [f32; 2]) -> Option<Entity> {
+        let (archetype, handle) = {
+            let registry = world.read_resource::<ArchetypeRegistry>();
+            match registry.get(id) {
+                Some(archetype) => (archetype.clone(), registry.sprite_sheet(archetype)),
+                None => {
+                    println!("unknown archetype : {}", id);
+                    return None;
+                },
+            }
         };
 
-        initialize_npc(
+        Some(initialize_npc(
             world,
-            variant,
+            &archetype,
             handle,
             self.auras_sheet_handle.clone(),
             self.health_bar_handle.clone(),
+            self.status_icons_handle.clone(),
             coords,
-        );
+        ))
+    }
+}
+
+// Puts a unit back the way it was saved, on top of the fresh
+// numbers it was spawned with.
+pub fn restore_member(world: &mut World, entity: Entity, archetype: &str, saved: &SavedMember) {
+    if let Some(attackable) = world.write_storage::<Attackable>().get_mut(entity) {
+        attackable.health = saved.health;
+        attackable.total_health = saved.total_health;
+    }
+    if let Some(energy) = world.write_storage::<Energy>().get_mut(entity) {
+        energy.mana = saved.mana;
+        energy.stamina = saved.stamina;
+    }
+    if let Some(attacker) = world.write_storage::<Attacker>().get_mut(entity) {
+        attacker.attack = saved.attack;
+    }
+    if let Err(e) = world.write_storage::<Stats>().insert(entity, saved.stats) {
+        println!("error restoring unit stats : {}", e);
+    }
+    if let Err(e) = world.write_storage::<Resistances>().insert(entity, saved.resistances) {
+        println!("error restoring unit resistances : {}", e);
+    }
+
+    // Only party members level up and wear equipment.
+    if !world.read_storage::<Experience>().contains(entity) {
+        return;
+    }
+    if let Err(e) = world.write_storage::<Equipment>().insert(entity, saved.equipment.clone()) {
+        println!("error restoring party member equipment : {}", e);
+    }
+
+    // Passive talents and equipment are already part of the saved stats,
+    // only the abilities they unlocked need adding back.
+    let talent_abilities: Vec<Ability> = {
+        let registry = world.read_resource::<ArchetypeRegistry>();
+        saved.experience.talents
+            .iter()
+            .filter_map(|id| registry.talent(archetype, id))
+            .filter_map(|talent| match &talent.effect {
+                TalentEffect::Ability(ability) => Some(ability.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+    let mut abilities = world.write_storage::<Abilities>();
+    if !abilities.contains(entity) {
+        if let Err(e) = abilities.insert(entity, Abilities::default()) {
+            println!("error restoring party member abilities : {}", e);
+        }
+    }
+    if let Some(abilities) = abilities.get_mut(entity) {
+        abilities.slots.extend(talent_abilities);
+    }
+    if let Err(e) = world.write_storage::<Experience>().insert(entity, saved.experience.clone()) {
+        println!("error restoring party member experience : {}", e);
+    }
+}
+
+// Removes the floor and everything on it.
+pub fn clear_floor(world: &mut World) {
+    let doomed: Vec<Entity> = {
+        let entities = world.entities();
+        let markers = world.read_storage::<Persistent>();
+        let parents = world.read_storage::<Parent>();
+        let tiles = world.read_storage::<Tile>();
+        let decorations = world.read_storage::<TileDecoration>();
+        let projectiles = world.read_storage::<Projectile>();
+        (&entities)
+            .join()
+            .filter(|e| {
+                markers.contains(*e)
+                    || parents.get(*e).map_or(false, |p| markers.contains(p.entity))
+                    || tiles.contains(*e)
+                    || decorations.contains(*e)
+                    || projectiles.contains(*e)
+            })
+            .collect()
+    };
+    if let Err(e) = world.delete_entities(&doomed) {
+        println!("error clearing the floor : {}", e);
     }
+    world.maintain();
+    // Any fight in progress was on the floor that is gone.
+    world.insert(Encounter::default());
 }
 
 fn initialize_room(
@@ -391,18 +754,20 @@ impl Rect {
 struct Room {
     dimensions: Rect,
     safe: bool,
+    boss: bool,
     enemy_spawn_chance: usize,
 }
 
 impl Room {
-    fn random() -> Room {
-        let width = rand::thread_rng().gen_range(MIN_ROOM_WIDTH, MAX_ROOM_WIDTH + 1);
-        let height = rand::thread_rng().gen_range(MIN_ROOM_HEIGHT, MAX_ROOM_HEIGHT + 1);
-        let x = rand::thread_rng().gen_range(MIN_ROOM_X, MAX_ROOM_X - width);
-        let y = rand::thread_rng().gen_range(MIN_ROOM_Y, MAX_ROOM_Y - height);
+    fn random<R: Rng>(rng: &mut R) -> Room {
+        let width = rng.gen_range(MIN_ROOM_WIDTH, MAX_ROOM_WIDTH + 1);
+        let height = rng.gen_range(MIN_ROOM_HEIGHT, MAX_ROOM_HEIGHT + 1);
+        let x = rng.gen_range(MIN_ROOM_X, MAX_ROOM_X - width);
+        let y = rng.gen_range(MIN_ROOM_Y, MAX_ROOM_Y - height);
         Room {
             dimensions: Rect::new(x, y, width, height),
             safe: false,
+            boss: false,
             enemy_spawn_chance: 3,
         }
     }
@@ -1 +1,16 @@
-pub mod map;
\ No newline at end of file
+pub mod map;
+pub mod events;
+pub mod supplies;
+pub mod archetypes;
+pub mod party;
+pub mod items;
+pub mod loot;
+pub mod dungeon;
+pub mod affixes;
+pub mod shop;
+pub mod save;
+pub mod run;
+pub mod simulation;
+pub mod turns;
+pub mod replay;
+pub mod orders;
@@ -0,0 +1,38 @@
+use amethyst::ecs::prelude::Entity;
+use serde::{Deserialize, Serialize};
+
+use crate::components::items::EquipSlot;
+
+// Clicks on the shop screen.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Trade {
+    // Buys the merchant's item in this row.
+    Buy(usize),
+    // Sells the party's item in this row.
+    Sell(usize),
+}
+
+// Clicks on the inventory screen, made for one party member.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum GearChange {
+    // Equips or uses the party's item in this row.
+    Use(usize),
+    TakeOff(EquipSlot),
+}
+
+// Orders given through `CommandSystem` that are carried out by other
+// systems, which take them from here on the next tick the game runs.
+#[derive(Default)]
+pub struct PartyOrders {
+    // Who wants to rest at a nearby campfire, if the party was told to.
+    pub rest: Option<Vec<Entity>>,
+    // Who was told to drink a healing potion.
+    pub drink: Vec<Entity>,
+    pub end_turn: bool,
+    // Orders played back from a replay that were given on the screens
+    // when it was recorded. Live, the screens carry them out themselves.
+    pub trades: Vec<Trade>,
+    pub gear: Vec<(Entity, GearChange)>,
+    // Who learned the talent in which row.
+    pub talents: Vec<(Entity, usize)>,
+}
@@ -0,0 +1,115 @@
+use amethyst::ecs::prelude::Entity;
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::components::progression::Experience;
+use crate::components::items::Equipment;
+use crate::components::stats::{Resistances, Stats};
+
+// The most characters the player can have in their party at once.
+pub const MAX_PARTY_SIZE: usize = 4;
+
+// What a party member carries over from one floor to the next.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedMember {
+    pub health: f32,
+    pub total_health: f32,
+    pub stats: Stats,
+    pub mana: f32,
+    pub stamina: f32,
+    pub attack: f32,
+    pub resistances: Resistances,
+    pub experience: Experience,
+    pub equipment: Equipment,
+}
+
+#[derive(Clone, Debug)]
+pub struct PartyMember {
+    // Id of the member's archetype, i.e. its class.
+    pub archetype: String,
+    // The member's unit on the current floor, if it has been spawned.
+    pub entity: Option<Entity>,
+    // Set once the member has been spawned, fresh recruits
+    // start out with their archetype's numbers.
+    pub saved: Option<SavedMember>,
+}
+
+impl PartyMember {
+    pub fn new(archetype: &str) -> PartyMember {
+        PartyMember {
+            archetype: archetype.to_string(),
+            entity: None,
+            saved: None,
+        }
+    }
+}
+
+// Everyone in the player's party, in the order they joined.
+// Unlike the units themselves the roster outlives a floor, so it
+// is what the next floor's party gets spawned from.
+#[derive(Default)]
+pub struct PartyRoster {
+    pub members: Vec<PartyMember>,
+}
+
+impl PartyRoster {
+    pub fn from_starting_party(starting: &StartingParty) -> PartyRoster {
+        let members = starting.members
+            .iter()
+            .take(MAX_PARTY_SIZE)
+            .map(|archetype| PartyMember::new(archetype))
+            .collect();
+        PartyRoster { members }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.members.len() >= MAX_PARTY_SIZE
+    }
+
+    // Adds a unit that is already on the floor to the party.
+    // Returns false if there is no room for it.
+    pub fn recruit(&mut self, archetype: &str, entity: Entity) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        let mut member = PartyMember::new(archetype);
+        member.entity = Some(entity);
+        self.members.push(member);
+        true
+    }
+
+    pub fn contains(&self, archetype: &str) -> bool {
+        self.members.iter().any(|m| m.archetype == archetype)
+    }
+
+    pub fn member_mut(&mut self, entity: Entity) -> Option<&mut PartyMember> {
+        self.members.iter_mut().find(|m| m.entity == Some(entity))
+    }
+}
+
+// The classes a new game starts with, read from `config/party.ron`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StartingParty {
+    pub members: Vec<String>,
+    // Ids of the items the party starts out carrying.
+    #[serde(default)]
+    pub items: Vec<String>,
+}
+
+impl Default for StartingParty {
+    fn default() -> Self {
+        StartingParty {
+            members: vec!["warrior".to_string()],
+            items: vec![],
+        }
+    }
+}
+
+impl StartingParty {
+    pub fn load(path: &Path) -> amethyst::Result<StartingParty> {
+        let starting: StartingParty = ron::de::from_str(&fs::read_to_string(path)?)?;
+        Ok(starting)
+    }
+}
@@ -0,0 +1,182 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use amethyst::{prelude::*, utils::application_root_dir};
+use serde::{Deserialize, Serialize};
+
+use crate::resources::orders::{GearChange, Trade};
+use crate::resources::run::start_run;
+use crate::resources::turns::CombatMode;
+
+// Where an ability was aimed, with units named by their marker id.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RecordedAim {
+    Caster,
+    Unit(u64),
+    Point([f32; 2]),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RecordedOrder {
+    Move([f32; 2]),
+    Attack(u64),
+    Ability {
+        slot: usize,
+        aim: RecordedAim,
+    },
+    Rest,
+    DrinkPotion,
+    EndTurn,
+    // The marker of the pickup to fetch.
+    Pickup(u64),
+    Trade(Trade),
+    Gear(GearChange),
+    // The row of the talent learned.
+    LearnTalent(usize),
+}
+
+// An order given through `CommandSystem`, and the tick it was given on.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RecordedCommand {
+    pub tick: u64,
+    pub units: Vec<u64>,
+    pub order: RecordedOrder,
+}
+
+// Everything needed to play a run over again: how it was started, and
+// every order the player gave, on the map or through the screens.
+// Saves carry the recording so far, so loading one keeps recording.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u32,
+    pub party: Vec<String>,
+    #[serde(default)]
+    pub mode: CombatMode,
+    pub commands: Vec<RecordedCommand>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> amethyst::Result<Recording> {
+        let recording: Recording = ron::de::from_str(&fs::read_to_string(path)?)?;
+        Ok(recording)
+    }
+
+    pub fn save(&self, path: &Path) -> amethyst::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, ron::ser::to_string_pretty(self, Default::default())?)?;
+        Ok(())
+    }
+}
+
+// Every new run is recorded, and a recording can be played back in
+// place of the player's orders.
+pub enum Replay {
+    Off,
+    Recording(Recording),
+    Playing {
+        recording: Recording,
+        // The first command that hasn't been played yet.
+        next: usize,
+    },
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Replay::Off
+    }
+}
+
+impl Replay {
+    pub fn is_playing(&self) -> bool {
+        match self {
+            Replay::Playing { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn record(&mut self, command: RecordedCommand) {
+        if let Replay::Recording(recording) = self {
+            recording.commands.push(command);
+        }
+    }
+
+    // The commands to play on `tick`. Once they have all been played
+    // the replay is over and the player takes over from there.
+    pub fn due(&mut self, tick: u64) -> Vec<RecordedCommand> {
+        let (due, finished) = match self {
+            Replay::Playing { recording, next } => {
+                let start = *next;
+                while *next < recording.commands.len() && recording.commands[*next].tick <= tick {
+                    *next += 1;
+                }
+                (recording.commands[start..*next].to_vec(), *next == recording.commands.len())
+            },
+            _ => return vec![],
+        };
+        if finished {
+            println!("replay finished");
+            *self = Replay::Off;
+        }
+        due
+    }
+}
+
+pub fn replay_path() -> amethyst::Result<PathBuf> {
+    Ok(application_root_dir()?.join("replays").join("last_run.ron"))
+}
+
+// The recording to play instead of showing the main menu, passed
+// as `--replay <file>` when starting the game.
+pub fn replay_arg() -> Option<PathBuf> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--replay");
+    args.next()?;
+    args.next().map(PathBuf::from)
+}
+
+// Starts the recorded run over and plays its orders back.
+pub fn start_replay(world: &mut World, recording: Recording) {
+    start_run(world, &recording.party, recording.mode, recording.seed);
+    world.insert(Replay::Playing { recording, next: 0 });
+}
+
+// Writes out the run being recorded, if any, and stops recording.
+pub fn finish_recording(world: &mut World) {
+    let replay = std::mem::replace(&mut *world.write_resource::<Replay>(), Replay::Off);
+    if let Replay::Recording(recording) = replay {
+        if let Err(e) = replay_path().and_then(|path| recording.save(&path)) {
+            println!("error saving the replay : {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(tick: u64) -> RecordedCommand {
+        RecordedCommand {
+            tick,
+            units: vec![1],
+            order: RecordedOrder::Move([0.0, 0.0]),
+        }
+    }
+
+    #[test]
+    fn commands_are_played_on_their_tick() {
+        let mut replay = Replay::Playing {
+            recording: Recording {
+                commands: vec![command(0), command(3), command(3), command(7)],
+                ..Recording::default()
+            },
+            next: 0,
+        };
+        assert_eq!(replay.due(0).len(), 1);
+        assert_eq!(replay.due(2).len(), 0);
+        assert_eq!(replay.due(3).len(), 2);
+        assert!(replay.is_playing());
+        assert_eq!(replay.due(8).len(), 1);
+        assert!(!replay.is_playing());
+    }
+}
@@ -0,0 +1,410 @@
+use amethyst::{ecs::saveload::SimpleMarkerAllocator, prelude::*, utils::application_root_dir};
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::components::save::SaveMarker;
+use crate::resources::dungeon::Dungeon;
+use crate::resources::items::{Inventory, ItemDatabase};
+use crate::resources::map::{MapBuilder, clear_floor};
+use crate::resources::orders::PartyOrders;
+use crate::resources::party::{PartyRoster, StartingParty};
+use crate::resources::replay::{Recording, Replay, finish_recording};
+use crate::resources::save::delete_run_saves;
+use crate::resources::shop::Shop;
+use crate::resources::simulation::FixedStep;
+use crate::resources::supplies::HealingPotions;
+use crate::resources::turns::CombatMode;
+
+// Party members downed when the party takes the stairs
+// get back up on the next floor with this much of their health.
+const DESCEND_HEALTH_FRACTION: f32 = 0.1;
+// Renown earned for every floor reached, every kill and for winning.
+const RENOWN_PER_FLOOR: u32 = 20;
+const RENOWN_PER_KILL: u32 = 2;
+const RENOWN_FOR_VICTORY: u32 = 100;
+
+// What a run looks like, read from `config/run.ron`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RunConfig {
+    // Killing the boss on the last floor wins the run.
+    pub floors: u32,
+    // Unlocked for good once enough renown has been earned over all runs.
+    #[serde(default)]
+    pub unlocks: Vec<Unlock>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            floors: 5,
+            unlocks: vec![],
+        }
+    }
+}
+
+impl RunConfig {
+    pub fn load(path: &Path) -> amethyst::Result<RunConfig> {
+        let config: RunConfig = ron::de::from_str(&fs::read_to_string(path)?)?;
+        Ok(config)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Unlock {
+    pub renown: u32,
+    pub reward: Reward,
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub enum Reward {
+    // The class can be recruited. Classes no unlock mentions always can.
+    Class(String),
+    // Every run starts with this much more gold.
+    StartingGold(u32),
+    // Every run starts with one of these in the inventory.
+    StartingItem(String),
+}
+
+impl Reward {
+    pub fn describe(&self) -> String {
+        match self {
+            Reward::Class(id) => format!("the {} can now be recruited", id),
+            Reward::StartingGold(gold) => format!("runs start with {} more gold", gold),
+            Reward::StartingItem(id) => format!("runs start with a {}", id.replace('_', " ")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RunOutcome {
+    Victory,
+    Defeat,
+}
+
+// What happened over the course of a run, shown when it ends.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct RunStats {
+    pub deepest_floor: u32,
+    pub kills: u32,
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    pub gold_earned: u32,
+    // Seconds spent in the dungeon.
+    pub time: f32,
+}
+
+impl RunStats {
+    pub fn renown(&self, outcome: RunOutcome) -> u32 {
+        let victory = match outcome {
+            RunOutcome::Victory => RENOWN_FOR_VICTORY,
+            RunOutcome::Defeat => 0,
+        };
+        self.deepest_floor * RENOWN_PER_FLOOR + self.kills * RENOWN_PER_KILL + victory
+    }
+}
+
+// The run in progress. Systems only flag what should happen next,
+// the game state acts on it since it needs the whole world.
+#[derive(Default)]
+pub struct Run {
+    pub stats: RunStats,
+    pub outcome: Option<RunOutcome>,
+    // What the run earned towards the next ones, for the game over screen.
+    pub rewards: Vec<String>,
+    // The party has reached the stairs.
+    pub descend: bool,
+    pub mode: CombatMode,
+}
+
+impl Run {
+    // Ends the run, unless it is already over.
+    pub fn finish(&mut self, outcome: RunOutcome) {
+        if self.outcome.is_none() {
+            self.outcome = Some(outcome);
+        }
+    }
+
+    // How the run went, for the game over and victory screens.
+    pub fn summary(&self) -> Vec<String> {
+        let stats = &self.stats;
+        let minutes = (stats.time / 60.0) as u32;
+        let mut lines = vec![
+            format!("deepest floor : {}", stats.deepest_floor),
+            format!("enemies slain : {}", stats.kills),
+            format!("damage dealt : {}", stats.damage_dealt.round()),
+            format!("damage taken : {}", stats.damage_taken.round()),
+            format!("gold found : {}", stats.gold_earned),
+            format!("time : {}m {}s", minutes, stats.time as u32 % 60),
+            String::new(),
+        ];
+        lines.extend(self.rewards.iter().cloned());
+        lines
+    }
+}
+
+// Progress kept from one run to the next, in its own file so
+// it outlives the run saves that are deleted when a run ends.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct MetaProgression {
+    pub runs: u32,
+    pub victories: u32,
+    pub best_floor: u32,
+    pub renown: u32,
+}
+
+pub fn meta_path() -> amethyst::Result<PathBuf> {
+    Ok(application_root_dir()?.join("saves").join("meta.ron"))
+}
+
+impl MetaProgression {
+    // A missing file is a player's first run.
+    pub fn load(path: &Path) -> amethyst::Result<MetaProgression> {
+        if !path.exists() {
+            return Ok(MetaProgression::default());
+        }
+        let meta: MetaProgression = ron::de::from_str(&fs::read_to_string(path)?)?;
+        Ok(meta)
+    }
+
+    pub fn save(&self, path: &Path) -> amethyst::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, ron::ser::to_string_pretty(self, Default::default())?)?;
+        Ok(())
+    }
+
+    // Adds a finished run, returning the renown it earned
+    // and whatever that was enough to unlock.
+    pub fn record<'a>(&mut self, stats: &RunStats, outcome: RunOutcome, config: &'a RunConfig) -> (u32, Vec<&'a Reward>) {
+        let earned = stats.renown(outcome);
+        let before = self.renown;
+
+        self.runs += 1;
+        if outcome == RunOutcome::Victory {
+            self.victories += 1;
+        }
+        self.best_floor = self.best_floor.max(stats.deepest_floor);
+        self.renown += earned;
+
+        let unlocked = config.unlocks
+            .iter()
+            .filter(|u| u.renown > before && u.renown <= self.renown)
+            .map(|u| &u.reward)
+            .collect();
+        (earned, unlocked)
+    }
+
+    pub fn rewards<'a>(&self, config: &'a RunConfig) -> Vec<&'a Reward> {
+        config.unlocks
+            .iter()
+            .filter(|u| u.renown <= self.renown)
+            .map(|u| &u.reward)
+            .collect()
+    }
+
+    // Classes that still need unlocking before they can be recruited.
+    pub fn locked_classes(&self, config: &RunConfig) -> Vec<String> {
+        config.unlocks
+            .iter()
+            .filter(|u| u.renown > self.renown)
+            .filter_map(|u| match &u.reward {
+                Reward::Class(id) => Some(id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// The starting party from `config/party.ron`, whose classes
+// are what the new run screen suggests.
+pub fn load_starting_party() -> StartingParty {
+    let path = match application_root_dir() {
+        Ok(app_root) => app_root.join("config").join("party.ron"),
+        Err(e) => {
+            println!("error finding the starting party, using the default : {}", e);
+            return StartingParty::default();
+        },
+    };
+    match StartingParty::load(&path) {
+        Ok(starting_party) => starting_party,
+        Err(e) => {
+            println!("error reading starting party, using the default : {}", e);
+            StartingParty::default()
+        },
+    }
+}
+
+// Sets up a fresh party of the given classes on the first floor
+// of the dungeon built from `seed`, with whatever earlier runs have
+// unlocked, and starts recording the run.
+pub fn start_run(world: &mut World, members: &[String], mode: CombatMode, seed: u32) {
+    finish_recording(world);
+    clear_floor(world);
+    // Markers are handed out from scratch, so the same seed always gives
+    // everything the same marker and replays can refer to units by them.
+    world.insert(SimpleMarkerAllocator::<SaveMarker>::default());
+
+    let dungeon = Dungeon::new(seed);
+    println!("dungeon seed : {}", dungeon.seed);
+    world.insert(dungeon);
+    world.insert(FixedStep::default());
+    world.insert(Replay::Recording(Recording {
+        seed,
+        party: members.to_vec(),
+        mode,
+        commands: vec![],
+    }));
+
+    let mut starting_party = load_starting_party();
+    starting_party.members = members.to_vec();
+    world.insert(PartyRoster::from_starting_party(&starting_party));
+    world.insert(HealingPotions::default());
+    world.insert(PartyOrders::default());
+
+    let mut items = starting_party.items;
+    let mut inventory = Inventory::default();
+    {
+        let config = world.read_resource::<RunConfig>();
+        let meta = world.read_resource::<MetaProgression>();
+        for reward in meta.rewards(&config) {
+            match reward {
+                Reward::StartingGold(gold) => inventory.gold += gold,
+                Reward::StartingItem(id) => items.push(id.clone()),
+                Reward::Class(_) => {},
+            }
+        }
+    }
+    {
+        let item_database = world.read_resource::<ItemDatabase>();
+        for id in items.iter() {
+            match item_database.create(id) {
+                Some(item) => {
+                    if inventory.add(item).is_err() {
+                        println!("no room for starting item : {}", id);
+                    }
+                },
+                None => println!("unknown starting item : {}", id),
+            }
+        }
+    }
+    world.insert(inventory);
+
+    {
+        let mut shop = world.write_resource::<Shop>();
+        shop.stock.clear();
+        shop.stocked_depth = 0;
+    }
+
+    world.insert(Run {
+        stats: RunStats {
+            deepest_floor: 1,
+            ..RunStats::default()
+        },
+        mode,
+        ..Run::default()
+    });
+
+    let mut builder = MapBuilder::initialize(world);
+    builder.build_map(world);
+}
+
+// Writes a finished run into the meta progression and
+// deletes its saves.
+pub fn end_run(world: &mut World) {
+    let (outcome, stats) = {
+        let run = world.read_resource::<Run>();
+        match run.outcome {
+            Some(outcome) => (outcome, run.stats.clone()),
+            None => return,
+        }
+    };
+
+    let (earned, unlocked) = {
+        let config = world.read_resource::<RunConfig>();
+        let mut meta = world.write_resource::<MetaProgression>();
+        let (earned, unlocked) = meta.record(&stats, outcome, &config);
+        let unlocked: Vec<String> = unlocked.iter().map(|r| r.describe()).collect();
+        match meta_path().and_then(|path| meta.save(&path)) {
+            Ok(()) => {},
+            Err(e) => println!("error saving meta progression : {}", e),
+        }
+        (earned, unlocked)
+    };
+
+    delete_run_saves(world.read_resource::<Dungeon>().seed);
+
+    let mut run = world.write_resource::<Run>();
+    run.rewards = vec![format!("renown earned : {}", earned)];
+    run.rewards.extend(unlocked);
+}
+
+// Takes the party down to the next floor.
+pub fn descend(world: &mut World) {
+    clear_floor(world);
+
+    let depth = {
+        let mut dungeon = world.write_resource::<Dungeon>();
+        dungeon.descend();
+        dungeon.depth
+    };
+    println!("the party descends to floor {}.", depth);
+
+    for member in world.write_resource::<PartyRoster>().members.iter_mut() {
+        member.entity = None;
+        if let Some(saved) = member.saved.as_mut() {
+            if saved.health <= 0.0 {
+                saved.health = saved.total_health * DESCEND_HEALTH_FRACTION;
+            }
+        }
+    }
+
+    {
+        let mut run = world.write_resource::<Run>();
+        run.descend = false;
+        run.stats.deepest_floor = run.stats.deepest_floor.max(depth);
+    }
+
+    let mut builder = MapBuilder::initialize(world);
+    builder.build_map(world);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RunConfig {
+        RunConfig {
+            floors: 3,
+            unlocks: vec![
+                Unlock { renown: 50, reward: Reward::Class("rogue".to_string()) },
+                Unlock { renown: 100, reward: Reward::StartingGold(25) },
+            ],
+        }
+    }
+
+    #[test]
+    fn renown_unlocks_rewards_once() {
+        let config = config();
+        let mut meta = MetaProgression::default();
+        assert_eq!(meta.locked_classes(&config), vec!["rogue".to_string()]);
+
+        let stats = RunStats { deepest_floor: 2, kills: 10, ..RunStats::default() };
+        let (earned, unlocked) = meta.record(&stats, RunOutcome::Defeat, &config);
+        assert_eq!(earned, 60);
+        assert_eq!(unlocked, vec![&Reward::Class("rogue".to_string())]);
+        assert!(meta.locked_classes(&config).is_empty());
+
+        let (_, unlocked) = meta.record(&stats, RunOutcome::Defeat, &config);
+        assert_eq!(unlocked, vec![&Reward::StartingGold(25)]);
+        assert_eq!(meta.rewards(&config).len(), 2);
+    }
+
+    #[test]
+    fn victories_are_worth_more() {
+        let stats = RunStats { deepest_floor: 3, ..RunStats::default() };
+        assert!(stats.renown(RunOutcome::Victory) > stats.renown(RunOutcome::Defeat));
+    }
+}
@@ -0,0 +1,454 @@
+use amethyst::{
+    core::Transform,
+    ecs::{
+        error::NoError,
+        prelude::{Entities, Entity, LazyUpdate},
+        saveload::{DeserializeComponents, Marker, MarkerAllocator, SerializeComponents, SimpleMarkerAllocator},
+        Join, ReadStorage, Write, WriteStorage,
+    },
+    prelude::*,
+    renderer::SpriteRender,
+    utils::application_root_dir,
+};
+
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::components::abilities::Energy;
+use crate::components::items::{Equipment, Item};
+use crate::components::loot::{Chest, LootSheet, Pickup, CHEST_OPEN_SPRITE, initialize_chest};
+use crate::components::npc::{
+    Npc, ArchetypeId, Attackable, Attacker, Boss, CanTarget, Dead, Downed, PlayerControlled, Recruitable, Recruiting,
+};
+use crate::components::progression::Experience;
+use crate::components::save::{Persisted, PersistedKind, Persistent, SaveMarker, UnitState};
+use crate::components::scenary::{CampFire, Merchant, Stairs, initialize_merchant, initialize_stairs};
+use crate::components::stats::{Resistances, Stats};
+use crate::components::tile::TileVariant;
+use crate::resources::dungeon::Dungeon;
+use crate::resources::items::Inventory;
+use crate::resources::map::{MapBuilder, MapGrid, clear_floor, restore_member};
+use crate::resources::party::{PartyMember, PartyRoster, SavedMember};
+use crate::resources::replay::{Recording, Replay, finish_recording};
+use crate::resources::run::{Run, RunStats};
+use crate::resources::turns::CombatMode;
+use crate::resources::shop::Shop;
+use crate::resources::simulation::FixedStep;
+use crate::systems::loot::spawn_pickup;
+
+// Bumped whenever the save format changes, older saves are refused.
+pub const SAVE_VERSION: u32 = 1;
+pub const SAVE_SLOTS: usize = 3;
+
+// Everything in a save that isn't an entity on the floor.
+const HEADER_FILE: &str = "game.ron";
+// The marked entities on the floor.
+const ENTITIES_FILE: &str = "entities.ron";
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedRosterMember {
+    pub archetype: String,
+    // The marker of the member's unit, if it was on the floor.
+    pub marker: Option<u64>,
+    pub saved: Option<SavedMember>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveHeader {
+    pub version: u32,
+    pub seed: u32,
+    pub depth: u32,
+    // What the dungeon's rng was reseeded with when the game was saved.
+    pub rng_seed: [u32; 4],
+    // The floor, column by column, and which of its tiles were explored.
+    pub tiles: Vec<TileVariant>,
+    #[serde(default)]
+    pub explored: Vec<bool>,
+    pub roster: Vec<SavedRosterMember>,
+    pub inventory: Inventory,
+    pub shop_stock: Vec<Item>,
+    pub shop_depth: u32,
+    #[serde(default)]
+    pub stats: RunStats,
+    #[serde(default)]
+    pub mode: CombatMode,
+    // The tick the game was saved on, and the run's recording up to it.
+    #[serde(default)]
+    pub tick: u64,
+    #[serde(default)]
+    pub recording: Option<Recording>,
+}
+
+// Just enough of a header to tell which version wrote it.
+#[derive(Deserialize)]
+struct SaveVersion {
+    version: u32,
+}
+
+pub fn slot_dir(slot: usize) -> amethyst::Result<PathBuf> {
+    Ok(application_root_dir()?.join("saves").join(format!("slot_{}", slot + 1)))
+}
+
+fn read_header(path: &Path) -> amethyst::Result<SaveHeader> {
+    let contents = fs::read_to_string(path)?;
+    let version: SaveVersion = ron::de::from_str(&contents)?;
+    if version.version != SAVE_VERSION {
+        return Err(amethyst::Error::from_string(format!(
+            "save is version {}, expected {}", version.version, SAVE_VERSION
+        )));
+    }
+    Ok(ron::de::from_str(&contents)?)
+}
+
+// A line about what's in a slot for the save menu, `None` if it's empty.
+pub fn describe_slot(slot: usize) -> Option<String> {
+    let path = slot_dir(slot).ok()?.join(HEADER_FILE);
+    if !path.exists() {
+        return None;
+    }
+    match read_header(&path) {
+        Ok(header) => Some(format!(
+            "floor {} - {} in party - {} gold",
+            header.depth,
+            header.roster.len(),
+            header.inventory.gold,
+        )),
+        Err(e) => Some(format!("unreadable save : {}", e)),
+    }
+}
+
+// Permadeath: once a run is over, its saves go with it.
+pub fn delete_run_saves(seed: u32) {
+    for slot in 0..SAVE_SLOTS {
+        let dir = match slot_dir(slot) {
+            Ok(dir) => dir,
+            Err(_) => continue,
+        };
+        let same_run = read_header(&dir.join(HEADER_FILE)).map_or(false, |header| header.seed == seed);
+        if same_run {
+            if let Err(e) = fs::remove_dir_all(&dir) {
+                println!("error deleting save slot {} : {}", slot + 1, e);
+            }
+        }
+    }
+}
+
+pub fn save_game(world: &mut World, slot: usize) -> amethyst::Result<()> {
+    if world.read_resource::<Run>().outcome.is_some() {
+        return Err(amethyst::Error::from_string("the run is over"));
+    }
+    let dir = slot_dir(slot)?;
+    fs::create_dir_all(&dir)?;
+
+    let rng_seed = world.write_resource::<Dungeon>().reseed();
+    let header = {
+        let dungeon = world.read_resource::<Dungeon>();
+        let roster = world.read_resource::<PartyRoster>();
+        let shop = world.read_resource::<Shop>();
+        let markers = world.read_storage::<Persistent>();
+        SaveHeader {
+            version: SAVE_VERSION,
+            seed: dungeon.seed,
+            depth: dungeon.depth,
+            rng_seed: rng_seed,
+            tiles: world.read_resource::<MapGrid>().tiles().to_vec(),
+            explored: world.read_resource::<MapGrid>().explored().to_vec(),
+            roster: roster.members
+                .iter()
+                .map(|m| SavedRosterMember {
+                    archetype: m.archetype.clone(),
+                    marker: m.entity.and_then(|e| markers.get(e)).map(|marker| marker.id()),
+                    saved: m.saved.clone(),
+                })
+                .collect(),
+            inventory: world.read_resource::<Inventory>().clone(),
+            shop_stock: shop.stock.clone(),
+            shop_depth: shop.stocked_depth,
+            stats: world.read_resource::<Run>().stats.clone(),
+            mode: world.read_resource::<Run>().mode,
+            tick: world.read_resource::<FixedStep>().tick,
+            recording: match &*world.read_resource::<Replay>() {
+                Replay::Recording(recording) => Some(recording.clone()),
+                _ => None,
+            },
+        }
+    };
+
+    let marked: Vec<Entity> = (&world.entities(), &world.read_storage::<Persistent>())
+        .join()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in marked {
+        if let Some(persisted) = capture(world, entity) {
+            world.write_storage::<Persisted>().insert(entity, persisted)?;
+        }
+    }
+
+    let entities = world.exec(
+        |(entities, persisted, markers): (Entities, ReadStorage<Persisted>, ReadStorage<Persistent>)| {
+            let mut serializer = ron::ser::Serializer::new(Some(PrettyConfig::default()), false);
+            SerializeComponents::<NoError, Persistent>::serialize(&(&persisted,), &entities, &markers, &mut serializer)
+                .map(|_| serializer.into_output_string())
+        },
+    );
+    world.write_storage::<Persisted>().clear();
+
+    fs::write(dir.join(HEADER_FILE), ron::ser::to_string_pretty(&header, PrettyConfig::default())?)?;
+    fs::write(dir.join(ENTITIES_FILE), entities?)?;
+    Ok(())
+}
+
+// What gets saved of a marked entity. Corpses are left behind.
+fn capture(world: &World, entity: Entity) -> Option<Persisted> {
+    if world.read_storage::<Dead>().contains(entity) {
+        return None;
+    }
+    let position = world
+        .read_storage::<Transform>()
+        .get(entity)
+        .map(|t| [t.translation().x, t.translation().y])?;
+
+    let kind = if let Some(chest) = world.read_storage::<Chest>().get(entity) {
+        PersistedKind::Chest(chest.clone())
+    } else if let Some(pickup) = world.read_storage::<Pickup>().get(entity) {
+        PersistedKind::Pickup(pickup.clone())
+    } else if let Some(campfire) = world.read_storage::<CampFire>().get(entity) {
+        PersistedKind::CampFire { rested: campfire.rested }
+    } else if world.read_storage::<Merchant>().contains(entity) {
+        PersistedKind::Merchant
+    } else if world.read_storage::<Stairs>().contains(entity) {
+        PersistedKind::Stairs
+    } else {
+        let archetype = world.read_storage::<ArchetypeId>().get(entity)?.0.clone();
+        let attackables = world.read_storage::<Attackable>();
+        let attackable = attackables.get(entity)?;
+        let energies = world.read_storage::<Energy>();
+        let energy = energies.get(entity);
+        let npc_selected = world.read_storage::<Npc>().get(entity).map_or(false, |npc| npc.selected);
+
+        PersistedKind::Unit(UnitState {
+            archetype: archetype,
+            member: SavedMember {
+                health: attackable.health,
+                total_health: attackable.total_health,
+                stats: world.read_storage::<Stats>().get(entity).cloned().unwrap_or_default(),
+                mana: energy.map_or(0.0, |e| e.mana),
+                stamina: energy.map_or(0.0, |e| e.stamina),
+                attack: world.read_storage::<Attacker>().get(entity).map_or(0.0, |a| a.attack),
+                resistances: world.read_storage::<Resistances>().get(entity).cloned().unwrap_or_default(),
+                experience: world.read_storage::<Experience>().get(entity).cloned().unwrap_or_default(),
+                equipment: world.read_storage::<Equipment>().get(entity).cloned().unwrap_or_default(),
+            },
+            selected: npc_selected,
+            player_controlled: world.read_storage::<PlayerControlled>().contains(entity),
+            recruitable: world.read_storage::<Recruitable>().contains(entity),
+            downed: world.read_storage::<Downed>().contains(entity),
+            boss: world.read_storage::<Boss>().contains(entity),
+        })
+    };
+
+    Some(Persisted {
+        position: position,
+        kind: kind,
+        target: world.read_storage::<CanTarget>().get(entity).and_then(|t| t.target),
+        recruiting: world.read_storage::<Recruiting>().get(entity).map(|r| (r.target, r.move_coords)),
+    })
+}
+
+// Replaces the current floor with the one saved in `slot`.
+pub fn load_game(world: &mut World, slot: usize) -> amethyst::Result<()> {
+    let dir = slot_dir(slot)?;
+    let header = read_header(&dir.join(HEADER_FILE))?;
+    let entities_ron = fs::read_to_string(dir.join(ENTITIES_FILE))?;
+    // Whatever was being recorded is written out, and the loaded
+    // run's own recording carries on from the tick it was saved on.
+    finish_recording(world);
+    world.insert(FixedStep::at(header.tick));
+    world.insert(match header.recording {
+        Some(recording) => Replay::Recording(recording),
+        None => Replay::Off,
+    });
+
+    // The builder forks the old dungeon's rng, so it has to be
+    // made before the saved dungeon replaces it.
+    let mut builder = MapBuilder::initialize(world);
+    clear_floor(world);
+    builder.restore(world, &header.tiles)?;
+    world.write_resource::<MapGrid>().set_explored(header.explored);
+
+    world.insert(Dungeon::resume(header.seed, header.depth, header.rng_seed));
+    world.insert(header.inventory);
+    world.insert(Run {
+        stats: header.stats,
+        mode: header.mode,
+        ..Run::default()
+    });
+    {
+        let mut shop = world.write_resource::<Shop>();
+        shop.stock = header.shop_stock;
+        shop.stocked_depth = header.shop_depth;
+    }
+
+    // Deserializing makes a bare entity for every saved one, holding
+    // only its `Persisted`. The real ones are spawned from those.
+    world.exec(
+        |(entities, mut markers, mut allocator, persisted): (
+            Entities,
+            WriteStorage<Persistent>,
+            Write<SimpleMarkerAllocator<SaveMarker>>,
+            WriteStorage<Persisted>,
+        )| {
+            let mut deserializer = ron::de::Deserializer::from_str(&entities_ron)?;
+            DeserializeComponents::<NoError, _>::deserialize(
+                &mut (persisted,),
+                &entities,
+                &mut markers,
+                &mut allocator,
+                &mut deserializer,
+            )?;
+            deserializer.end()
+        },
+    )?;
+
+    let placeholders: Vec<Entity> = (&world.entities(), &world.read_storage::<Persistent>())
+        .join()
+        .map(|(entity, _)| entity)
+        .collect();
+    let saved: Vec<(Entity, u64, Persisted)> = {
+        let entities = world.entities();
+        let markers = world.read_storage::<Persistent>();
+        let mut persisted = world.write_storage::<Persisted>();
+        (&entities, &markers, persisted.drain())
+            .join()
+            .map(|(entity, marker, persisted)| (entity, marker.id(), persisted))
+            .collect()
+    };
+
+    let mut spawned: HashMap<Entity, Entity> = HashMap::new();
+    for (placeholder, _, persisted) in saved.iter() {
+        if let Some(entity) = respawn(world, &builder, persisted) {
+            spawned.insert(*placeholder, entity);
+        }
+    }
+
+    // Point references at the new entities.
+    for (placeholder, _, persisted) in saved.iter() {
+        let entity = match spawned.get(placeholder) {
+            Some(entity) => *entity,
+            None => continue,
+        };
+        if let Some(target) = persisted.target.and_then(|t| spawned.get(&t)) {
+            if let Some(can_target) = world.write_storage::<CanTarget>().get_mut(entity) {
+                can_target.target = Some(*target);
+            }
+        }
+        if let Some((target, move_coords)) = persisted.recruiting {
+            if let Some(target) = spawned.get(&target) {
+                world.write_storage::<Recruiting>().insert(entity, Recruiting { target: *target, move_coords })?;
+            }
+        }
+    }
+
+    let by_marker: HashMap<u64, Entity> = saved
+        .iter()
+        .filter_map(|(placeholder, id, _)| spawned.get(placeholder).map(|entity| (*id, *entity)))
+        .collect();
+    let members = header.roster
+        .into_iter()
+        .map(|m| PartyMember {
+            archetype: m.archetype,
+            entity: m.marker.and_then(|id| by_marker.get(&id).cloned()),
+            saved: m.saved,
+        })
+        .collect();
+    world.insert(PartyRoster { members });
+
+    world.delete_entities(&placeholders)?;
+    world.maintain();
+    // Respawning marked everything anew. They get their saved markers
+    // back, so replays and rolls keyed by marker go on as they would
+    // have, and the allocator moves past them for whatever comes next.
+    world.exec(
+        |(entities, mut markers, mut allocator): (
+            Entities,
+            WriteStorage<Persistent>,
+            Write<SimpleMarkerAllocator<SaveMarker>>,
+        )| -> amethyst::Result<()> {
+            for (id, entity) in by_marker.iter() {
+                markers.insert(*entity, allocator.allocate(*entity, Some(*id)))?;
+            }
+            allocator.maintain(&entities, &markers);
+            Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+// Spawns a saved entity the way it was first spawned, then puts its saved state back.
+fn respawn(world: &mut World, builder: &MapBuilder, persisted: &Persisted) -> Option<Entity> {
+    let at = persisted.position;
+    match &persisted.kind {
+        PersistedKind::Unit(unit) => {
+            let entity = builder.spawn_npc(world, &unit.archetype, at)?;
+            restore_member(world, entity, &unit.archetype, &unit.member);
+            if let Some(npc) = world.write_storage::<Npc>().get_mut(entity) {
+                npc.selected = unit.selected;
+                npc.move_coords = at;
+            }
+            if !unit.player_controlled {
+                world.write_storage::<PlayerControlled>().remove(entity);
+            }
+            if unit.recruitable {
+                if let Err(e) = world.write_storage::<Recruitable>().insert(entity, Recruitable) {
+                    println!("error restoring recruit : {}", e);
+                }
+            }
+            if unit.downed {
+                if let Err(e) = world.write_storage::<Downed>().insert(entity, Downed) {
+                    println!("error restoring downed unit : {}", e);
+                }
+            }
+            if unit.boss {
+                if let Err(e) = world.write_storage::<Boss>().insert(entity, Boss) {
+                    println!("error restoring the boss : {}", e);
+                }
+            }
+            Some(entity)
+        },
+        PersistedKind::Chest(chest) => {
+            let handle = world.read_resource::<LootSheet>().0.clone();
+            let entity = initialize_chest(world, handle, chest.clone(), at);
+            if chest.opened {
+                if let Some(render) = world.write_storage::<SpriteRender>().get_mut(entity) {
+                    render.sprite_number = CHEST_OPEN_SPRITE;
+                }
+            }
+            Some(entity)
+        },
+        PersistedKind::Pickup(pickup) => Some(spawn_pickup(
+            &world.read_resource::<LazyUpdate>(),
+            &world.entities(),
+            &world.read_resource::<LootSheet>(),
+            pickup.clone(),
+            at,
+        )),
+        PersistedKind::CampFire { rested } => {
+            let entity = builder.spawn_campfire(world, at);
+            if let Some(campfire) = world.write_storage::<CampFire>().get_mut(entity) {
+                campfire.rested = *rested;
+            }
+            Some(entity)
+        },
+        PersistedKind::Merchant => {
+            let handle = world.read_resource::<LootSheet>().0.clone();
+            Some(initialize_merchant(world, handle, at))
+        },
+        PersistedKind::Stairs => {
+            let handle = world.read_resource::<LootSheet>().0.clone();
+            Some(initialize_stairs(world, handle, at))
+        },
+    }
+}
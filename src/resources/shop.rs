@@ -0,0 +1,171 @@
+use rand::Rng;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::components::items::{Item, ItemKind};
+use crate::resources::affixes::ItemGenerator;
+use crate::resources::items::{Inventory, ItemDatabase};
+
+// Rows on the merchant's side of the shop screen.
+pub const SHOP_CAPACITY: usize = 12;
+
+// What the merchant sells, read from `assets/items/shop.ron`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShopConfig {
+    // Base items that are back on sale after every restock.
+    #[serde(default)]
+    pub staples: Vec<String>,
+    // How many pieces of random equipment are stocked, scaled to the floor.
+    #[serde(default)]
+    pub generated: usize,
+    // The fraction of an item's price the merchant pays for it.
+    #[serde(default = "default_sell_fraction")]
+    pub sell_fraction: f32,
+}
+
+fn default_sell_fraction() -> f32 {
+    0.4
+}
+
+impl Default for ShopConfig {
+    fn default() -> Self {
+        ShopConfig {
+            staples: vec![],
+            generated: 0,
+            sell_fraction: default_sell_fraction(),
+        }
+    }
+}
+
+// The merchant's stock. It travels with the party, restocking
+// once per floor. Anything the party sells stays on sale until then.
+#[derive(Default)]
+pub struct Shop {
+    pub config: ShopConfig,
+    pub stock: Vec<Item>,
+    // The floor the stock was last refreshed on, zero if never.
+    pub stocked_depth: u32,
+}
+
+impl Shop {
+    pub fn load(path: &Path) -> amethyst::Result<Shop> {
+        let config: ShopConfig = ron::de::from_str(&fs::read_to_string(path)?)?;
+        Ok(Shop {
+            config: config,
+            stock: vec![],
+            stocked_depth: 0,
+        })
+    }
+
+    pub fn needs_restock(&self, depth: u32) -> bool {
+        self.stocked_depth != depth
+    }
+
+    pub fn restock<R: Rng>(&mut self, rng: &mut R, depth: u32, items: &ItemDatabase, generator: &ItemGenerator) {
+        self.stock.clear();
+        for id in self.config.staples.iter() {
+            match items.create(id) {
+                Some(item) => self.stock.push(item),
+                None => println!("unknown item in shop : {}", id),
+            }
+        }
+        for _ in 0..self.config.generated {
+            self.stock.extend(generator.generate(rng, depth));
+        }
+        self.stock.truncate(SHOP_CAPACITY);
+        self.stocked_depth = depth;
+    }
+
+    pub fn sell_price(&self, item: &Item) -> u32 {
+        (item.price() as f32 * self.config.sell_fraction) as u32
+    }
+
+    // Moves the item at `index` into the inventory, if the party
+    // can pay for it and has room to carry it.
+    pub fn buy(&mut self, index: usize, inventory: &mut Inventory) -> Result<Item, &'static str> {
+        let price = match self.stock.get(index) {
+            Some(item) => item.price(),
+            None => return Err("nothing to buy there"),
+        };
+        if inventory.gold < price {
+            return Err("not enough gold");
+        }
+        if inventory.is_full() {
+            return Err("no room in the inventory");
+        }
+
+        let item = self.stock.remove(index);
+        inventory.gold -= price;
+        inventory.items.push(item.clone());
+        Ok(item)
+    }
+
+    // Sells the inventory item at `index` to the merchant.
+    pub fn sell(&mut self, index: usize, inventory: &mut Inventory) -> Result<u32, &'static str> {
+        match inventory.items.get(index) {
+            Some(Item { kind: ItemKind::Key(_), .. }) => return Err("the merchant has no use for keys"),
+            Some(_) => {},
+            None => return Err("nothing to sell there"),
+        }
+
+        let item = inventory.take(index).unwrap();
+        let price = self.sell_price(&item);
+        inventory.gold += price;
+        if self.stock.len() < SHOP_CAPACITY {
+            self.stock.push(item);
+        }
+        Ok(price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::items::{Affix, AffixPosition, EquipSlot, ItemBonus, Rarity};
+
+    fn sword(rarity: Rarity, affixes: Vec<Affix>) -> Item {
+        Item {
+            name: "Sword".to_string(),
+            kind: ItemKind::Equipment(EquipSlot::Weapon),
+            rarity: rarity,
+            bonus: ItemBonus::default(),
+            affixes: affixes,
+            value: 20,
+        }
+    }
+
+    fn sharp() -> Affix {
+        Affix {
+            name: "Sharp".to_string(),
+            position: AffixPosition::Prefix,
+            bonus: ItemBonus { attack: 2.0, ..ItemBonus::default() },
+        }
+    }
+
+    #[test]
+    fn rarity_and_affixes_raise_the_price() {
+        let plain = sword(Rarity::Common, vec![]).price();
+        assert_eq!(plain, 20);
+        assert!(sword(Rarity::Rare, vec![]).price() > plain);
+        assert!(sword(Rarity::Common, vec![sharp()]).price() > plain);
+    }
+
+    #[test]
+    fn buying_and_selling_moves_gold() {
+        let mut shop = Shop::default();
+        shop.stock.push(sword(Rarity::Common, vec![]));
+        let mut inventory = Inventory::default();
+
+        assert!(shop.buy(0, &mut inventory).is_err());
+        inventory.gold = 25;
+        assert!(shop.buy(0, &mut inventory).is_ok());
+        assert_eq!(inventory.gold, 5);
+        assert_eq!(inventory.items.len(), 1);
+        assert!(shop.stock.is_empty());
+
+        assert_eq!(shop.sell(0, &mut inventory), Ok(8));
+        assert_eq!(inventory.gold, 13);
+        assert_eq!(shop.stock.len(), 1);
+    }
+}
@@ -0,0 +1,150 @@
+// Whether the dungeon is being played. Gameplay systems only run while
+// it is `Running`; menus, overlays and the player's own pause stop it,
+// but everything keeps being drawn and the ui systems keep working.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Simulation {
+    Running,
+    Paused,
+}
+
+// Nothing runs until a run has been started or loaded.
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation::Paused
+    }
+}
+
+// Whether the player's selection and orders are taken. Unlike the
+// simulation they keep working while the game is paused with space,
+// so orders can be lined up and carried out once it is unpaused.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlayerInput {
+    Enabled,
+    Disabled,
+}
+
+impl Default for PlayerInput {
+    fn default() -> Self {
+        PlayerInput::Disabled
+    }
+}
+
+// How fast the game can be set to run, from slowest to fastest.
+pub const TIME_SCALES: [f32; 4] = [0.25, 0.5, 1.0, 2.0];
+const NORMAL_SPEED: usize = 2;
+
+// The player's pause and time scale while in the dungeon.
+#[derive(Clone, Copy, Debug)]
+pub struct GameSpeed {
+    pub paused: bool,
+    // Index into `TIME_SCALES`.
+    pub speed: usize,
+}
+
+impl Default for GameSpeed {
+    fn default() -> Self {
+        GameSpeed {
+            paused: false,
+            speed: NORMAL_SPEED,
+        }
+    }
+}
+
+impl GameSpeed {
+    pub fn time_scale(&self) -> f32 {
+        TIME_SCALES[self.speed]
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(TIME_SCALES.len() - 1);
+    }
+
+    // What the simulation should be doing while the game is being played.
+    pub fn simulation(&self) -> Simulation {
+        if self.paused {
+            Simulation::Paused
+        } else {
+            Simulation::Running
+        }
+    }
+}
+
+// The simulation always advances in ticks of the same length, however
+// long a frame took, so the same seed and the same orders always play
+// out the same way. Gameplay systems must use `TICK_SECONDS` instead of
+// `Time`, and roll anything that matters on `Dungeon::roll_rng` rather
+// than `thread_rng`.
+pub const TICK_SECONDS: f32 = 1.0 / 60.0;
+// After a long hitch the simulation falls behind instead of trying to
+// catch up all at once.
+const MAX_TICKS_PER_FRAME: u32 = 8;
+
+// Frame time not yet turned into ticks.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FixedStep {
+    // Ticks run since the run was started.
+    pub tick: u64,
+    accumulator: f32,
+}
+
+impl FixedStep {
+    // Picks up counting from a saved game's tick.
+    pub fn at(tick: u64) -> FixedStep {
+        FixedStep {
+            tick,
+            ..FixedStep::default()
+        }
+    }
+
+    // Adds a frame's (scaled) time and returns how many ticks to run.
+    pub fn advance(&mut self, delta: f32) -> u32 {
+        self.accumulator += delta;
+        let mut ticks = 0;
+        while self.accumulator >= TICK_SECONDS && ticks < MAX_TICKS_PER_FRAME {
+            self.accumulator -= TICK_SECONDS;
+            ticks += 1;
+        }
+        if ticks == MAX_TICKS_PER_FRAME {
+            self.accumulator = self.accumulator.min(TICK_SECONDS);
+        }
+        ticks
+    }
+
+    // How far the current frame is between the last two ticks.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / TICK_SECONDS).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_stays_within_the_time_scales() {
+        let mut speed = GameSpeed::default();
+        assert_eq!(speed.time_scale(), 1.0);
+        for _ in 0..TIME_SCALES.len() {
+            speed.slower();
+        }
+        assert_eq!(speed.time_scale(), 0.25);
+        for _ in 0..TIME_SCALES.len() {
+            speed.faster();
+        }
+        assert_eq!(speed.time_scale(), 2.0);
+    }
+
+    #[test]
+    fn fixed_step_runs_whole_ticks() {
+        let mut step = FixedStep::default();
+        assert_eq!(step.advance(TICK_SECONDS * 0.5), 0);
+        assert_eq!(step.advance(TICK_SECONDS * 2.0), 2);
+        assert!((step.alpha() - 0.5).abs() < 0.01);
+        assert_eq!(step.advance(1.0), MAX_TICKS_PER_FRAME);
+        assert!(step.alpha() <= 1.0);
+    }
+}
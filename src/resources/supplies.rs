@@ -0,0 +1,14 @@
+// Healing potions carried by the party as a whole.
+pub struct HealingPotions {
+    pub count: u32,
+    pub heal_amount: f32,
+}
+
+impl Default for HealingPotions {
+    fn default() -> Self {
+        HealingPotions {
+            count: 3,
+            heal_amount: 75.0,
+        }
+    }
+}
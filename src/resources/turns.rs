@@ -0,0 +1,191 @@
+use amethyst::ecs::prelude::Entity;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::TILE_WIDTH;
+
+// Every unit gets this many action points at the start of its turn.
+pub const ACTION_POINTS: u32 = 6;
+// Walking costs one point a tile, swings and abilities cost more.
+pub const ATTACK_COST: u32 = 3;
+pub const ABILITY_COST: u32 = 3;
+// How much real time a unit's turn stands in for, for ability cooldowns.
+pub const TURN_SECONDS: f32 = 3.0;
+// Enemies this close to the party when a fight breaks out join in.
+pub const ENCOUNTER_RADIUS: f32 = 240.0;
+
+// How fights play out, picked when a run is started.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum CombatMode {
+    RealTime,
+    TurnBased,
+}
+
+impl Default for CombatMode {
+    fn default() -> Self {
+        CombatMode::RealTime
+    }
+}
+
+impl CombatMode {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            CombatMode::RealTime => "real-time",
+            CombatMode::TurnBased => "turn-based",
+        }
+    }
+
+    pub fn toggled(&self) -> CombatMode {
+        match self {
+            CombatMode::RealTime => CombatMode::TurnBased,
+            CombatMode::TurnBased => CombatMode::RealTime,
+        }
+    }
+}
+
+// A fight in turn-based mode. Everyone in it takes turns in order of
+// initiative, and only the unit whose turn it is gets to move or act.
+#[derive(Default)]
+pub struct Encounter {
+    // Highest initiative first. Empty while there is no fight.
+    pub order: Vec<Entity>,
+    pub current: usize,
+    pub round: u32,
+    pub action_points: u32,
+    // The tile the acting unit was on last tick, to count the tiles it walks.
+    pub last_tile: [usize; 2],
+    // How long the acting unit has had nothing left to do.
+    pub idle: f32,
+    // Set while an ability the acting unit has started is paid for.
+    pub cast_paid: bool,
+    // The player asked to end the turn early.
+    pub end_turn: bool,
+}
+
+impl Encounter {
+    pub fn is_active(&self) -> bool {
+        !self.order.is_empty()
+    }
+
+    pub fn acting(&self) -> Option<Entity> {
+        self.order.get(self.current).cloned()
+    }
+
+    pub fn begin_turn(&mut self, position: [f32; 2]) {
+        self.action_points = ACTION_POINTS;
+        self.last_tile = tile_of(position);
+        self.idle = 0.0;
+        self.cast_paid = false;
+        self.end_turn = false;
+    }
+
+    // Moves on to whoever is next, starting a new round after the last.
+    pub fn advance(&mut self) {
+        self.current += 1;
+        if self.current >= self.order.len() {
+            self.current = 0;
+            self.round += 1;
+        }
+    }
+
+    // Pays for an action, returning false if there are not enough points left.
+    pub fn spend(&mut self, cost: u32) -> bool {
+        if self.action_points < cost {
+            return false;
+        }
+        self.action_points -= cost;
+        true
+    }
+
+    // Pays for every tile stepped on since the last tick, diagonal steps
+    // included. Returns false once the unit has run out of points to
+    // walk any further.
+    pub fn pay_for_walking(&mut self, tile: [usize; 2]) -> bool {
+        let steps = tiles_between(self.last_tile, tile);
+        self.last_tile = tile;
+        self.action_points = self.action_points.saturating_sub(steps);
+        self.action_points > 0
+    }
+
+    // Where a unit on `from` sent to `to` is allowed to walk to this
+    // turn: the middle of a tile, at most as many tiles away as it has
+    // points left, along the way it was sent.
+    pub fn step_limit(&self, from: [f32; 2], to: [f32; 2]) -> [f32; 2] {
+        let start = tile_of(from);
+        let end = tile_of(to);
+        let steps = tiles_between(start, end);
+        let budget = self.action_points.min(steps);
+        if steps == 0 {
+            return snap_to_grid(from);
+        }
+        let along = |a: usize, b: usize| {
+            let moved = (b as f32 - a as f32) * budget as f32 / steps as f32;
+            (a as f32 + moved.round()) as usize
+        };
+        let tile = [along(start[0], end[0]), along(start[1], end[1])];
+        [(tile[0] as f32 + 0.5) * TILE_WIDTH, (tile[1] as f32 + 0.5) * TILE_WIDTH]
+    }
+}
+
+// The tile `coords` falls in.
+pub fn tile_of(coords: [f32; 2]) -> [usize; 2] {
+    [(coords[0].max(0.0) / TILE_WIDTH) as usize, (coords[1].max(0.0) / TILE_WIDTH) as usize]
+}
+
+// How many steps apart two tiles are, a diagonal step counting as one.
+fn tiles_between(a: [usize; 2], b: [usize; 2]) -> u32 {
+    let dx = (a[0] as i64 - b[0] as i64).abs();
+    let dy = (a[1] as i64 - b[1] as i64).abs();
+    dx.max(dy) as u32
+}
+
+// A unit's place in the turn order, the higher the sooner.
+pub fn roll_initiative<R: Rng>(rng: &mut R, dexterity: f32) -> f32 {
+    rng.gen_range(0.0, 10.0) + dexterity
+}
+
+// The center of the tile `coords` falls in.
+pub fn snap_to_grid(coords: [f32; 2]) -> [f32; 2] {
+    [
+        ((coords[0] / TILE_WIDTH).floor() + 0.5) * TILE_WIDTH,
+        ((coords[1] / TILE_WIDTH).floor() + 0.5) * TILE_WIDTH,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walking_costs_a_point_a_tile() {
+        let mut encounter = Encounter::default();
+        encounter.begin_turn([1.0, 1.0]);
+        assert!(encounter.pay_for_walking([2, 1]));
+        assert_eq!(encounter.action_points, ACTION_POINTS - 2);
+        assert!(encounter.spend(ATTACK_COST));
+        assert!(!encounter.pay_for_walking([4, 3]));
+        assert_eq!(encounter.action_points, 0);
+        assert!(!encounter.spend(ATTACK_COST));
+    }
+
+    #[test]
+    fn moves_are_cut_short_at_the_last_affordable_tile() {
+        let mut encounter = Encounter::default();
+        let half = TILE_WIDTH * 0.5;
+        encounter.begin_turn([half, half]);
+        encounter.action_points = 3;
+        // Ten tiles to the right, only three of them can be walked.
+        let limit = encounter.step_limit([half, half], [TILE_WIDTH * 10.0 + 3.0, half]);
+        assert_eq!(limit, [TILE_WIDTH * 3.0 + half, half]);
+        // Close enough moves only get lined up with the tile they end on.
+        let limit = encounter.step_limit([half, half], [TILE_WIDTH * 2.0 + 1.0, 1.0]);
+        assert_eq!(limit, [TILE_WIDTH * 2.0 + half, half]);
+    }
+
+    #[test]
+    fn snaps_to_tile_centers() {
+        let half = TILE_WIDTH * 0.5;
+        assert_eq!(snap_to_grid([1.0, TILE_WIDTH + 1.0]), [half, TILE_WIDTH + half]);
+    }
+}
@@ -0,0 +1,107 @@
+use amethyst::{core::timing::Time, ecs::Dispatcher, input::InputEvent, prelude::*};
+
+use crate::resources::run::{Run, RunOutcome, descend, end_run};
+use crate::resources::simulation::{FixedStep, GameSpeed, PlayerInput, Simulation};
+use crate::resources::replay::finish_recording;
+use crate::systems::simulation::{build_simulation, run_ticks};
+use crate::states::{close_screen, handle_save_request};
+use crate::states::inventory::InventoryState;
+use crate::states::pause::PauseState;
+use crate::states::run_end::{GameOverState, VictoryState};
+
+// Playing through the dungeon. The gameplay systems only run while
+// this state is on top and the player has not paused, pushing an
+// overlay stops them along with the player's input.
+#[derive(Default)]
+pub struct GameplayState {
+    // The gameplay systems, run a fixed number of times per second.
+    simulation: Option<Dispatcher<'static, 'static>>,
+}
+
+// Hands the dungeon back to the player, still paused
+// if that is how they left it.
+fn resume(world: &mut World) {
+    let speed = *world.read_resource::<GameSpeed>();
+    *world.write_resource::<Simulation>() = speed.simulation();
+    *world.write_resource::<PlayerInput>() = PlayerInput::Enabled;
+    world.write_resource::<Time>().set_time_scale(speed.time_scale());
+}
+
+fn suspend(world: &mut World) {
+    *world.write_resource::<Simulation>() = Simulation::Paused;
+    *world.write_resource::<PlayerInput>() = PlayerInput::Disabled;
+}
+
+impl SimpleState for GameplayState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        // A new or loaded run starts unpaused, at whatever speed was picked last.
+        data.world.write_resource::<GameSpeed>().paused = false;
+        self.simulation = Some(build_simulation(data.world));
+        resume(data.world);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.simulation = None;
+        suspend(data.world);
+        finish_recording(data.world);
+    }
+
+    fn on_pause(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        suspend(data.world);
+    }
+
+    fn on_resume(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        resume(data.world);
+    }
+
+    fn handle_event(&mut self, data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        match event {
+            StateEvent::Input(InputEvent::ActionPressed(action)) => match action.as_str() {
+                // Escape closes an open screen before it pauses.
+                "pause" if close_screen(data.world) => Trans::None,
+                "pause" => Trans::Push(Box::new(PauseState::default())),
+                "inventory" => Trans::Push(Box::new(InventoryState)),
+                _ => Trans::None,
+            },
+            _ => Trans::None,
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        self.run_ticks(data.world);
+        handle_save_request(data.world);
+
+        let (descend_requested, outcome) = {
+            let run = data.world.read_resource::<Run>();
+            (run.descend, run.outcome)
+        };
+        match outcome {
+            Some(outcome) => {
+                end_run(data.world);
+                match outcome {
+                    RunOutcome::Victory => Trans::Switch(Box::new(VictoryState::default())),
+                    RunOutcome::Defeat => Trans::Switch(Box::new(GameOverState::default())),
+                }
+            },
+            None => {
+                if descend_requested {
+                    descend(data.world);
+                }
+                Trans::None
+            },
+        }
+    }
+}
+
+impl GameplayState {
+    // Catches the simulation up with the time that has passed this frame.
+    fn run_ticks(&mut self, world: &mut World) {
+        let simulation = match self.simulation.as_mut() {
+            Some(simulation) => simulation,
+            None => return,
+        };
+        let delta = world.read_resource::<Time>().delta_seconds();
+        let ticks = world.write_resource::<FixedStep>().advance(delta);
+        run_ticks(simulation, world, ticks);
+    }
+}
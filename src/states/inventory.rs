@@ -0,0 +1,38 @@
+use amethyst::{input::InputEvent, prelude::*};
+
+use crate::states::screen_is_open;
+use crate::systems::ui::{OpenScreen, Screen};
+
+// Pushed over the game while the inventory screen is open, so
+// nothing happens to the party while its gear is being changed.
+pub struct InventoryState;
+
+impl SimpleState for InventoryState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.write_resource::<OpenScreen>().screen = Some(Screen::Inventory);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let mut screen = data.world.write_resource::<OpenScreen>();
+        if screen.is_open(Screen::Inventory) {
+            screen.screen = None;
+        }
+    }
+
+    fn handle_event(&mut self, _data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        match event {
+            StateEvent::Input(InputEvent::ActionPressed(action)) if action == "inventory" || action == "pause" => {
+                Trans::Pop
+            },
+            _ => Trans::None,
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        // Another screen took its place.
+        if !screen_is_open(data.world, Screen::Inventory) {
+            return Trans::Pop;
+        }
+        Trans::None
+    }
+}
@@ -0,0 +1,154 @@
+use amethyst::{
+    assets::Loader,
+    core::transform::Transform,
+    ecs::saveload::{SimpleMarker, SimpleMarkerAllocator},
+    prelude::*,
+    renderer::Camera,
+    ui::TtfFormat,
+    utils::application_root_dir,
+};
+
+use crate::components::loot::LootSheet;
+use crate::components::projectile::ProjectileSheet;
+use crate::components::save::{Persisted, SaveMarker};
+use crate::components::tile::TileDecoration;
+use crate::resources::affixes::ItemGenerator;
+use crate::resources::archetypes::ArchetypeRegistry;
+use crate::resources::dungeon::Dungeon;
+use crate::resources::items::ItemDatabase;
+use crate::resources::loot::LootTables;
+use crate::resources::run::{MetaProgression, RunConfig, meta_path};
+use crate::resources::replay::{Recording, replay_arg, start_replay};
+use crate::resources::shop::Shop;
+use crate::resources::simulation::FixedStep;
+use crate::states::gameplay::GameplayState;
+use crate::states::main_menu::MainMenuState;
+use crate::systems::{
+    hotbar::initialize_hotbar,
+    inventory::initialize_inventory_screen,
+    progression::initialize_talent_screen,
+    save::initialize_save_menu,
+    shop::initialize_shop_screen,
+    simulation::build_simulation,
+    speed::initialize_speed_indicator,
+    turns::initialize_turn_indicator,
+    ui::UiFont,
+};
+use crate::{load_sprite_sheet, ARENA_HEIGHT, ARENA_WIDTH};
+
+// Reads everything the game needs once, before the main menu.
+pub struct LoadingState;
+
+impl SimpleState for LoadingState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        load_resources(world);
+
+        // `GameplayState` builds its own copy of the gameplay systems,
+        // this one is only built so everything they use is registered
+        // before the first floor is spawned or a game is loaded.
+        build_simulation(world);
+
+        initialize_camera(world);
+        initialize_hotbar(world);
+        initialize_talent_screen(world);
+        initialize_inventory_screen(world);
+        initialize_shop_screen(world);
+        initialize_save_menu(world);
+        initialize_speed_indicator(world);
+        initialize_turn_indicator(world);
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        // Started to watch a replay, which goes straight into the run.
+        if let Some(path) = replay_arg() {
+            match Recording::load(&path) {
+                Ok(recording) => {
+                    start_replay(data.world, recording);
+                    return Trans::Switch(Box::new(GameplayState::default()));
+                },
+                Err(e) => println!("error reading replay {} : {}", path.display(), e),
+            }
+        }
+        Trans::Switch(Box::new(MainMenuState::default()))
+    }
+}
+
+// Everything the gameplay systems need that comes from files. Nothing
+// in here needs a window, so the headless simulation loads it too.
+pub fn load_resources(world: &mut World) {
+    let app_root = application_root_dir().expect("failed to find the application root");
+
+    // Everything spawned on the floor is marked for saving.
+    world.register::<SimpleMarker<SaveMarker>>();
+    world.register::<Persisted>();
+    world.register::<TileDecoration>();
+    world.insert(SimpleMarkerAllocator::<SaveMarker>::default());
+
+    let archetypes = ArchetypeRegistry::load(world, &app_root.join("assets").join("archetypes"))
+        .expect("failed to load archetypes");
+    world.insert(archetypes);
+
+    let item_database = ItemDatabase::load(&app_root.join("assets").join("items").join("base_items.ron"))
+        .expect("failed to load items");
+    let item_generator = ItemGenerator::load(&app_root.join("assets").join("items").join("affixes.ron"), &item_database)
+        .expect("failed to load affixes");
+    world.insert(item_generator);
+    world.insert(item_database);
+
+    let shop = Shop::load(&app_root.join("assets").join("items").join("shop.ron"))
+        .expect("failed to load the shop");
+    world.insert(shop);
+
+    let loot_tables = LootTables::load(&app_root.join("assets").join("items").join("loot_tables.ron"))
+        .expect("failed to load loot tables");
+    world.insert(loot_tables);
+
+    let loot_sheet = load_sprite_sheet(world, "texture/loot.png", "texture/loot.ron");
+    world.insert(LootSheet(loot_sheet));
+
+    let projectile_sheet = load_sprite_sheet(world, "texture/projectile.png", "texture/projectile.ron");
+    world.insert(ProjectileSheet(projectile_sheet));
+
+    let font = world.read_resource::<Loader>().load(
+        "font/square.ttf",
+        TtfFormat,
+        (),
+        &world.read_resource(),
+    );
+    world.insert(UiFont(font));
+
+    let run_config = match RunConfig::load(&app_root.join("config").join("run.ron")) {
+        Ok(run_config) => run_config,
+        Err(e) => {
+            println!("error reading run config, using the default : {}", e);
+            RunConfig::default()
+        },
+    };
+    world.insert(run_config);
+
+    let meta = meta_path()
+        .and_then(|path| MetaProgression::load(&path))
+        .unwrap_or_else(|e| {
+            println!("error reading meta progression, starting over : {}", e);
+            MetaProgression::default()
+        });
+    world.insert(meta);
+
+    // Replaced when a run is started or loaded, loading
+    // a game from the main menu builds its map from this.
+    world.insert(Dungeon::default());
+    world.insert(FixedStep::default());
+}
+
+fn initialize_camera(world: &mut World) {
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(ARENA_WIDTH * 0.5, ARENA_HEIGHT * 0.5, 100.0);
+
+    world
+        .create_entity()
+        .with(Camera::standard_2d(ARENA_WIDTH, ARENA_HEIGHT))
+        .with(transform)
+        .build();
+}
@@ -0,0 +1,92 @@
+use amethyst::{
+    ecs::prelude::Entity,
+    input::InputEvent,
+    prelude::*,
+    ui::{UiEvent, UiEventType},
+};
+
+use crate::resources::map::clear_floor;
+use crate::resources::save::SAVE_SLOTS;
+use crate::states::gameplay::GameplayState;
+use crate::states::menu::{Menu, BUTTON_GAP, BUTTON_HEIGHT};
+use crate::states::new_run::NewRunState;
+use crate::states::{close_screen, handle_save_request, open_save_menu, screen_is_open};
+use crate::systems::save::SaveMenu;
+use crate::systems::ui::Screen;
+
+const PANEL_WIDTH: f32 = 320.0;
+const PANEL_HEIGHT: f32 = 240.0;
+
+// The first thing shown, and where abandoning a run leads back to.
+#[derive(Default)]
+pub struct MainMenuState {
+    menu: Menu,
+    new_run: Option<Entity>,
+    continue_run: Option<Entity>,
+    quit: Option<Entity>,
+    hidden: bool,
+}
+
+impl SimpleState for MainMenuState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        // Whatever run was going on has been left behind.
+        clear_floor(world);
+
+        let step = BUTTON_HEIGHT + BUTTON_GAP;
+        self.menu.panel(world, "the dungeon".to_string(), PANEL_WIDTH, PANEL_HEIGHT);
+        self.new_run = Some(self.menu.button(world, "new run", step));
+        self.continue_run = Some(self.menu.button(world, "continue", 0.0));
+        self.quit = Some(self.menu.button(world, "quit", -step));
+        self.hidden = false;
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.menu.clear(data.world);
+    }
+
+    fn handle_event(&mut self, data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        let target = match event {
+            StateEvent::Ui(UiEvent { event_type: UiEventType::Click, target }) => target,
+            StateEvent::Input(InputEvent::ActionPressed(action)) if action == "pause" => {
+                close_screen(data.world);
+                return Trans::None;
+            },
+            _ => return Trans::None,
+        };
+        // Clicks belong to the save menu while it is open.
+        if self.hidden {
+            return Trans::None;
+        }
+
+        if Some(target) == self.new_run {
+            Trans::Switch(Box::new(NewRunState::default()))
+        } else if Some(target) == self.continue_run {
+            let world = data.world;
+            open_save_menu(world, false);
+            let saves = world.read_resource::<SaveMenu>().slots.iter().filter(|s| s.is_some()).count();
+            if saves == 0 {
+                println!("there are no saved games in any of the {} slots.", SAVE_SLOTS);
+            }
+            Trans::None
+        } else if Some(target) == self.quit {
+            Trans::Quit
+        } else {
+            Trans::None
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        if handle_save_request(data.world) {
+            return Trans::Switch(Box::new(GameplayState::default()));
+        }
+
+        let hidden = screen_is_open(data.world, Screen::SaveSlots);
+        if hidden != self.hidden {
+            self.menu.set_hidden(data.world, hidden);
+            self.hidden = hidden;
+        }
+        Trans::None
+    }
+}
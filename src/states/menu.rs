@@ -0,0 +1,115 @@
+use amethyst::{
+    core::Hidden,
+    ecs::prelude::Entity,
+    prelude::*,
+    ui::{Anchor, Interactable, LineMode, UiImage, UiText, UiTransform},
+};
+
+use crate::systems::ui::UiFont;
+
+const PANEL_COLOR: [f32; 4] = [0.05, 0.05, 0.1, 0.95];
+const BUTTON_COLOR: [f32; 4] = [0.1, 0.1, 0.15, 0.9];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+pub const BUTTON_WIDTH: f32 = 240.0;
+pub const BUTTON_HEIGHT: f32 = 32.0;
+pub const BUTTON_GAP: f32 = 8.0;
+
+// The ui a state puts up for as long as it is active.
+// Unlike the screens opened during a run it is created when
+// the state starts and deleted again when it stops.
+#[derive(Default)]
+pub struct Menu {
+    entities: Vec<Entity>,
+}
+
+impl Menu {
+    // A background panel with text along its top, centered on the screen.
+    pub fn panel(&mut self, world: &mut World, text: String, width: f32, height: f32) -> Entity {
+        let font = load_font(world);
+        let entity = world
+            .create_entity()
+            .with(UiTransform::new(
+                format!("menu_panel_{}", self.entities.len()),
+                Anchor::Middle,
+                Anchor::Middle,
+                0.0,
+                0.0,
+                8.0,
+                width,
+                height,
+            ))
+            .with(UiImage::SolidColor(PANEL_COLOR))
+            .with(UiText::new(
+                font,
+                text,
+                TEXT_COLOR,
+                18.0,
+                LineMode::Wrap,
+                Anchor::TopMiddle,
+            ))
+            .build();
+        self.entities.push(entity);
+        entity
+    }
+
+    // A button `y` px above the middle of the screen.
+    pub fn button(&mut self, world: &mut World, text: &str, y: f32) -> Entity {
+        let font = load_font(world);
+        let entity = world
+            .create_entity()
+            .with(UiTransform::new(
+                format!("menu_button_{}", self.entities.len()),
+                Anchor::Middle,
+                Anchor::Middle,
+                0.0,
+                y,
+                9.0,
+                BUTTON_WIDTH,
+                BUTTON_HEIGHT,
+            ))
+            .with(UiImage::SolidColor(BUTTON_COLOR))
+            .with(UiText::new(
+                font,
+                text.to_string(),
+                TEXT_COLOR,
+                14.0,
+                LineMode::Single,
+                Anchor::Middle,
+            ))
+            .with(Interactable)
+            .build();
+        self.entities.push(entity);
+        entity
+    }
+
+    pub fn set_text(&self, world: &mut World, entity: Entity, text: String) {
+        if let Some(ui_text) = world.write_storage::<UiText>().get_mut(entity) {
+            ui_text.text = text;
+        }
+    }
+
+    // Hides the menu while one of the run's screens, like
+    // the save menu, is opened over it.
+    pub fn set_hidden(&self, world: &mut World, hidden: bool) {
+        let mut hiddens = world.write_storage::<Hidden>();
+        for entity in self.entities.iter() {
+            if !hidden {
+                hiddens.remove(*entity);
+            } else if let Err(e) = hiddens.insert(*entity, Hidden) {
+                println!("error hiding menu : {}", e);
+            }
+        }
+    }
+
+    pub fn clear(&mut self, world: &mut World) {
+        if let Err(e) = world.delete_entities(&self.entities) {
+            println!("error deleting menu : {}", e);
+        }
+        self.entities.clear();
+    }
+}
+
+fn load_font(world: &mut World) -> amethyst::ui::FontHandle {
+    world.read_resource::<UiFont>().0.clone()
+}
@@ -0,0 +1,59 @@
+use amethyst::prelude::*;
+
+use crate::resources::save::{load_game, save_game};
+use crate::systems::save::{SaveMenu, SaveRequest};
+use crate::systems::ui::{OpenScreen, Screen};
+
+pub mod menu;
+pub mod loading;
+pub mod main_menu;
+pub mod new_run;
+pub mod gameplay;
+pub mod pause;
+pub mod inventory;
+pub mod run_end;
+
+// Carries out whatever was picked from the save menu, which more
+// than one state can open. Returns true if a game was loaded.
+pub fn handle_save_request(world: &mut World) -> bool {
+    let request = world.write_resource::<SaveMenu>().request.take();
+    match request {
+        Some(SaveRequest::Save(slot)) => {
+            match save_game(world, slot) {
+                Ok(()) => println!("saved the game to slot {}.", slot + 1),
+                Err(e) => println!("error saving the game : {}", e),
+            }
+            false
+        },
+        Some(SaveRequest::Load(slot)) => match load_game(world, slot) {
+            Ok(()) => {
+                println!("loaded the game from slot {}.", slot + 1);
+                true
+            },
+            Err(e) => {
+                println!("error loading the game : {}", e);
+                false
+            },
+        },
+        None => false,
+    }
+}
+
+// Opens the save menu, to save the run or to load one.
+pub fn open_save_menu(world: &mut World, saving: bool) {
+    {
+        let mut menu = world.write_resource::<SaveMenu>();
+        menu.saving = saving;
+        menu.refresh();
+    }
+    world.write_resource::<OpenScreen>().screen = Some(Screen::SaveSlots);
+}
+
+pub fn screen_is_open(world: &World, screen: Screen) -> bool {
+    world.read_resource::<OpenScreen>().is_open(screen)
+}
+
+// Closes whichever screen is open, returning false if there was none.
+pub fn close_screen(world: &mut World) -> bool {
+    world.write_resource::<OpenScreen>().screen.take().is_some()
+}
@@ -0,0 +1,147 @@
+use amethyst::{
+    ecs::prelude::Entity,
+    input::InputEvent,
+    prelude::*,
+    ui::{UiEvent, UiEventType},
+};
+
+use crate::resources::archetypes::ArchetypeRegistry;
+use crate::resources::party::MAX_PARTY_SIZE;
+use crate::resources::run::{MetaProgression, RunConfig, load_starting_party, start_run};
+use crate::resources::turns::CombatMode;
+use crate::states::gameplay::GameplayState;
+use crate::states::main_menu::MainMenuState;
+use crate::states::menu::{Menu, BUTTON_GAP, BUTTON_HEIGHT};
+
+const PANEL_WIDTH: f32 = 520.0;
+const PANEL_HEIGHT: f32 = 560.0;
+// Where the first class button goes, below the renown and rewards text.
+const CLASSES_TOP: f32 = 40.0;
+
+// Picks the classes the next run starts with, out of those unlocked
+// so far, and shows what earlier runs have earned.
+#[derive(Default)]
+pub struct NewRunState {
+    menu: Menu,
+    // A button per unlocked class, and whether it is picked.
+    classes: Vec<(Entity, String, bool)>,
+    mode: CombatMode,
+    mode_button: Option<Entity>,
+    start: Option<Entity>,
+    back: Option<Entity>,
+}
+
+impl NewRunState {
+    fn picked(&self) -> Vec<String> {
+        self.classes
+            .iter()
+            .filter(|(_, _, picked)| *picked)
+            .map(|(_, id, _)| id.clone())
+            .collect()
+    }
+
+    fn refresh(&self, world: &mut World) {
+        for (entity, id, picked) in self.classes.iter() {
+            let name = world.read_resource::<ArchetypeRegistry>()
+                .get(id)
+                .map_or(id.clone(), |a| a.name.clone());
+            let text = if *picked { format!("[x] {}", name) } else { format!("[ ] {}", name) };
+            self.menu.set_text(world, *entity, text);
+        }
+        if let Some(button) = self.mode_button {
+            self.menu.set_text(world, button, format!("combat : {}", self.mode.describe()));
+        }
+    }
+}
+
+impl SimpleState for NewRunState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        let (text, unlocked) = {
+            let config = world.read_resource::<RunConfig>();
+            let meta = world.read_resource::<MetaProgression>();
+            let registry = world.read_resource::<ArchetypeRegistry>();
+            let locked = meta.locked_classes(&config);
+
+            let mut lines = vec![
+                "new run".to_string(),
+                String::new(),
+                format!(
+                    "renown : {} - runs : {} - victories : {} - best floor : {}",
+                    meta.renown, meta.runs, meta.victories, meta.best_floor,
+                ),
+            ];
+            lines.extend(meta.rewards(&config).iter().map(|r| r.describe()));
+            lines.push(String::new());
+            lines.push(format!("pick up to {} classes", MAX_PARTY_SIZE));
+
+            let unlocked: Vec<String> = registry.party_classes()
+                .into_iter()
+                .filter(|id| !locked.iter().any(|l| l == id))
+                .map(|id| id.to_string())
+                .collect();
+            (lines.join("\n"), unlocked)
+        };
+
+        // The starting party from the config is picked to begin with.
+        let suggested = load_starting_party().members;
+
+        let step = BUTTON_HEIGHT + BUTTON_GAP;
+        self.menu.panel(world, text, PANEL_WIDTH, PANEL_HEIGHT);
+        self.classes.clear();
+        for (index, id) in unlocked.into_iter().enumerate() {
+            let entity = self.menu.button(world, "", CLASSES_TOP - index as f32 * step);
+            let picked = suggested.contains(&id);
+            self.classes.push((entity, id, picked));
+        }
+        let bottom = CLASSES_TOP - self.classes.len() as f32 * step - step * 0.5;
+        self.mode_button = Some(self.menu.button(world, "", bottom));
+        self.start = Some(self.menu.button(world, "start", bottom - step * 1.5));
+        self.back = Some(self.menu.button(world, "back", bottom - step * 2.5));
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.menu.clear(data.world);
+    }
+
+    fn handle_event(&mut self, data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        let target = match event {
+            StateEvent::Ui(UiEvent { event_type: UiEventType::Click, target }) => target,
+            StateEvent::Input(InputEvent::ActionPressed(action)) if action == "pause" => {
+                return Trans::Switch(Box::new(MainMenuState::default()));
+            },
+            _ => return Trans::None,
+        };
+
+        if Some(target) == self.start {
+            let members = self.picked();
+            if members.is_empty() {
+                println!("pick at least one class to start a run.");
+                return Trans::None;
+            }
+            start_run(data.world, &members, self.mode, rand::random());
+            return Trans::Switch(Box::new(GameplayState::default()));
+        }
+        if Some(target) == self.back {
+            return Trans::Switch(Box::new(MainMenuState::default()));
+        }
+        if Some(target) == self.mode_button {
+            self.mode = self.mode.toggled();
+            self.refresh(data.world);
+            return Trans::None;
+        }
+
+        let count = self.classes.iter().filter(|(_, _, picked)| *picked).count();
+        if let Some(class) = self.classes.iter_mut().find(|(entity, _, _)| *entity == target) {
+            if !class.2 && count >= MAX_PARTY_SIZE {
+                println!("a party can only have {} members.", MAX_PARTY_SIZE);
+            } else {
+                class.2 = !class.2;
+            }
+        }
+        self.refresh(data.world);
+        Trans::None
+    }
+}
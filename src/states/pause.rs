@@ -0,0 +1,89 @@
+use amethyst::{
+    ecs::prelude::Entity,
+    input::InputEvent,
+    prelude::*,
+    ui::{UiEvent, UiEventType},
+};
+
+use crate::states::main_menu::MainMenuState;
+use crate::states::menu::{Menu, BUTTON_GAP, BUTTON_HEIGHT};
+use crate::states::{close_screen, handle_save_request, open_save_menu, screen_is_open};
+use crate::systems::ui::Screen;
+
+const PANEL_WIDTH: f32 = 320.0;
+const PANEL_HEIGHT: f32 = 280.0;
+
+// Pushed over the game, which keeps being drawn but stands still
+// until the pause is lifted. Saving and loading work from here.
+#[derive(Default)]
+pub struct PauseState {
+    menu: Menu,
+    resume: Option<Entity>,
+    save: Option<Entity>,
+    load: Option<Entity>,
+    main_menu: Option<Entity>,
+    hidden: bool,
+}
+
+impl SimpleState for PauseState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        let step = BUTTON_HEIGHT + BUTTON_GAP;
+        self.menu.panel(world, "paused".to_string(), PANEL_WIDTH, PANEL_HEIGHT);
+        self.resume = Some(self.menu.button(world, "resume", step * 1.5));
+        self.save = Some(self.menu.button(world, "save game", step * 0.5));
+        self.load = Some(self.menu.button(world, "load game", -step * 0.5));
+        self.main_menu = Some(self.menu.button(world, "main menu", -step * 1.5));
+        self.hidden = false;
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.menu.clear(data.world);
+    }
+
+    fn handle_event(&mut self, data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        let target = match event {
+            StateEvent::Ui(UiEvent { event_type: UiEventType::Click, target }) => target,
+            StateEvent::Input(InputEvent::ActionPressed(action)) if action == "pause" => {
+                // Back out of the save menu first.
+                if close_screen(data.world) {
+                    return Trans::None;
+                }
+                return Trans::Pop;
+            },
+            _ => return Trans::None,
+        };
+        if self.hidden {
+            return Trans::None;
+        }
+
+        if Some(target) == self.resume {
+            Trans::Pop
+        } else if Some(target) == self.save {
+            open_save_menu(data.world, true);
+            Trans::None
+        } else if Some(target) == self.load {
+            open_save_menu(data.world, false);
+            Trans::None
+        } else if Some(target) == self.main_menu {
+            Trans::NewStack(vec![Box::new(MainMenuState::default())])
+        } else {
+            Trans::None
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        // A loaded game picks up where it was saved, not paused.
+        if handle_save_request(data.world) {
+            return Trans::Pop;
+        }
+
+        let hidden = screen_is_open(data.world, Screen::SaveSlots);
+        if hidden != self.hidden {
+            self.menu.set_hidden(data.world, hidden);
+            self.hidden = hidden;
+        }
+        Trans::None
+    }
+}
@@ -0,0 +1,70 @@
+use amethyst::{input::InputEvent, prelude::*};
+
+use crate::resources::run::Run;
+use crate::states::main_menu::MainMenuState;
+use crate::states::menu::Menu;
+use crate::states::new_run::NewRunState;
+
+const PANEL_WIDTH: f32 = 480.0;
+const PANEL_HEIGHT: f32 = 400.0;
+
+// Shows how the run went under `title`, the finished floor
+// staying in view behind it.
+fn show_summary(menu: &mut Menu, world: &mut World, title: &str) {
+    let mut lines = vec![title.to_string(), String::new()];
+    lines.extend(world.read_resource::<Run>().summary());
+    lines.push(String::new());
+    lines.push("press enter to start a new run, escape for the main menu".to_string());
+    menu.panel(world, lines.join("\n"), PANEL_WIDTH, PANEL_HEIGHT);
+}
+
+fn next_state(event: StateEvent) -> SimpleTrans {
+    match event {
+        StateEvent::Input(InputEvent::ActionPressed(action)) => match action.as_str() {
+            "new_run" => Trans::Switch(Box::new(NewRunState::default())),
+            "pause" => Trans::Switch(Box::new(MainMenuState::default())),
+            _ => Trans::None,
+        },
+        _ => Trans::None,
+    }
+}
+
+// The whole party is down.
+#[derive(Default)]
+pub struct GameOverState {
+    menu: Menu,
+}
+
+impl SimpleState for GameOverState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        show_summary(&mut self.menu, data.world, "the party has fallen");
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.menu.clear(data.world);
+    }
+
+    fn handle_event(&mut self, _data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        next_state(event)
+    }
+}
+
+// The boss of the last floor is dead.
+#[derive(Default)]
+pub struct VictoryState {
+    menu: Menu,
+}
+
+impl SimpleState for VictoryState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        show_summary(&mut self.menu, data.world, "victory!");
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.menu.clear(data.world);
+    }
+
+    fn handle_event(&mut self, _data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        next_state(event)
+    }
+}
@@ -0,0 +1,307 @@
+use crate::components::abilities::{Abilities, Ability, AbilityAim, AbilityEffect, AbilityShape, Cast, Energy};
+use crate::components::npc::{Npc, Attackable, Dead, Enemy, PlayerControlled, Downed, Waiting};
+use crate::components::projectile::{Projectile, ProjectileSheet};
+use crate::components::stats::{Stats, Resistances, resolve_damage};
+use crate::components::status::{StatusEffects, effective_stats};
+use crate::resources::events::CombatEvent;
+use crate::resources::dungeon::Dungeon;
+use crate::systems::combat::{apply_heal, apply_outcome};
+use crate::systems::commands::{calc_velocity_vec, distance};
+use crate::systems::projectile::fire_projectile;
+use crate::resources::simulation::{FixedStep, TICK_SECONDS};
+use crate::components::save::{Persistent, marker_id};
+
+use amethyst::core::{
+    Transform,
+};
+use amethyst::ecs::{
+    prelude::{Entity, Entities, LazyUpdate},
+    Join, Read, ReadExpect, ReadStorage, System, Write, WriteStorage,
+};
+use amethyst::shrev::EventChannel;
+
+// Ticks ability cooldowns and energy, walks casters into range of
+// their queued ability, and resolves casts once their cast time is up.
+pub struct AbilitySystem;
+
+impl<'s> System<'s> for AbilitySystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Abilities>,
+        WriteStorage<'s, Energy>,
+        WriteStorage<'s, Npc>,
+        ReadStorage<'s, Transform>,
+        WriteStorage<'s, Attackable>,
+        ReadStorage<'s, Stats>,
+        ReadStorage<'s, Resistances>,
+        ReadStorage<'s, Enemy>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Downed>,
+        Read<'s, LazyUpdate>,
+        ReadExpect<'s, ProjectileSheet>,
+        Write<'s, EventChannel<CombatEvent>>,
+        WriteStorage<'s, StatusEffects>,
+        ReadStorage<'s, Waiting>,
+        Read<'s, Dungeon>,
+        Read<'s, FixedStep>,
+        ReadStorage<'s, Persistent>,
+        ReadStorage<'s, Dead>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut abilities,
+            mut energies,
+            mut npcs,
+            transforms,
+            mut attackables,
+            stats,
+            resistances,
+            enemies,
+            pcs,
+            downeds,
+            lazy,
+            projectile_sheet,
+            mut combat_events,
+            mut statuses,
+            waitings,
+            dungeon,
+            step,
+            markers,
+            deads,
+        ): Self::SystemData
+    ) {
+        let delta = TICK_SECONDS;
+
+        for energy in (&mut energies).join() {
+            energy.regenerate(delta);
+        }
+
+        let mut finished: Vec<(Entity, Ability, AbilityAim)> = vec![];
+
+        for (entity, caster, npc, transform, _, _) in (&entities, &mut abilities, &mut npcs, &transforms, !&downeds, !&waitings).join() {
+            for ability in caster.slots.iter_mut() {
+                if ability.cooldown_remaining > 0.0 {
+                    ability.cooldown_remaining = (ability.cooldown_remaining - delta).max(0.0);
+                }
+            }
+
+            // Being stunned breaks any cast in progress.
+            if statuses.get(entity).map_or(false, |s| s.is_stunned()) {
+                caster.casting = None;
+                continue;
+            }
+
+            if let Some(cast) = caster.casting.as_mut() {
+                cast.elapsed += delta;
+                let ability = &caster.slots[cast.order.slot];
+                if cast.elapsed < ability.cast_time {
+                    continue;
+                }
+
+                let order = cast.order;
+                caster.casting = None;
+                let ability = &mut caster.slots[order.slot];
+                let energy = energies.get_mut(entity);
+                if let Some(energy) = energy {
+                    if !energy.can_afford(ability) {
+                        continue;
+                    }
+                    energy.pay(ability);
+                }
+                ability.cooldown_remaining = ability.cooldown;
+                finished.push((entity, ability.clone(), order.aim));
+                continue;
+            }
+
+            let order = match caster.queued {
+                Some(order) => order,
+                None => continue,
+            };
+
+            let ability = &caster.slots[order.slot];
+            let affordable = energies.get(entity).map_or(true, |e| e.can_afford(ability));
+            if !ability.is_ready() || !affordable {
+                caster.queued = None;
+                continue;
+            }
+
+            let aim_point = match order.aim {
+                AbilityAim::Entity(target) => match transforms.get(target) {
+                    Some(t) if entities.is_alive(target)
+                        && can_target(ability, entity, target, (&enemies, &pcs), &attackables, &deads, &downeds) =>
+                    {
+                        [t.translation().x, t.translation().y]
+                    },
+                    _ => {
+                        caster.queued = None;
+                        continue;
+                    }
+                },
+                AbilityAim::Point(point) => point,
+                AbilityAim::Caster => [transform.translation().x, transform.translation().y],
+            };
+
+            let curr = [transform.translation().x, transform.translation().y];
+            if ability.needs_range() && distance(curr, aim_point) > ability.range {
+                // Walk towards the target until it is in range.
+                npc.velocity = calc_velocity_vec(curr, aim_point, npc.move_speed);
+                npc.move_coords = aim_point;
+                continue;
+            }
+
+            npc.velocity = [0.0, 0.0];
+            caster.queued = None;
+            caster.casting = Some(Cast {
+                order: order,
+                elapsed: 0.0,
+            });
+        }
+
+        let default_resistances = Resistances::default();
+
+        for (caster, ability, aim) in finished {
+            let caster_pos = match transforms.get(caster) {
+                Some(t) => [t.translation().x, t.translation().y],
+                None => continue,
+            };
+            let aim_point = match aim {
+                AbilityAim::Entity(target) => match transforms.get(target) {
+                    Some(t) => [t.translation().x, t.translation().y],
+                    None => continue,
+                },
+                AbilityAim::Point(point) => point,
+                AbilityAim::Caster => caster_pos,
+            };
+            let hostile_to_players = enemies.contains(caster);
+            // Beneficial abilities pick from the caster's own side.
+            let targets_players = hostile_to_players != ability.is_beneficial();
+
+            let targets: Vec<(Entity, [f32; 2])> = match ability.shape {
+                AbilityShape::OnSelf => vec![(caster, caster_pos)],
+                // Whoever it was cast at may have gone down, or
+                // changed sides, during the cast.
+                AbilityShape::Targeted => match aim {
+                    AbilityAim::Entity(target)
+                        if can_target(&ability, caster, target, (&enemies, &pcs), &attackables, &deads, &downeds) =>
+                    {
+                        vec![(target, aim_point)]
+                    },
+                    _ => vec![],
+                },
+                _ => {
+                    (&entities, &transforms, &attackables, !&downeds)
+                        .join()
+                        .filter(|(e, _, _, _)| match targets_players {
+                            true => pcs.contains(*e),
+                            false => enemies.contains(*e),
+                        })
+                        .filter(|(_, _, a, _)| a.health > 0.0)
+                        .map(|(e, t, _, _)| (e, [t.translation().x, t.translation().y]))
+                        .filter(|(_, pos)| in_shape(ability.shape, ability.range, caster_pos, aim_point, *pos))
+                        .collect()
+                }
+            };
+
+            let caster_stats = effective_stats(stats.get(caster), statuses.get(caster));
+
+            for (target, target_pos) in targets {
+                match &ability.effect {
+                    AbilityEffect::Damage(damage) => {
+                        let attackable = match attackables.get_mut(target) {
+                            Some(a) if a.health > 0.0 => a,
+                            _ => continue,
+                        };
+                        let mut rng = dungeon.roll_rng(step.tick, marker_id(&markers, caster), marker_id(&markers, target));
+                        let outcome = resolve_damage(
+                            &mut rng,
+                            *damage,
+                            &caster_stats,
+                            &effective_stats(stats.get(target), statuses.get(target)),
+                            resistances.get(target).unwrap_or(&default_resistances),
+                        );
+                        apply_outcome(
+                            &mut combat_events,
+                            caster,
+                            target,
+                            damage.damage_type,
+                            outcome,
+                            attackable,
+                            statuses.get_mut(target),
+                        );
+                    },
+                    AbilityEffect::Projectile { damage, speed } => {
+                        let projectile = Projectile::new(caster, caster_stats, *damage, hostile_to_players)
+                            .aimed(caster_pos, target_pos, *speed, ability.range);
+                        fire_projectile(&lazy, &entities, &projectile_sheet, projectile, caster_pos);
+                    },
+                    AbilityEffect::Status(effect) => {
+                        if statuses.get(target).is_none() {
+                            if let Err(e) = statuses.insert(target, StatusEffects::default()) {
+                                println!("error adding status effects : {}", e);
+                                continue;
+                            }
+                        }
+                        statuses.get_mut(target).unwrap().apply(effect.clone().from_source(caster));
+                    },
+                    AbilityEffect::Heal(amount) => {
+                        if let Some(attackable) = attackables.get_mut(target) {
+                            apply_heal(&mut combat_events, Some(caster), target, *amount, attackable);
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
+
+// Can `caster` use a single target ability on `target`? Only standing
+// units can be targeted, beneficial abilities on the caster's own side
+// and anything else on the other side.
+fn can_target(
+    ability: &Ability,
+    caster: Entity,
+    target: Entity,
+    (enemies, pcs): (&ReadStorage<Enemy>, &ReadStorage<PlayerControlled>),
+    attackables: &WriteStorage<Attackable>,
+    deads: &ReadStorage<Dead>,
+    downeds: &ReadStorage<Downed>,
+) -> bool {
+    if deads.contains(target) || downeds.contains(target) {
+        return false;
+    }
+    if attackables.get(target).map_or(true, |a| a.health <= 0.0) {
+        return false;
+    }
+    let same_side = (enemies.contains(caster) && enemies.contains(target))
+        || (pcs.contains(caster) && pcs.contains(target));
+    let other_side = (enemies.contains(caster) && pcs.contains(target))
+        || (pcs.contains(caster) && enemies.contains(target));
+    match ability.is_beneficial() {
+        true => same_side,
+        false => other_side,
+    }
+}
+
+// Is `pos` covered by an ability of the given shape cast
+// from `origin` and aimed at `aim`?
+fn in_shape(shape: AbilityShape, range: f32, origin: [f32; 2], aim: [f32; 2], pos: [f32; 2]) -> bool {
+    match shape {
+        AbilityShape::Area { radius } => distance(aim, pos) <= radius,
+        AbilityShape::Cone { half_angle } => {
+            if distance(origin, pos) > range {
+                return false;
+            }
+            let aim_angle = (aim[1] - origin[1]).atan2(aim[0] - origin[0]);
+            let pos_angle = (pos[1] - origin[1]).atan2(pos[0] - origin[0]);
+            let mut diff = (pos_angle - aim_angle).abs();
+            if diff > std::f32::consts::PI {
+                diff = 2.0 * std::f32::consts::PI - diff;
+            }
+            diff <= half_angle
+        },
+        _ => false,
+    }
+}
@@ -1,13 +1,13 @@
 use crate::components::animated::IdleAnimation;
-use crate::components::npc::Npc;
+use crate::components::npc::{Npc, Attacker, Dead, Downed};
+use crate::resources::simulation::TICK_SECONDS;
 
 use amethyst::core::{
-    timing::Time,
 };
 use amethyst::renderer::SpriteRender;
 use amethyst::ecs::{
     prelude::Entities,
-    Join, Read, System, WriteStorage, ReadStorage
+    Join, System, WriteStorage, ReadStorage
 };
 
 #[derive(Default)]
@@ -19,19 +19,26 @@ impl<'s> System<'s> for IdleAnimationSystem {
         WriteStorage<'s, SpriteRender>,
         WriteStorage<'s, IdleAnimation>,
         ReadStorage<'s, Npc>,
-        Read<'s, Time>,
+        ReadStorage<'s, Attacker>,
+        ReadStorage<'s, Dead>,
+        ReadStorage<'s, Downed>,
     );
 
-    fn run(&mut self, (entities, mut sprite_renders, mut animations, npcs, time): Self::SystemData) {
-        for (entity, sprite_render, anim) in (&entities, &mut sprite_renders, &mut animations).join() {
+    fn run(&mut self, (entities, mut sprite_renders, mut animations, npcs, attackers, deads, downeds): Self::SystemData) {
+        for (entity, sprite_render, anim, _, _) in (&entities, &mut sprite_renders, &mut animations, !&deads, !&downeds).join() {
+            // Mid-swing the fight animation owns the sprite.
+            if attackers.get(entity).map_or(false, |a| a.is_swinging()) {
+                continue;
+            }
+
             if let Some(npc) = npcs.get(entity) {
                 if npc.velocity == [0.0, 0.0] {
-                    anim.anim.animate(time.delta_seconds(), sprite_render);
+                    anim.anim.animate(TICK_SECONDS, sprite_render);
                 } else if !anim.anim.is_reset() {
                     anim.anim.reset();
                 }
             } else {
-                anim.anim.animate(time.delta_seconds(), sprite_render);
+                anim.anim.animate(TICK_SECONDS, sprite_render);
             }
         }
     }
@@ -1,18 +1,27 @@
-use crate::components::npc::{Npc, CanTarget, Attackable, Attacker, HealthBar};
-use crate::systems::commands::calc_velocity_vec;
+use crate::components::npc::{Npc, CanTarget, Attackable, Attacker, AttackPhase, HealthBar, Downed, Enemy, Waiting};
+use crate::systems::commands::{calc_velocity_vec, distance};
 use crate::components::animated::FightAnimation;
+use crate::components::stats::{Stats, Resistances, Damage, DamageOutcome, DamageType, resolve_damage, resolve_heal};
+use crate::components::projectile::{AttackKind, Projectile, ProjectileSheet};
+use crate::components::abilities::Abilities;
+use crate::components::status::{StatusEffects, effective_stats};
+use crate::resources::events::CombatEvent;
+use crate::resources::dungeon::Dungeon;
+use crate::systems::projectile::fire_projectile;
+use crate::resources::simulation::{FixedStep, TICK_SECONDS};
+use crate::components::save::{Persistent, marker_id};
 
 use amethyst::core::{
     math::Vector3,
-    timing::Time,
     Transform,
     Parent,
 };
 use amethyst::ecs::{
-    prelude::Entities,
-    Join, Read, System, WriteStorage, ReadStorage
+    prelude::{Entity, Entities, LazyUpdate},
+    Join, Read, ReadExpect, Write, System, WriteStorage, ReadStorage
 };
 use amethyst::renderer::SpriteRender;
+use amethyst::shrev::EventChannel;
 
 pub struct HealthBarSystem;
 
@@ -49,9 +58,21 @@ impl<'s> System<'s> for CombatSystem {
         ReadStorage<'s, Transform>,
         WriteStorage<'s, Attackable>,
         WriteStorage<'s, Attacker>,
-        Read<'s, Time>,
         WriteStorage<'s, FightAnimation>,
         WriteStorage<'s, SpriteRender>,
+        ReadStorage<'s, Stats>,
+        ReadStorage<'s, Resistances>,
+        Write<'s, EventChannel<CombatEvent>>,
+        ReadStorage<'s, Downed>,
+        Read<'s, LazyUpdate>,
+        ReadExpect<'s, ProjectileSheet>,
+        ReadStorage<'s, Enemy>,
+        ReadStorage<'s, Abilities>,
+        WriteStorage<'s, StatusEffects>,
+        ReadStorage<'s, Waiting>,
+        Read<'s, Dungeon>,
+        Read<'s, FixedStep>,
+        ReadStorage<'s, Persistent>,
     );
 
     fn run(
@@ -63,68 +84,246 @@ impl<'s> System<'s> for CombatSystem {
             transforms, 
             mut attackables, 
             mut attackers, 
-            time,
             mut anims,
             mut renders,
+            stats,
+            resistances,
+            mut combat_events,
+            downeds,
+            lazy,
+            projectile_sheet,
+            enemies,
+            abilities,
+            mut statuses,
+            waitings,
+            dungeon,
+            step,
+            markers,
         ): Self::SystemData
     ) {        
+        let delta = TICK_SECONDS;
+        let default_resistances = Resistances::default();
+
         for (
+            entity,
             npc, 
             attacker, 
             targeter, 
             transform,
             anim,
             render,
+            _,
+            _,
         ) in (
+            &entities,
             &mut npcs, 
             &mut attackers, 
             &mut targeters, 
             &transforms,
             &mut anims,
             &mut renders,
+            !&downeds,
+            !&waitings,
         ).join() {
-            if let Some(target) = targeter.target {
-                // is the target attackable?
-                if let Some(attackable) = attackables.get_mut(target) {
-                    // We have an attackable target
-                    // Is our attackable target transformable?
-                    if let Some(t_transform) = transforms.get(target) {
-                        let target_x = t_transform.translation().x;
-                        let target_y = t_transform.translation().y;
-                        let curr_x = transform.translation().x;
-                        let curr_y = transform.translation().y;
-                        let dist_x = target_x - curr_x;
-                        let dist_y = target_y - curr_y;
-                        let dist = (dist_x.powf(2.0) + dist_y.powf(2.0)).sqrt();
-                        if dist <= attacker.attack_range {
-                            anim.anim.animate(time.delta_seconds(), render);
-                            if time.frame_number() % attacker.attack_speed == 0 {
-                                println!("attacks for {} damage", attacker.attack);
-                                attackable.health -= attacker.attack;
-                                println!("attacked has {} health remaining!", attackable.health);
-                                if attackable.health <= 0.0 {
-                                    println!("attacked has died!");
-                                    targeter.target = None;
-                                    if let Err(e) = entities.delete(target) {
-                                        println!("error deleting entity : {}", e);
-                                    }
-                                }
-                            }
-                        } else {
-                            anim.anim.reset();
-                            // The target is outside our attack range, move towards it
-                            // until it is within range.
-                            let dest = match target_x > curr_x {
-                                true => [target_x - (0.75 * attacker.attack_range), target_y],
-                                false => [target_x + (0.75 * attacker.attack_range), target_y],
-                            };
-                            let velocity = calc_velocity_vec([curr_x, curr_y], dest, npc.move_speed);
-                            npc.velocity = velocity;
-                            npc.move_coords = dest;
-                        }
+            // The cooldown keeps ticking down while we walk, so a unit
+            // that chases its target can swing as soon as it arrives.
+            if attacker.cooldown_remaining > 0.0 {
+                attacker.cooldown_remaining = (attacker.cooldown_remaining - delta).max(0.0);
+            }
+
+            // Using an ability takes priority over auto-attacking,
+            // and stunned units can do neither.
+            if abilities.get(entity).map_or(false, |a| a.is_busy())
+                || statuses.get(entity).map_or(false, |s| s.is_stunned())
+            {
+                if attacker.is_swinging() {
+                    attacker.interrupt();
+                    anim.anim.reset();
+                }
+                continue;
+            }
+
+            let target = match targeter.target {
+                // Targets that have already been killed this frame are
+                // waiting to be cleaned up, leave them alone.
+                Some(target) if attackables.get(target).map_or(false, |a| a.health > 0.0)
+                    && transforms.contains(target) => target,
+                _ => {
+                    if attacker.is_swinging() {
+                        attacker.interrupt();
+                        anim.anim.reset();
                     }
+                    continue;
+                }
+            };
+
+            let t_transform = transforms.get(target).unwrap();
+            let target_x = t_transform.translation().x;
+            let target_y = t_transform.translation().y;
+            let curr_x = transform.translation().x;
+            let curr_y = transform.translation().y;
+            if distance([curr_x, curr_y], [target_x, target_y]) > attacker.attack_range {
+                if attacker.is_swinging() {
+                    attacker.interrupt();
                 }
+                anim.anim.reset();
+                // The target is outside our attack range, move towards it
+                // until it is within range.
+                let dest = match target_x > curr_x {
+                    true => [target_x - (0.75 * attacker.attack_range), target_y],
+                    false => [target_x + (0.75 * attacker.attack_range), target_y],
+                };
+                let velocity = calc_velocity_vec([curr_x, curr_y], dest, npc.move_speed);
+                npc.velocity = velocity;
+                npc.move_coords = dest;
+                continue;
             }
+
+            // Start a new swing once the previous one has cooled down.
+            if attacker.phase == AttackPhase::Ready {
+                if attacker.cooldown_remaining > 0.0 {
+                    continue;
+                }
+                anim.anim.reset();
+                attacker.phase = AttackPhase::WindUp;
+                combat_events.single_write(CombatEvent::AttackStarted {
+                    attacker: entity,
+                    target: target,
+                });
+            }
+
+            anim.anim.animate_once(delta, render);
+
+            // Damage is applied on the impact frame of the swing,
+            // not at the start of it. Ranged attackers let go of
+            // their projectile on that frame instead.
+            if attacker.phase == AttackPhase::WindUp && anim.is_past_impact() {
+                attacker.phase = AttackPhase::Recovery;
+                let damage = Damage::new(attacker.attack, attacker.damage_type);
+                let attacker_stats = effective_stats(stats.get(entity), statuses.get(entity));
+
+                if let AttackKind::Ranged { projectile_speed } = attacker.kind {
+                    let projectile = Projectile::new(entity, attacker_stats, damage, enemies.contains(entity))
+                        .aimed([curr_x, curr_y], [target_x, target_y], projectile_speed, attacker.attack_range);
+                    fire_projectile(&lazy, &entities, &projectile_sheet, projectile, [curr_x, curr_y]);
+                    continue;
+                }
+
+                let mut rng = dungeon.roll_rng(step.tick, marker_id(&markers, entity), marker_id(&markers, target));
+                let outcome = resolve_damage(
+                    &mut rng,
+                    damage,
+                    &attacker_stats,
+                    &effective_stats(stats.get(target), statuses.get(target)),
+                    resistances.get(target).unwrap_or(&default_resistances),
+                );
+                let attackable = attackables.get_mut(target).unwrap();
+                if apply_outcome(
+                    &mut combat_events,
+                    entity,
+                    target,
+                    attacker.damage_type,
+                    outcome,
+                    attackable,
+                    statuses.get_mut(target),
+                ) {
+                    targeter.target = None;
+                }
+            }
+
+            if attacker.phase == AttackPhase::Recovery && anim.anim.is_finished() {
+                attacker.phase = AttackPhase::Ready;
+                attacker.cooldown_remaining = attacker.attack_cooldown;
+                anim.anim.reset();
+            }
+        }
+    }
+}
+
+// Applies a resolved blow to the target's health and reports it on the
+// combat event channel. Returns whether the blow killed the target.
+pub fn apply_outcome(
+    combat_events: &mut EventChannel<CombatEvent>,
+    source: Entity,
+    target: Entity,
+    damage_type: DamageType,
+    outcome: DamageOutcome,
+    attackable: &mut Attackable,
+    statuses: Option<&mut StatusEffects>,
+) -> bool {
+    match outcome {
+        DamageOutcome::Miss => {
+            combat_events.single_write(CombatEvent::Miss {
+                attacker: source,
+                target: target,
+            });
+            false
+        },
+        DamageOutcome::Hit { amount, critical } => {
+            combat_events.single_write(CombatEvent::Hit {
+                attacker: source,
+                target: target,
+                critical: critical,
+            });
+            let damage = Damage::new(amount, damage_type);
+            apply_damage(combat_events, Some(source), target, damage, critical, attackable, statuses)
         }
     }
-}
\ No newline at end of file
+}
+
+// Takes damage off the target's health, after any shields it has up,
+// and reports it. Used directly for damage that is not a blow from
+// a unit, such as poison ticking. Returns whether the target died.
+pub fn apply_damage(
+    combat_events: &mut EventChannel<CombatEvent>,
+    source: Option<Entity>,
+    target: Entity,
+    damage: Damage,
+    critical: bool,
+    attackable: &mut Attackable,
+    statuses: Option<&mut StatusEffects>,
+) -> bool {
+    let amount = match statuses {
+        Some(statuses) => statuses.absorb(damage.amount),
+        None => damage.amount,
+    };
+    let was_alive = attackable.health > 0.0;
+    attackable.health = (attackable.health - amount).max(0.0);
+    combat_events.single_write(CombatEvent::Damage {
+        source: source,
+        target: target,
+        amount: amount,
+        damage_type: damage.damage_type,
+        critical: critical,
+        remaining_health: attackable.health,
+    });
+    if was_alive && attackable.health <= 0.0 {
+        combat_events.single_write(CombatEvent::Death {
+            entity: target,
+            killer: source,
+        });
+        return true;
+    }
+    false
+}
+
+// Heals the target, never past its maximum health, and reports it.
+// Returns how much health was actually restored.
+pub fn apply_heal(
+    combat_events: &mut EventChannel<CombatEvent>,
+    source: Option<Entity>,
+    target: Entity,
+    amount: f32,
+    attackable: &mut Attackable,
+) -> f32 {
+    let healed = resolve_heal(amount, attackable.health, attackable.total_health);
+    if healed > 0.0 {
+        attackable.health += healed;
+        combat_events.single_write(CombatEvent::Heal {
+            source: source,
+            target: target,
+            amount: healed,
+        });
+    }
+    healed
+}
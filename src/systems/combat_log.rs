@@ -0,0 +1,43 @@
+use crate::resources::events::CombatEvent;
+
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Read, System, SystemData};
+use amethyst::shrev::{EventChannel, ReaderId};
+
+// Prints every combat event to stdout.
+#[derive(SystemDesc)]
+#[system_desc(name(CombatLogSystemDesc))]
+pub struct CombatLogSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<CombatEvent>,
+}
+
+impl CombatLogSystem {
+    pub fn new(reader_id: ReaderId<CombatEvent>) -> Self {
+        CombatLogSystem { reader_id }
+    }
+}
+
+impl<'s> System<'s> for CombatLogSystem {
+    type SystemData = Read<'s, EventChannel<CombatEvent>>;
+
+    fn run(&mut self, combat_events: Self::SystemData) {
+        for event in combat_events.read(&mut self.reader_id) {
+            match event {
+                CombatEvent::AttackStarted { .. } => {},
+                CombatEvent::Hit { critical, .. } => {
+                    if *critical {
+                        println!("critical hit!");
+                    }
+                },
+                CombatEvent::Miss { .. } => println!("attack missed!"),
+                CombatEvent::Damage { amount, damage_type, remaining_health, .. } => {
+                    println!("attacks for {} {:?} damage", amount, damage_type);
+                    println!("attacked has {} health remaining!", remaining_health);
+                },
+                CombatEvent::Heal { amount, .. } => println!("healed for {}", amount),
+                CombatEvent::Death { .. } => println!("attacked has died!"),
+            }
+        }
+    }
+}
@@ -7,7 +7,8 @@ use amethyst::assets::AssetStorage;
 use amethyst::derive::SystemDesc;
 use amethyst::ecs::{
     prelude::Entity,
-    Join, Read, ReadStorage, System, SystemData, WriteStorage, ReadExpect
+    saveload::Marker,
+    Join, Read, ReadStorage, System, SystemData, Write, WriteStorage, ReadExpect
 };
 use amethyst::input::{InputHandler, StringBindings, Button};
 use amethyst::winit::MouseButton;
@@ -18,11 +19,53 @@ use amethyst::renderer::{
 use amethyst::ecs::prelude::Entities;
 use amethyst::window::ScreenDimensions;
 
-use crate::components::npc::{Npc, CanTarget, PlayerControlled};
+use std::collections::HashMap;
+
+use crate::components::loot::{Collecting, Pickup};
+use crate::components::npc::{Npc, CanTarget, PlayerControlled, Downed};
+use crate::components::save::{Persistent, marker_id};
+use crate::components::abilities::{Abilities, AbilityAim, AbilityOrder, PendingAbility, HOTBAR_SLOTS};
+use crate::systems::hotbar::is_over_hotbar;
+use crate::systems::ui::OpenScreen;
+use crate::resources::orders::{GearChange, PartyOrders, Trade};
+use crate::resources::replay::{RecordedAim, RecordedCommand, RecordedOrder, Replay};
+use crate::resources::simulation::FixedStep;
+
+// How close to a pickup a click has to land to send the party for it.
+const PICKUP_CLICK_RADIUS: f32 = 10.0;
+
+const ABILITY_ACTIONS: [&str; HOTBAR_SLOTS] = ["ability_1", "ability_2", "ability_3", "ability_4", "ability_5", "ability_6"];
+
+// An order for some of the party. Orders come from the player's
+// clicks and hotkeys, or from a replay.
+#[derive(Clone, Copy, Debug)]
+pub enum Order {
+    Move([f32; 2]),
+    Attack(Entity),
+    Ability(AbilityOrder),
+    Rest,
+    DrinkPotion,
+    EndTurn,
+    Pickup(Entity),
+    // Only ever played back, the screens carry these out themselves.
+    Trade(Trade),
+    Gear(GearChange),
+    LearnTalent(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct Command {
+    pub units: Vec<Entity>,
+    pub order: Order,
+}
 
 #[derive(SystemDesc, Default)]
 pub struct CommandSystem {
     mouse_was_down: bool,
+    ability_keys_were_down: [bool; HOTBAR_SLOTS],
+    rest_key_was_down: bool,
+    potion_key_was_down: bool,
+    end_turn_key_was_down: bool,
 }
 
 impl<'s> System<'s> for CommandSystem {
@@ -38,90 +81,365 @@ impl<'s> System<'s> for CommandSystem {
         ReadStorage<'s, SpriteRender>,
         Read<'s, AssetStorage<SpriteSheet>>,
         ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Downed>,
+        WriteStorage<'s, Abilities>,
+        Write<'s, PendingAbility>,
+        Read<'s, OpenScreen>,
+        Write<'s, Replay>,
+        Read<'s, FixedStep>,
+        ReadStorage<'s, Persistent>,
+        Write<'s, PartyOrders>,
+        ReadStorage<'s, Pickup>,
+        WriteStorage<'s, Collecting>,
     );
 
-    fn run(&mut self, (entities, transforms, mut npcs, mut targeters, cameras, input, active_camera, screen_dimensions, sprites, sprite_sheets, pcs): Self::SystemData) {
-        if let Some(mouse_pos) = input.mouse_position() {
-            let mut camera_join = (&cameras, &transforms).join();
-            if let Some((camera, camera_transform)) = active_camera
-                .entity
-                .and_then(|a| camera_join.get(a, &entities))
-                .or_else(|| camera_join.next())
-            {
-                let ray = camera.projection().screen_ray(
-                    Point2::new(mouse_pos.0, mouse_pos.1),
-                    Vector2::new(screen_dimensions.width(), screen_dimensions.height()),
-                    camera_transform,
-                );
-                let distance = ray.intersect_plane(&Plane::with_z(0.0)).unwrap();
-                let mouse_world_pos = ray.at_distance(distance);
-
-                // We now have the in-world position of our mouse location
-
-
-                // We only want to append a move command on mouse-up. Implement
-                // mouse-up by storing when the mouse is down, and then consider mouse-up
-                // when the event transitions off mouse-down.
-                if input.button_is_down(Button::Mouse(MouseButton::Left)) {
-                    self.mouse_was_down = true;
-                } else if self.mouse_was_down {
-                    let mut target: Option<Entity> = None;
-                    let mut should_move = true;
-                    for (sprite, _, entity, transform) in (&sprites, &mut npcs, &entities, &transforms).join() {
-                        let sprite_sheet = sprite_sheets.get(&sprite.sprite_sheet).unwrap();
-                        let sprite = &sprite_sheet.sprites[sprite.sprite_number];
-                        let (min_x, max_x, min_y, max_y) = {
-                            (
-                                transform.translation().x - (sprite.width * 0.5),
-                                transform.translation().x + (sprite.width * 0.5),
-                                transform.translation().y - (sprite.height * 0.5),
-                                transform.translation().y + (sprite.height * 0.5),
-                            )
+    fn run(&mut self, (entities, transforms, mut npcs, mut targeters, cameras, input, active_camera, screen_dimensions, sprites, sprite_sheets, pcs, downeds, mut abilities, mut pending, open_screen, mut replay, step, markers, mut orders, pickups, mut collectings): Self::SystemData) {
+        let commands = if replay.is_playing() {
+            let due = replay.due(step.tick);
+            if due.is_empty() {
+                vec![]
+            } else {
+                let by_marker: HashMap<u64, Entity> = (&entities, &markers)
+                    .join()
+                    .map(|(entity, marker)| (marker.id(), entity))
+                    .collect();
+                due.iter().filter_map(|command| from_recorded(command, &by_marker)).collect()
+            }
+        } else {
+            let mut commands = vec![];
+            let selected: Vec<Entity> = (&entities, &npcs, &pcs, !&downeds)
+                .join()
+                .filter(|(_, npc, _, _)| npc.selected)
+                .map(|(entity, _, _, _)| entity)
+                .collect();
+
+            // Hotkeys pick an ability slot. Abilities that need aiming wait for
+            // the next click, the rest are used straight away.
+            for (slot, action) in ABILITY_ACTIONS.iter().enumerate() {
+                let is_down = input.action_is_down(action).unwrap_or(false);
+                if is_down && !self.ability_keys_were_down[slot] {
+                    let needs_aim = (&npcs, &abilities, &pcs)
+                        .join()
+                        .filter(|(n, _, _)| n.selected)
+                        .filter_map(|(_, a, _)| a.slots.get(slot))
+                        .any(|ability| ability.needs_aim());
+
+                    if needs_aim {
+                        pending.slot = match pending.slot {
+                            Some(s) if s == slot => None,
+                            _ => Some(slot),
                         };
-                        if mouse_world_pos.x > min_x
-                            && mouse_world_pos.x < max_x
-                            && mouse_world_pos.y > min_y
-                            && mouse_world_pos.y < max_y
-                        {
-                            if let Some(_) = pcs.get(entity) {
-                                should_move = false;
-                            } else {
-                                target = Some(entity);
-                            }
-                            
-                        }
+                    } else {
+                        pending.slot = None;
+                        commands.push(Command {
+                            units: selected.clone(),
+                            order: Order::Ability(AbilityOrder { slot, aim: AbilityAim::Caster }),
+                        });
                     }
+                }
+                self.ability_keys_were_down[slot] = is_down;
+            }
+
+            if key_pressed(&input, "rest", &mut self.rest_key_was_down) {
+                commands.push(Command {
+                    units: selected.clone(),
+                    order: Order::Rest,
+                });
+            }
+            if key_pressed(&input, "drink_potion", &mut self.potion_key_was_down) {
+                commands.push(Command {
+                    units: selected.clone(),
+                    order: Order::DrinkPotion,
+                });
+            }
+            if key_pressed(&input, "end_turn", &mut self.end_turn_key_was_down) {
+                commands.push(Command {
+                    units: vec![],
+                    order: Order::EndTurn,
+                });
+            }
+
+            // We only want to append a move command on mouse-up. Implement
+            // mouse-up by storing when the mouse is down, and then consider mouse-up
+            // when the event transitions off mouse-down.
+            let mouse_down = input.button_is_down(Button::Mouse(MouseButton::Left));
+            let released = self.mouse_was_down && !mouse_down;
+            self.mouse_was_down = mouse_down;
+
+            let click = match input.mouse_position() {
+                // The hotbar and any open screen handle their own clicks.
+                Some(mouse_pos) if released
+                    && open_screen.screen.is_none()
+                    && !is_over_hotbar(mouse_pos, screen_dimensions.width(), screen_dimensions.height()) =>
+                {
+                    mouse_world_pos(&entities, &transforms, &cameras, &active_camera, &screen_dimensions, mouse_pos)
+                },
+                _ => None,
+            };
+            if let Some(at) = click {
+                let under_mouse = units_at(at, &entities, &npcs, &transforms, &sprites, &sprite_sheets);
+                let clicked = under_mouse.last().cloned();
+                let target = under_mouse.iter().filter(|e| !pcs.contains(**e)).last().cloned();
+                let should_move = under_mouse.iter().all(|e| !pcs.contains(*e));
+                let pickup = pickup_at(at, &entities, &pickups, &transforms);
 
-                    for (npc, targeter, transform) in (&mut npcs, &mut targeters, &transforms).join().filter(|(n, _, _)| n.selected == true) {
-
-                        match target {
-                            Some(_) => {
-                                targeter.target = target;
-                            },
-                            None => {
-                                if should_move {
-                                    let modified_y = mouse_world_pos.y + 15.0;
-                                    let velocity = calc_velocity_vec(
-                                        [transform.translation().x, transform.translation().y],
-                                        [mouse_world_pos.x, modified_y],
-                                        npc.move_speed,
-                                    );
-
-                                    targeter.target = None;
-                                    npc.velocity = velocity;
-                                    npc.move_coords = [mouse_world_pos.x, modified_y];
-                                }
+                let order = match (pending.slot.take(), target) {
+                    // Abilities can be aimed at party members too, e.g. heals.
+                    (Some(slot), _) => Some(Order::Ability(AbilityOrder {
+                        slot,
+                        aim: match clicked {
+                            Some(entity) => AbilityAim::Entity(entity),
+                            None => AbilityAim::Point(at),
+                        },
+                    })),
+                    (None, Some(target)) => Some(Order::Attack(target)),
+                    (None, None) if should_move => Some(match pickup {
+                        Some(pickup) => Order::Pickup(pickup),
+                        None => Order::Move([at[0], at[1] + 15.0]),
+                    }),
+                    (None, None) => None,
+                };
+                if let Some(order) = order {
+                    commands.push(Command {
+                        units: selected,
+                        order,
+                    });
+                }
+            }
+            commands
+        };
+
+        for command in commands {
+            replay.record(to_recorded(&command, step.tick, &markers));
+            // These are carried out by the systems they're for.
+            match command.order {
+                Order::Rest => {
+                    orders.rest = Some(command.units);
+                    continue;
+                },
+                Order::DrinkPotion => {
+                    orders.drink.extend(command.units);
+                    continue;
+                },
+                Order::EndTurn => {
+                    orders.end_turn = true;
+                    continue;
+                },
+                Order::Trade(trade) => {
+                    orders.trades.push(trade);
+                    continue;
+                },
+                Order::Gear(change) => {
+                    orders.gear.extend(command.units.iter().map(|unit| (*unit, change)));
+                    continue;
+                },
+                Order::LearnTalent(row) => {
+                    orders.talents.extend(command.units.iter().map(|unit| (*unit, row)));
+                    continue;
+                },
+                _ => {},
+            }
+            for unit in command.units {
+                // Any other order calls off fetching a pickup.
+                collectings.remove(unit);
+                match command.order {
+                    Order::Ability(order) => {
+                        if let Some(caster) = abilities.get_mut(unit) {
+                            if order.slot < caster.slots.len() {
+                                caster.queued = Some(order);
                             }
                         }
-
-                        self.mouse_was_down = false;
-                    }
+                    },
+                    Order::Attack(target) => {
+                        let targeter = match targeters.get_mut(unit) {
+                            Some(targeter) => targeter,
+                            None => continue,
+                        };
+                        // A direct order replaces whatever ability was lined up.
+                        if let Some(caster) = abilities.get_mut(unit) {
+                            caster.cancel();
+                        }
+                        targeter.target = Some(target);
+                    },
+                    Order::Move(coords) => {
+                        let (npc, targeter, transform) = match (npcs.get_mut(unit), targeters.get_mut(unit), transforms.get(unit)) {
+                            (Some(npc), Some(targeter), Some(transform)) => (npc, targeter, transform),
+                            _ => continue,
+                        };
+                        if let Some(caster) = abilities.get_mut(unit) {
+                            caster.cancel();
+                        }
+                        targeter.target = None;
+                        npc.velocity = calc_velocity_vec(
+                            [transform.translation().x, transform.translation().y],
+                            coords,
+                            npc.move_speed,
+                        );
+                        npc.move_coords = coords;
+                    },
+                    Order::Pickup(pickup) => {
+                        let at = match transforms.get(pickup) {
+                            Some(t) if pickups.contains(pickup) => [t.translation().x, t.translation().y],
+                            _ => continue,
+                        };
+                        let (npc, targeter, transform) = match (npcs.get_mut(unit), targeters.get_mut(unit), transforms.get(unit)) {
+                            (Some(npc), Some(targeter), Some(transform)) => (npc, targeter, transform),
+                            _ => continue,
+                        };
+                        if let Some(caster) = abilities.get_mut(unit) {
+                            caster.cancel();
+                        }
+                        targeter.target = None;
+                        npc.velocity = calc_velocity_vec(
+                            [transform.translation().x, transform.translation().y],
+                            at,
+                            npc.move_speed,
+                        );
+                        npc.move_coords = at;
+                        if let Err(e) = collectings.insert(unit, Collecting { pickup }) {
+                            println!("error sending a unit for a pickup : {}", e);
+                        }
+                    },
+                    _ => {},
                 }
             }
         }
     }
 }
 
+// Whether `action` went down since the last time it was checked.
+fn key_pressed(input: &InputHandler<StringBindings>, action: &str, was_down: &mut bool) -> bool {
+    let is_down = input.action_is_down(action).unwrap_or(false);
+    let pressed = is_down && !*was_down;
+    *was_down = is_down;
+    pressed
+}
+
+// Where on the map the mouse is pointing.
+fn mouse_world_pos(
+    entities: &Entities,
+    transforms: &WriteStorage<Transform>,
+    cameras: &ReadStorage<Camera>,
+    active_camera: &ActiveCamera,
+    screen_dimensions: &ScreenDimensions,
+    mouse_pos: (f32, f32),
+) -> Option<[f32; 2]> {
+    let mut camera_join = (cameras, transforms).join();
+    let (camera, camera_transform) = active_camera
+        .entity
+        .and_then(|a| camera_join.get(a, entities))
+        .or_else(|| camera_join.next())?;
+    let ray = camera.projection().screen_ray(
+        Point2::new(mouse_pos.0, mouse_pos.1),
+        Vector2::new(screen_dimensions.width(), screen_dimensions.height()),
+        camera_transform,
+    );
+    let distance = ray.intersect_plane(&Plane::with_z(0.0)).unwrap();
+    let mouse_world_pos = ray.at_distance(distance);
+    Some([mouse_world_pos.x, mouse_world_pos.y])
+}
+
+// Every unit whose sprite covers `at`.
+fn units_at(
+    at: [f32; 2],
+    entities: &Entities,
+    npcs: &WriteStorage<Npc>,
+    transforms: &WriteStorage<Transform>,
+    sprites: &ReadStorage<SpriteRender>,
+    sprite_sheets: &AssetStorage<SpriteSheet>,
+) -> Vec<Entity> {
+    let mut units = vec![];
+    for (sprite, _, entity, transform) in (sprites, npcs, entities, transforms).join() {
+        let sprite_sheet = sprite_sheets.get(&sprite.sprite_sheet).unwrap();
+        let sprite = &sprite_sheet.sprites[sprite.sprite_number];
+        let (min_x, max_x, min_y, max_y) = {
+            (
+                transform.translation().x - (sprite.width * 0.5),
+                transform.translation().x + (sprite.width * 0.5),
+                transform.translation().y - (sprite.height * 0.5),
+                transform.translation().y + (sprite.height * 0.5),
+            )
+        };
+        if at[0] > min_x && at[0] < max_x && at[1] > min_y && at[1] < max_y {
+            units.push(entity);
+        }
+    }
+    units
+}
+
+// The pickup closest to `at`, if one is close enough to have been clicked.
+fn pickup_at(
+    at: [f32; 2],
+    entities: &Entities,
+    pickups: &ReadStorage<Pickup>,
+    transforms: &WriteStorage<Transform>,
+) -> Option<Entity> {
+    (entities, pickups, transforms)
+        .join()
+        .map(|(entity, _, transform)| (entity, distance(at, [transform.translation().x, transform.translation().y])))
+        .filter(|(_, dist)| *dist <= PICKUP_CLICK_RADIUS)
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(entity, _)| entity)
+}
+
+fn to_recorded(command: &Command, tick: u64, markers: &ReadStorage<Persistent>) -> RecordedCommand {
+    let order = match command.order {
+        Order::Move(coords) => RecordedOrder::Move(coords),
+        Order::Attack(target) => RecordedOrder::Attack(marker_id(markers, target)),
+        Order::Ability(AbilityOrder { slot, aim }) => RecordedOrder::Ability {
+            slot,
+            aim: match aim {
+                AbilityAim::Caster => RecordedAim::Caster,
+                AbilityAim::Entity(entity) => RecordedAim::Unit(marker_id(markers, entity)),
+                AbilityAim::Point(point) => RecordedAim::Point(point),
+            },
+        },
+        Order::Rest => RecordedOrder::Rest,
+        Order::DrinkPotion => RecordedOrder::DrinkPotion,
+        Order::EndTurn => RecordedOrder::EndTurn,
+        Order::Pickup(pickup) => RecordedOrder::Pickup(marker_id(markers, pickup)),
+        Order::Trade(trade) => RecordedOrder::Trade(trade),
+        Order::Gear(change) => RecordedOrder::Gear(change),
+        Order::LearnTalent(row) => RecordedOrder::LearnTalent(row),
+    };
+    RecordedCommand {
+        tick,
+        units: command.units.iter().map(|unit| marker_id(markers, *unit)).collect(),
+        order,
+    }
+}
+
+// `None` if whoever the order was aimed at is gone.
+fn from_recorded(command: &RecordedCommand, by_marker: &HashMap<u64, Entity>) -> Option<Command> {
+    let order = match command.order {
+        RecordedOrder::Move(coords) => Order::Move(coords),
+        RecordedOrder::Attack(target) => Order::Attack(*by_marker.get(&target)?),
+        RecordedOrder::Ability { slot, aim } => Order::Ability(AbilityOrder {
+            slot,
+            aim: match aim {
+                RecordedAim::Caster => AbilityAim::Caster,
+                RecordedAim::Unit(id) => AbilityAim::Entity(*by_marker.get(&id)?),
+                RecordedAim::Point(point) => AbilityAim::Point(point),
+            },
+        }),
+        RecordedOrder::Rest => Order::Rest,
+        RecordedOrder::DrinkPotion => Order::DrinkPotion,
+        RecordedOrder::EndTurn => Order::EndTurn,
+        RecordedOrder::Pickup(pickup) => Order::Pickup(*by_marker.get(&pickup)?),
+        RecordedOrder::Trade(trade) => Order::Trade(trade),
+        RecordedOrder::Gear(change) => Order::Gear(change),
+        RecordedOrder::LearnTalent(row) => Order::LearnTalent(row),
+    };
+    Some(Command {
+        units: command.units.iter().filter_map(|id| by_marker.get(id).cloned()).collect(),
+        order,
+    })
+}
+
+pub fn distance(p1: [f32; 2], p2: [f32; 2]) -> f32 {
+    ((p2[0] - p1[0]).powf(2.0) + (p2[1] - p1[1]).powf(2.0)).sqrt()
+}
+
 pub fn calc_velocity_vec(current_pos: [f32; 2], target_pos: [f32; 2], move_speed: f32) -> [f32; 2] {
     let direct_velocity_x = target_pos[0] - current_pos[0];
     let direct_velocity_y = target_pos[1] - current_pos[1];
@@ -0,0 +1,156 @@
+use crate::components::animated::{DeathAnimation, IdleAnimation, WalkAnimation, FightAnimation};
+use crate::components::npc::{
+    Npc, CanTarget, Attackable, Attacker, Enemy, PlayerControlled, Dead, Downed,
+};
+use crate::resources::events::CombatEvent;
+use crate::resources::simulation::TICK_SECONDS;
+
+use amethyst::core::{
+    Parent,
+};
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{
+    prelude::{Entity, Entities},
+    Join, Read, ReadStorage, System, SystemData, WriteStorage,
+};
+use amethyst::renderer::SpriteRender;
+use amethyst::shrev::{EventChannel, ReaderId};
+
+// How long a corpse lies on the floor before being removed.
+const CORPSE_LIFETIME: f32 = 10.0;
+
+// Turns units that combat reports dead into corpses (or downed party
+// members), plays their death animation and eventually cleans them up.
+#[derive(SystemDesc)]
+#[system_desc(name(DeathSystemDesc))]
+pub struct DeathSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<CombatEvent>,
+}
+
+impl DeathSystem {
+    pub fn new(reader_id: ReaderId<CombatEvent>) -> Self {
+        DeathSystem { reader_id }
+    }
+}
+
+impl<'s> System<'s> for DeathSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<CombatEvent>>,
+        ReadStorage<'s, Parent>,
+        ReadStorage<'s, PlayerControlled>,
+        WriteStorage<'s, Dead>,
+        WriteStorage<'s, Downed>,
+        WriteStorage<'s, Npc>,
+        WriteStorage<'s, CanTarget>,
+        WriteStorage<'s, Attackable>,
+        WriteStorage<'s, Attacker>,
+        WriteStorage<'s, Enemy>,
+        WriteStorage<'s, IdleAnimation>,
+        WriteStorage<'s, WalkAnimation>,
+        WriteStorage<'s, FightAnimation>,
+        WriteStorage<'s, DeathAnimation>,
+        WriteStorage<'s, SpriteRender>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            combat_events,
+            parents,
+            pcs,
+            mut deads,
+            mut downeds,
+            mut npcs,
+            mut targeters,
+            mut attackables,
+            mut attackers,
+            mut enemies,
+            mut idle_anims,
+            mut walk_anims,
+            mut fight_anims,
+            mut death_anims,
+            mut renders,
+        ): Self::SystemData
+    ) {
+        let mut died: Vec<Entity> = vec![];
+        for event in combat_events.read(&mut self.reader_id) {
+            if let CombatEvent::Death { entity, .. } = event {
+                if entities.is_alive(*entity) && !deads.contains(*entity) && !downeds.contains(*entity) {
+                    died.push(*entity);
+                }
+            }
+        }
+
+        for entity in died {
+            // Nobody should keep swinging at the fallen.
+            for targeter in (&mut targeters).join() {
+                if targeter.target == Some(entity) {
+                    targeter.target = None;
+                }
+            }
+
+            if let Some(anim) = death_anims.get_mut(entity) {
+                anim.anim.reset();
+            }
+
+            if pcs.contains(entity) {
+                // Party members are only downed, keep them (and their
+                // health bar) around so they can be revived later.
+                if let Some(npc) = npcs.get_mut(entity) {
+                    npc.velocity = [0.0, 0.0];
+                }
+                if let Some(targeter) = targeters.get_mut(entity) {
+                    targeter.target = None;
+                }
+                if let Some(attacker) = attackers.get_mut(entity) {
+                    attacker.interrupt();
+                }
+                if let Err(e) = downeds.insert(entity, Downed) {
+                    println!("error downing entity : {}", e);
+                }
+                continue;
+            }
+
+            // Strip everything that lets the corpse move, fight or
+            // be fought, leaving just the sprite on the floor.
+            npcs.remove(entity);
+            targeters.remove(entity);
+            attackables.remove(entity);
+            attackers.remove(entity);
+            enemies.remove(entity);
+            idle_anims.remove(entity);
+            walk_anims.remove(entity);
+            fight_anims.remove(entity);
+            if let Err(e) = deads.insert(entity, Dead::default()) {
+                println!("error killing entity : {}", e);
+            }
+
+            // Health bars and auras hang off the unit as children.
+            for (child, parent) in (&entities, &parents).join() {
+                if parent.entity == entity {
+                    if let Err(e) = entities.delete(child) {
+                        println!("error deleting entity : {}", e);
+                    }
+                }
+            }
+        }
+
+        for (entity, dead) in (&entities, &mut deads).join() {
+            dead.elapsed += TICK_SECONDS;
+            if dead.elapsed >= CORPSE_LIFETIME {
+                if let Err(e) = entities.delete(entity) {
+                    println!("error deleting entity : {}", e);
+                }
+            }
+        }
+
+        // Play the death animation through once, then hold on the last
+        // frame so the body stays on the floor.
+        for (anim, render, _) in (&mut death_anims, &mut renders, deads.mask() | downeds.mask()).join() {
+            anim.anim.animate_once(TICK_SECONDS, render);
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use crate::components::npc::{Enemy, PlayerControlled, CanTarget};
+use crate::components::abilities::{Abilities, AbilityAim, AbilityOrder, AbilityShape, Energy};
+use crate::components::npc::{Enemy, PlayerControlled, CanTarget, Downed, Attackable, Waiting};
+use crate::systems::commands::distance;
 
 use amethyst::core::{
     Transform,
@@ -22,23 +24,33 @@ impl<'s> System<'s> for EnemyTargetingSystem {
         WriteStorage<'s, Enemy>,
         ReadStorage<'s, PlayerControlled>,
         ReadStorage<'s, Transform>,
+        ReadStorage<'s, Downed>,
+        WriteStorage<'s, Abilities>,
+        ReadStorage<'s, Energy>,
+        ReadStorage<'s, Attackable>,
+        ReadStorage<'s, Waiting>,
     );
 
-    fn run(&mut self, (entities, mut targeters, mut enemies, pcs, transforms): Self::SystemData) {
-        for (targeter, enemy, transform) in (&mut targeters, &mut enemies, &transforms).join() {
+    fn run(&mut self, (entities, mut targeters, mut enemies, pcs, transforms, downeds, mut abilities, energies, attackables, waitings): Self::SystemData) {
+        // Wounded enemies, for casters with heals to look after.
+        let wounded: Vec<(Entity, [f32; 2], f32)> = (&entities, &enemies, &attackables, &transforms)
+            .join()
+            .filter(|(_, _, a, _)| a.health > 0.0 && a.health < a.total_health)
+            .map(|(e, _, a, t)| (e, [t.translation().x, t.translation().y], a.health / a.total_health))
+            .collect();
+
+        for (entity, targeter, enemy, transform, _) in (&entities, &mut targeters, &mut enemies, &transforms, !&waitings).join() {
             let enemy_x = transform.translation().x;
             let enemy_y = transform.translation().y;
 
             let mut targets: Vec<Targetable> = vec![];
-            for (_, entity, transform) in (&pcs, &entities, &transforms).join() {
-                let distance_x = enemy_x - transform.translation().x;
-                let distance_y = enemy_y - transform.translation().y;
-                let distance = (distance_x.powf(2.0) + distance_y.powf(2.0)).sqrt();
+            for (_, entity, transform, _) in (&pcs, &entities, &transforms, !&downeds).join() {
+                let dist = distance([enemy_x, enemy_y], [transform.translation().x, transform.translation().y]);
 
-                if enemy.fov_radius >= distance {
+                if enemy.fov_radius >= dist {
                     targets.push(Targetable { 
                         target: entity,
-                        distance: distance,
+                        distance: dist,
                     });
                 }
             }
@@ -52,6 +64,40 @@ impl<'s> System<'s> for EnemyTargetingSystem {
                 }
                 targeter.target = Some(closest.target);
             }
+
+            // Enemies with abilities use the first one that is ready
+            // once they have someone to fight.
+            let target = match targeter.target {
+                Some(target) => target,
+                None => continue,
+            };
+            let caster = match abilities.get_mut(entity) {
+                Some(caster) if !caster.is_busy() => caster,
+                _ => continue,
+            };
+
+            for (slot, ability) in caster.slots.iter().enumerate() {
+                if !ability.is_ready() || !energies.get(entity).map_or(true, |e| e.can_afford(ability)) {
+                    continue;
+                }
+
+                let aim = if ability.shape == AbilityShape::OnSelf {
+                    Some(AbilityAim::Caster)
+                } else if ability.is_beneficial() {
+                    wounded
+                        .iter()
+                        .filter(|(_, pos, _)| distance([enemy_x, enemy_y], *pos) <= enemy.fov_radius)
+                        .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
+                        .map(|(ally, _, _)| AbilityAim::Entity(*ally))
+                } else {
+                    Some(AbilityAim::Entity(target))
+                };
+
+                if let Some(aim) = aim {
+                    caster.queued = Some(AbilityOrder { slot, aim });
+                    break;
+                }
+            }
         }
     }
-}
\ No newline at end of file
+}
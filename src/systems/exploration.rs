@@ -0,0 +1,71 @@
+use crate::components::npc::{Dead, PlayerControlled};
+use crate::components::tile::{Tile, TileDecoration};
+use crate::resources::map::MapGrid;
+use crate::TILE_WIDTH;
+
+use amethyst::core::{
+    Hidden, Transform,
+};
+use amethyst::ecs::{
+    prelude::Entities,
+    Join, Read, ReadStorage, System, WriteExpect, WriteStorage,
+};
+
+// How many tiles around them party members can see.
+const SIGHT_RADIUS: usize = 8;
+
+// Marks the tiles the party can see as explored.
+pub struct ExplorationSystem;
+
+impl<'s> System<'s> for ExplorationSystem {
+    type SystemData = (
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Dead>,
+        ReadStorage<'s, Transform>,
+        WriteExpect<'s, MapGrid>,
+    );
+
+    fn run(&mut self, (player_controlled, deads, transforms, mut grid): Self::SystemData) {
+        for (_, _, transform) in (&player_controlled, !&deads, &transforms).join() {
+            let translation = transform.translation();
+            grid.explore([translation.x, translation.y], SIGHT_RADIUS);
+        }
+    }
+}
+
+// Hides the tiles the party hasn't explored yet. Runs every frame, before
+// there is a floor too, so the grid may still be the blank default.
+pub struct FogSystem;
+
+impl<'s> System<'s> for FogSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Tile>,
+        ReadStorage<'s, TileDecoration>,
+        ReadStorage<'s, Transform>,
+        WriteStorage<'s, Hidden>,
+        Read<'s, MapGrid>,
+    );
+
+    fn run(&mut self, (entities, tiles, decorations, transforms, mut hiddens, grid): Self::SystemData) {
+        for (entity, transform, _) in (&entities, &transforms, tiles.mask() | decorations.mask()).join() {
+            // Tall tiles are drawn higher up than the tile they stand on,
+            // and only walls are decorated.
+            let lift = match tiles.get(entity) {
+                Some(tile) => (tile.variant.tile_dimensions()[1] - TILE_WIDTH) / 2.0,
+                None => TILE_WIDTH / 2.0,
+            };
+            let translation = transform.translation();
+            let x = (translation.x.max(0.0) / TILE_WIDTH) as usize;
+            let y = ((translation.y - lift).max(0.0) / TILE_WIDTH) as usize;
+            let explored = grid.is_explored(x, y);
+            if explored && hiddens.contains(entity) {
+                hiddens.remove(entity);
+            } else if !explored && !hiddens.contains(entity) {
+                if let Err(e) = hiddens.insert(entity, Hidden) {
+                    println!("error hiding unexplored tile : {}", e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::resources::events::CombatEvent;
+use crate::systems::ui::UiFont;
+use crate::{ARENA_HEIGHT, ARENA_WIDTH};
+
+use amethyst::{
+    core::{timing::Time, Transform},
+    derive::SystemDesc,
+    ecs::{
+        prelude::{Component, DenseVecStorage, Entities, LazyUpdate},
+        Join, Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteStorage,
+    },
+    prelude::*,
+    renderer::{palette::Srgba, resources::Tint, Camera},
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, LineMode, UiText, UiTransform},
+};
+
+// How long numbers float for, and how far they rise meanwhile.
+const TEXT_LIFETIME: f32 = 0.9;
+const TEXT_RISE: f32 = 24.0;
+// Numbers start a little above the unit's middle.
+const TEXT_OFFSET: f32 = 12.0;
+const TEXT_SIZE: f32 = 14.0;
+const CRIT_TEXT_SIZE: f32 = 20.0;
+
+const DAMAGE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const CRIT_COLOR: [f32; 4] = [1.0, 0.6, 0.2, 1.0];
+const HEAL_COLOR: [f32; 4] = [0.4, 1.0, 0.4, 1.0];
+const MISS_COLOR: [f32; 4] = [0.7, 0.7, 0.7, 1.0];
+
+// Units tinted this color when hit, fading back to normal.
+const FLASH_SECONDS: f32 = 0.15;
+const FLASH_COLOR: [f32; 3] = [1.0, 0.35, 0.35];
+
+// How long and how far the camera shakes on a critical hit.
+const SHAKE_SECONDS: f32 = 0.25;
+const SHAKE_STRENGTH: f32 = 4.0;
+const SHAKE_SPEED: f32 = 60.0;
+
+// A number rising from a unit and fading out.
+#[derive(Default)]
+pub struct FloatingText {
+    pub elapsed: f32,
+}
+
+impl Component for FloatingText {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A unit that was just hit.
+pub struct HitFlash {
+    pub remaining: f32,
+}
+
+impl Component for HitFlash {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// Time left shaking the camera.
+#[derive(Default)]
+pub struct ScreenShake {
+    pub remaining: f32,
+    pub elapsed: f32,
+}
+
+// Turns what happens in a fight into something to see: numbers over
+// the units, a flash on whoever was hit and a shake on critical hits.
+// Only reads the combat events, so it plays no part in the simulation.
+#[derive(SystemDesc)]
+#[system_desc(name(CombatFeedbackSystemDesc))]
+pub struct CombatFeedbackSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<CombatEvent>,
+}
+
+impl CombatFeedbackSystem {
+    pub fn new(reader_id: ReaderId<CombatEvent>) -> Self {
+        CombatFeedbackSystem { reader_id }
+    }
+}
+
+impl<'s> System<'s> for CombatFeedbackSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<CombatEvent>>,
+        ReadStorage<'s, Transform>,
+        WriteStorage<'s, HitFlash>,
+        Write<'s, ScreenShake>,
+        Read<'s, LazyUpdate>,
+        ReadExpect<'s, UiFont>,
+    );
+
+    fn run(&mut self, (entities, combat_events, transforms, mut flashes, mut shake, lazy, font): Self::SystemData) {
+        for event in combat_events.read(&mut self.reader_id) {
+            let (target, text, color, size) = match event {
+                CombatEvent::Damage { target, amount, critical, .. } => {
+                    if let Err(e) = flashes.insert(*target, HitFlash { remaining: FLASH_SECONDS }) {
+                        println!("error flashing entity : {}", e);
+                    }
+                    if *critical {
+                        *shake = ScreenShake { remaining: SHAKE_SECONDS, elapsed: 0.0 };
+                        (*target, format!("{}!", amount.round()), CRIT_COLOR, CRIT_TEXT_SIZE)
+                    } else {
+                        (*target, format!("{}", amount.round()), DAMAGE_COLOR, TEXT_SIZE)
+                    }
+                },
+                CombatEvent::Heal { target, amount, .. } => (*target, format!("+{}", amount.round()), HEAL_COLOR, TEXT_SIZE),
+                CombatEvent::Miss { target, .. } => (*target, "miss".to_string(), MISS_COLOR, TEXT_SIZE),
+                _ => continue,
+            };
+
+            let at = match transforms.get(target) {
+                Some(transform) => [transform.translation().x, transform.translation().y + TEXT_OFFSET],
+                None => continue,
+            };
+            spawn_floating_text(&lazy, &entities, &font, text, color, size, at);
+        }
+    }
+}
+
+// The ui is laid out over the whole window while the map is drawn to
+// fit it, so a point on the map is the same fraction of the way across
+// either.
+fn spawn_floating_text(
+    lazy: &LazyUpdate,
+    entities: &Entities,
+    font: &UiFont,
+    text: String,
+    color: [f32; 4],
+    size: f32,
+    at: [f32; 2],
+) {
+    let transform = UiTransform::new(
+        "combat_text".to_string(),
+        Anchor::BottomLeft,
+        Anchor::Middle,
+        at[0] / ARENA_WIDTH,
+        at[1] / ARENA_HEIGHT,
+        2.0,
+        0.1,
+        0.05,
+    )
+    .into_percent();
+
+    lazy.create_entity(entities)
+        .with(transform)
+        .with(UiText::new(font.0.clone(), text, color, size, LineMode::Single, Anchor::Middle))
+        .with(FloatingText::default())
+        .build();
+}
+
+pub struct FloatingTextSystem;
+
+impl<'s> System<'s> for FloatingTextSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, FloatingText>,
+        WriteStorage<'s, UiTransform>,
+        WriteStorage<'s, UiText>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (entities, mut floating, mut ui_transforms, mut texts, time): Self::SystemData) {
+        let delta = time.delta_seconds();
+        for (entity, floating, transform, text) in (&entities, &mut floating, &mut ui_transforms, &mut texts).join() {
+            floating.elapsed += delta;
+            if floating.elapsed >= TEXT_LIFETIME {
+                if let Err(e) = entities.delete(entity) {
+                    println!("error deleting entity : {}", e);
+                }
+                continue;
+            }
+            transform.local_y += TEXT_RISE / TEXT_LIFETIME * delta / ARENA_HEIGHT;
+            text.color[3] = 1.0 - floating.elapsed / TEXT_LIFETIME;
+        }
+    }
+}
+
+pub struct HitFlashSystem;
+
+impl<'s> System<'s> for HitFlashSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, HitFlash>,
+        WriteStorage<'s, Tint>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (entities, mut flashes, mut tints, time): Self::SystemData) {
+        let mut finished = vec![];
+        for (entity, flash) in (&entities, &mut flashes).join() {
+            flash.remaining -= time.delta_seconds();
+            if flash.remaining <= 0.0 {
+                tints.remove(entity);
+                finished.push(entity);
+                continue;
+            }
+
+            // From the flash color back towards white.
+            let strength = flash.remaining / FLASH_SECONDS;
+            let channel = |c: f32| 1.0 - (1.0 - c) * strength;
+            let tint = Tint(Srgba::new(channel(FLASH_COLOR[0]), channel(FLASH_COLOR[1]), channel(FLASH_COLOR[2]), 1.0));
+            if let Err(e) = tints.insert(entity, tint) {
+                println!("error tinting entity : {}", e);
+            }
+        }
+        for entity in finished {
+            flashes.remove(entity);
+        }
+    }
+}
+
+// Shakes the camera about where it normally sits, in the middle of
+// the map, settling back as the shake runs out.
+pub struct ScreenShakeSystem;
+
+impl<'s> System<'s> for ScreenShakeSystem {
+    type SystemData = (
+        Write<'s, ScreenShake>,
+        ReadStorage<'s, Camera>,
+        WriteStorage<'s, Transform>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (mut shake, cameras, mut transforms, time): Self::SystemData) {
+        if shake.remaining <= 0.0 {
+            return;
+        }
+        shake.remaining = (shake.remaining - time.delta_seconds()).max(0.0);
+        shake.elapsed += time.delta_seconds();
+
+        let strength = SHAKE_STRENGTH * shake.remaining / SHAKE_SECONDS;
+        let angle = shake.elapsed * SHAKE_SPEED;
+        let offset = [strength * angle.sin(), strength * (angle * 1.3).cos()];
+        for (_, transform) in (&cameras, &mut transforms).join() {
+            transform.set_translation_x(ARENA_WIDTH * 0.5 + offset[0]);
+            transform.set_translation_y(ARENA_HEIGHT * 0.5 + offset[1]);
+        }
+    }
+}
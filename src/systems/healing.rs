@@ -0,0 +1,226 @@
+use crate::components::abilities::Energy;
+use crate::components::animated::DeathAnimation;
+use crate::components::npc::{Attackable, Enemy, PlayerControlled, Downed, Recovery};
+use crate::components::scenary::CampFire;
+use crate::resources::events::CombatEvent;
+use crate::resources::orders::PartyOrders;
+use crate::resources::supplies::HealingPotions;
+use crate::systems::combat::apply_heal;
+use crate::resources::simulation::TICK_SECONDS;
+use crate::systems::commands::distance;
+
+use amethyst::core::{
+    Transform,
+};
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{
+    prelude::{Entity, Entities},
+    Join, ReadStorage, System, SystemData, Write, WriteStorage,
+};
+use amethyst::shrev::{EventChannel, ReaderId};
+
+// Recovery heals in whole ticks rather than a sliver every frame.
+const RECOVERY_INTERVAL: f32 = 1.0;
+// How close a party member has to be to a campfire to rest at it.
+const REST_RADIUS: f32 = 40.0;
+// No enemies may be left standing this close to a campfire.
+const CAMPFIRE_CLEAR_RADIUS: f32 = 150.0;
+// Downed party members get back up with this much of their health
+// before the rest heals them the rest of the way.
+const REVIVE_HEALTH_FRACTION: f32 = 0.1;
+
+// Heals units with `Recovery` once they have been out of combat for a while.
+#[derive(SystemDesc)]
+#[system_desc(name(RecoverySystemDesc))]
+pub struct RecoverySystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<CombatEvent>,
+}
+
+impl RecoverySystem {
+    pub fn new(reader_id: ReaderId<CombatEvent>) -> Self {
+        RecoverySystem { reader_id }
+    }
+}
+
+impl<'s> System<'s> for RecoverySystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Recovery>,
+        WriteStorage<'s, Attackable>,
+        ReadStorage<'s, Downed>,
+        Write<'s, EventChannel<CombatEvent>>,
+    );
+
+    fn run(&mut self, (entities, mut recoveries, mut attackables, downeds, mut combat_events): Self::SystemData) {
+        // Anyone swinging or being swung at is in combat.
+        let mut fighting: Vec<Entity> = vec![];
+        for event in combat_events.read(&mut self.reader_id) {
+            match event {
+                CombatEvent::AttackStarted { attacker, target }
+                | CombatEvent::Hit { attacker, target, .. }
+                | CombatEvent::Miss { attacker, target } => {
+                    fighting.push(*attacker);
+                    fighting.push(*target);
+                },
+                CombatEvent::Damage { source, target, .. } => {
+                    fighting.push(*target);
+                    if let Some(source) = source {
+                        fighting.push(*source);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        for entity in fighting {
+            if let Some(recovery) = recoveries.get_mut(entity) {
+                recovery.since_combat = 0.0;
+                recovery.tick_timer = 0.0;
+            }
+        }
+
+        for (entity, recovery, attackable, _) in (&entities, &mut recoveries, &mut attackables, !&downeds).join() {
+            recovery.since_combat += TICK_SECONDS;
+            if recovery.since_combat < recovery.delay || attackable.health >= attackable.total_health {
+                continue;
+            }
+
+            recovery.tick_timer += TICK_SECONDS;
+            while recovery.tick_timer >= RECOVERY_INTERVAL {
+                recovery.tick_timer -= RECOVERY_INTERVAL;
+                apply_heal(
+                    &mut combat_events,
+                    None,
+                    entity,
+                    recovery.per_second * RECOVERY_INTERVAL,
+                    attackable,
+                );
+            }
+        }
+    }
+}
+
+// Lets the party rest at a campfire that has been cleared of enemies,
+// reviving anyone downed and restoring everyone's health and energy.
+// Each campfire can only be rested at once.
+pub struct RestSystem;
+
+impl<'s> System<'s> for RestSystem {
+    type SystemData = (
+        Entities<'s>,
+        Write<'s, PartyOrders>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Enemy>,
+        WriteStorage<'s, CampFire>,
+        WriteStorage<'s, Attackable>,
+        WriteStorage<'s, Downed>,
+        WriteStorage<'s, Energy>,
+        WriteStorage<'s, DeathAnimation>,
+        Write<'s, EventChannel<CombatEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut orders,
+            transforms,
+            pcs,
+            enemies,
+            mut campfires,
+            mut attackables,
+            mut downeds,
+            mut energies,
+            mut death_anims,
+            mut combat_events,
+        ): Self::SystemData
+    ) {
+        let resters: Vec<[f32; 2]> = match orders.rest.take() {
+            Some(units) => units
+                .into_iter()
+                .filter(|unit| pcs.contains(*unit) && !downeds.contains(*unit))
+                .filter_map(|unit| transforms.get(unit))
+                .map(|t| [t.translation().x, t.translation().y])
+                .collect(),
+            None => return,
+        };
+
+        let campfire = (&entities, &campfires, &transforms)
+            .join()
+            .filter(|(_, campfire, _)| !campfire.rested)
+            .map(|(e, _, t)| (e, [t.translation().x, t.translation().y]))
+            .find(|(_, pos)| resters.iter().any(|r| distance(*r, *pos) <= REST_RADIUS));
+
+        let (campfire, campfire_pos) = match campfire {
+            Some(campfire) => campfire,
+            None => return,
+        };
+
+        let enemies_nearby = (&enemies, &attackables, &transforms)
+            .join()
+            .filter(|(_, a, _)| a.health > 0.0)
+            .any(|(_, _, t)| distance([t.translation().x, t.translation().y], campfire_pos) <= CAMPFIRE_CLEAR_RADIUS);
+        if enemies_nearby {
+            println!("it is not safe to rest here yet!");
+            return;
+        }
+
+        println!("the party rests by the fire.");
+        campfires.get_mut(campfire).unwrap().rested = true;
+
+        for (entity, _, attackable) in (&entities, &pcs, &mut attackables).join() {
+            if downeds.remove(entity).is_some() {
+                attackable.health = attackable.total_health * REVIVE_HEALTH_FRACTION;
+                if let Some(anim) = death_anims.get_mut(entity) {
+                    anim.anim.reset();
+                }
+            }
+            let amount = attackable.total_health;
+            apply_heal(&mut combat_events, Some(campfire), entity, amount, attackable);
+
+            if let Some(energy) = energies.get_mut(entity) {
+                energy.mana = energy.max_mana;
+                energy.stamina = energy.max_stamina;
+            }
+        }
+    }
+}
+
+// Has each injured party member told to drink one of the party's
+// healing potions do so.
+pub struct PotionSystem;
+
+impl<'s> System<'s> for PotionSystem {
+    type SystemData = (
+        Write<'s, PartyOrders>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Downed>,
+        WriteStorage<'s, Attackable>,
+        Write<'s, HealingPotions>,
+        Write<'s, EventChannel<CombatEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (mut orders, pcs, downeds, mut attackables, mut potions, mut combat_events): Self::SystemData
+    ) {
+        for entity in orders.drink.drain(..) {
+            if !pcs.contains(entity) || downeds.contains(entity) {
+                continue;
+            }
+            let attackable = match attackables.get_mut(entity) {
+                Some(attackable) if attackable.health < attackable.total_health => attackable,
+                _ => continue,
+            };
+            if potions.count == 0 {
+                println!("out of healing potions!");
+                break;
+            }
+            potions.count -= 1;
+            let amount = potions.heal_amount;
+            apply_heal(&mut combat_events, Some(entity), entity, amount, attackable);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::components::abilities::{Abilities, PendingAbility, HOTBAR_SLOTS};
+use crate::components::npc::{Npc, PlayerControlled};
+use crate::systems::ui::UiFont;
+
+use amethyst::{
+    derive::SystemDesc,
+    ecs::{
+        prelude::{Component, DenseVecStorage},
+        Join, Read, ReadStorage, System, SystemData, Write, WriteStorage,
+    },
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, Interactable, LineMode, UiEvent, UiEventType, UiImage, UiText, UiTransform},
+};
+
+const SLOT_WIDTH: f32 = 140.0;
+const SLOT_HEIGHT: f32 = 40.0;
+const SLOT_GAP: f32 = 8.0;
+
+const SLOT_COLOR: [f32; 4] = [0.1, 0.1, 0.15, 0.8];
+const SLOT_PENDING_COLOR: [f32; 4] = [0.4, 0.3, 0.1, 0.9];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const TEXT_COOLDOWN_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+// A button on the hotbar, bound to an ability slot.
+pub struct HotbarSlot {
+    pub slot: usize,
+}
+
+impl Component for HotbarSlot {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn initialize_hotbar(world: &mut World) {
+    let font = world.read_resource::<UiFont>().0.clone();
+
+    let total_width = HOTBAR_SLOTS as f32 * (SLOT_WIDTH + SLOT_GAP) - SLOT_GAP;
+    for slot in 0..HOTBAR_SLOTS {
+        let x = -(total_width * 0.5) + (SLOT_WIDTH * 0.5) + slot as f32 * (SLOT_WIDTH + SLOT_GAP);
+        let transform = UiTransform::new(
+            format!("hotbar_slot_{}", slot),
+            Anchor::BottomMiddle,
+            Anchor::BottomMiddle,
+            x,
+            SLOT_GAP,
+            1.0,
+            SLOT_WIDTH,
+            SLOT_HEIGHT,
+        );
+
+        world
+            .create_entity()
+            .with(transform)
+            .with(UiImage::SolidColor(SLOT_COLOR))
+            .with(UiText::new(
+                font.clone(),
+                format!("{}", slot + 1),
+                TEXT_COLOR,
+                16.0,
+                LineMode::Single,
+                Anchor::Middle,
+            ))
+            .with(Interactable)
+            .with(HotbarSlot { slot })
+            .build();
+    }
+}
+
+// Is the mouse (in screen coordinates, origin top left) over the hotbar?
+// World clicks there should be left to the UI.
+pub fn is_over_hotbar(mouse_pos: (f32, f32), screen_width: f32, screen_height: f32) -> bool {
+    let total_width = HOTBAR_SLOTS as f32 * (SLOT_WIDTH + SLOT_GAP) - SLOT_GAP;
+    let min_x = (screen_width - total_width) * 0.5;
+    let max_x = min_x + total_width;
+    let min_y = screen_height - SLOT_GAP - SLOT_HEIGHT;
+    mouse_pos.0 >= min_x && mouse_pos.0 <= max_x && mouse_pos.1 >= min_y
+}
+
+// Keeps the hotbar in sync with the abilities of the selected party
+// leader, and turns clicks on it into pending ability picks.
+#[derive(SystemDesc)]
+#[system_desc(name(HotbarSystemDesc))]
+pub struct HotbarSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<UiEvent>,
+}
+
+impl HotbarSystem {
+    pub fn new(reader_id: ReaderId<UiEvent>) -> Self {
+        HotbarSystem { reader_id }
+    }
+}
+
+impl<'s> System<'s> for HotbarSystem {
+    type SystemData = (
+        Read<'s, EventChannel<UiEvent>>,
+        Write<'s, PendingAbility>,
+        ReadStorage<'s, HotbarSlot>,
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, UiImage>,
+        ReadStorage<'s, Npc>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Abilities>,
+    );
+
+    fn run(
+        &mut self,
+        (ui_events, mut pending, hotbar_slots, mut texts, mut images, npcs, pcs, abilities): Self::SystemData
+    ) {
+        for event in ui_events.read(&mut self.reader_id) {
+            if event.event_type != UiEventType::Click {
+                continue;
+            }
+            if let Some(hotbar_slot) = hotbar_slots.get(event.target) {
+                pending.slot = match pending.slot {
+                    Some(slot) if slot == hotbar_slot.slot => None,
+                    _ => Some(hotbar_slot.slot),
+                };
+            }
+        }
+
+        let leader = (&npcs, &pcs, &abilities)
+            .join()
+            .find(|(npc, _, _)| npc.selected)
+            .map(|(_, _, abilities)| abilities);
+
+        for (hotbar_slot, text, image) in (&hotbar_slots, &mut texts, &mut images).join() {
+            let ability = leader.and_then(|a| a.slots.get(hotbar_slot.slot));
+            match ability {
+                Some(ability) => {
+                    if ability.is_ready() {
+                        text.text = format!("{}: {}", hotbar_slot.slot + 1, ability.name);
+                        text.color = TEXT_COLOR;
+                    } else {
+                        text.text = format!("{}: {} ({:.1})", hotbar_slot.slot + 1, ability.name, ability.cooldown_remaining);
+                        text.color = TEXT_COOLDOWN_COLOR;
+                    }
+                },
+                None => {
+                    text.text = format!("{}", hotbar_slot.slot + 1);
+                    text.color = TEXT_COOLDOWN_COLOR;
+                }
+            }
+
+            *image = match pending.slot {
+                Some(slot) if slot == hotbar_slot.slot => UiImage::SolidColor(SLOT_PENDING_COLOR),
+                _ => UiImage::SolidColor(SLOT_COLOR),
+            };
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::components::interpolation::Interpolation;
+use crate::resources::simulation::FixedStep;
+
+use amethyst::core::Transform;
+use amethyst::ecs::{Join, Read, ReadStorage, System, WriteStorage};
+
+// First thing every tick: puts transforms back where the simulation
+// left them, undoing the last frame's interpolation.
+pub struct TickStartSystem;
+
+impl<'s> System<'s> for TickStartSystem {
+    type SystemData = (
+        WriteStorage<'s, Interpolation>,
+        WriteStorage<'s, Transform>,
+    );
+
+    fn run(&mut self, (mut interpolations, mut transforms): Self::SystemData) {
+        for (interpolation, transform) in (&mut interpolations, &mut transforms).join() {
+            interpolation.previous = interpolation.current;
+            transform.set_translation_x(interpolation.current[0]);
+            transform.set_translation_y(interpolation.current[1]);
+        }
+    }
+}
+
+// Last thing every tick: remembers where everything ended up.
+pub struct TickEndSystem;
+
+impl<'s> System<'s> for TickEndSystem {
+    type SystemData = (
+        WriteStorage<'s, Interpolation>,
+        ReadStorage<'s, Transform>,
+    );
+
+    fn run(&mut self, (mut interpolations, transforms): Self::SystemData) {
+        for (interpolation, transform) in (&mut interpolations, &transforms).join() {
+            interpolation.current = [transform.translation().x, transform.translation().y];
+        }
+    }
+}
+
+// Once per frame: draws everything between its last two ticks.
+pub struct InterpolationSystem;
+
+impl<'s> System<'s> for InterpolationSystem {
+    type SystemData = (
+        Read<'s, FixedStep>,
+        ReadStorage<'s, Interpolation>,
+        WriteStorage<'s, Transform>,
+    );
+
+    fn run(&mut self, (step, interpolations, mut transforms): Self::SystemData) {
+        let alpha = step.alpha();
+        for (interpolation, transform) in (&interpolations, &mut transforms).join() {
+            let [x, y] = interpolation.lerp(alpha);
+            transform.set_translation_x(x);
+            transform.set_translation_y(y);
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use crate::components::abilities::Energy;
+use crate::components::items::{Consumable, EquipSlot, Equipment, Item, ItemKind, EQUIP_SLOTS};
+use crate::components::npc::{Npc, Attackable, Attacker, PlayerControlled, Downed};
+use crate::components::save::{Persistent, marker_id};
+use crate::components::stats::{Resistances, Stats};
+use crate::components::status::StatusEffects;
+use crate::resources::events::CombatEvent;
+use crate::resources::items::{Inventory, INVENTORY_CAPACITY};
+use crate::resources::orders::{GearChange, PartyOrders};
+use crate::resources::replay::{RecordedCommand, RecordedOrder, Replay};
+use crate::resources::simulation::FixedStep;
+use crate::systems::combat::apply_heal;
+use crate::systems::ui::{OpenScreen, Screen, UiFont, set_hidden};
+
+use amethyst::{
+    core::Hidden,
+    derive::SystemDesc,
+    ecs::{
+        prelude::{Component, DenseVecStorage, Entities, Entity},
+        Join, Read, ReadStorage, System, SystemData, Write, WriteStorage,
+    },
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, Interactable, LineMode, UiEvent, UiEventType, UiImage, UiText, UiTransform},
+};
+
+const PANEL_WIDTH: f32 = 720.0;
+const COLUMN_WIDTH: f32 = 340.0;
+const ROW_HEIGHT: f32 = 28.0;
+const ROW_GAP: f32 = 4.0;
+const HEADER_HEIGHT: f32 = 48.0;
+
+const PANEL_COLOR: [f32; 4] = [0.05, 0.05, 0.1, 0.9];
+const ROW_COLOR: [f32; 4] = [0.1, 0.1, 0.15, 0.9];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const EMPTY_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+// Puts `item` on in its slot, returning whatever was there before.
+// Items that can't be equipped are handed straight back.
+pub fn equip(
+    item: Item,
+    equipment: &mut Equipment,
+    attacker: Option<&mut Attacker>,
+    attackable: Option<&mut Attackable>,
+    stats: Option<&mut Stats>,
+    resistances: Option<&mut Resistances>,
+) -> Option<Item> {
+    let slot = match item.equip_slot() {
+        Some(slot) => slot,
+        None => return Some(item),
+    };
+
+    let mut attacker = attacker;
+    let mut attackable = attackable;
+    let mut stats = stats;
+    let mut resistances = resistances;
+
+    let previous = equipment.slots.remove(&slot);
+    if let Some(previous) = &previous {
+        previous.total_bonus().apply(
+            -1.0,
+            attacker.as_mut().map(|a| &mut **a),
+            attackable.as_mut().map(|a| &mut **a),
+            stats.as_mut().map(|s| &mut **s),
+            resistances.as_mut().map(|r| &mut **r),
+        );
+    }
+    item.total_bonus().apply(1.0, attacker, attackable, stats, resistances);
+    equipment.slots.insert(slot, item);
+    previous
+}
+
+// Takes off whatever is in `slot`.
+pub fn unequip(
+    slot: EquipSlot,
+    equipment: &mut Equipment,
+    attacker: Option<&mut Attacker>,
+    attackable: Option<&mut Attackable>,
+    stats: Option<&mut Stats>,
+    resistances: Option<&mut Resistances>,
+) -> Option<Item> {
+    let item = equipment.slots.remove(&slot)?;
+    item.total_bonus().apply(-1.0, attacker, attackable, stats, resistances);
+    Some(item)
+}
+
+// Everything about a party member that changing their gear touches.
+pub type GearStorages<'s> = (
+    WriteStorage<'s, Equipment>,
+    WriteStorage<'s, Attacker>,
+    WriteStorage<'s, Attackable>,
+    WriteStorage<'s, Stats>,
+    WriteStorage<'s, Resistances>,
+    WriteStorage<'s, Energy>,
+    WriteStorage<'s, StatusEffects>,
+    ReadStorage<'s, Downed>,
+    Write<'s, EventChannel<CombatEvent>>,
+);
+
+// Equips or uses one of the party's items on `member`, or takes
+// something of theirs off. Returns false if nothing changed.
+pub fn change_gear(member: Entity, change: GearChange, inventory: &mut Inventory, gear: &mut GearStorages) -> bool {
+    let (equipments, attackers, attackables, stats, resistances, energies, statuses, downeds, combat_events) = gear;
+    let equipment = match equipments.get_mut(member) {
+        Some(equipment) => equipment,
+        None => return false,
+    };
+
+    let index = match change {
+        GearChange::TakeOff(slot) => {
+            if inventory.is_full() {
+                println!("the party can't carry any more!");
+                return false;
+            }
+            let item = unequip(
+                slot,
+                equipment,
+                attackers.get_mut(member),
+                attackables.get_mut(member),
+                stats.get_mut(member),
+                resistances.get_mut(member),
+            );
+            return match item {
+                Some(item) => {
+                    inventory.items.push(item);
+                    true
+                },
+                None => false,
+            };
+        },
+        GearChange::Use(index) if index < inventory.items.len() => index,
+        GearChange::Use(_) => return false,
+    };
+
+    match inventory.items[index].kind.clone() {
+        ItemKind::Equipment(_) => {
+            let item = inventory.items.remove(index);
+            let previous = equip(
+                item,
+                equipment,
+                attackers.get_mut(member),
+                attackables.get_mut(member),
+                stats.get_mut(member),
+                resistances.get_mut(member),
+            );
+            if let Some(previous) = previous {
+                inventory.items.insert(index, previous);
+            }
+            true
+        },
+        ItemKind::Consumable(consumable) => {
+            if downeds.contains(member) {
+                return false;
+            }
+            match consumable {
+                Consumable::Heal(amount) => {
+                    if let Some(attackable) = attackables.get_mut(member) {
+                        apply_heal(combat_events, Some(member), member, amount, attackable);
+                    }
+                },
+                Consumable::RestoreMana(amount) => {
+                    if let Some(energy) = energies.get_mut(member) {
+                        energy.mana = (energy.mana + amount).min(energy.max_mana);
+                    }
+                },
+                Consumable::Status(effect) => {
+                    if let Some(status) = statuses.get_mut(member) {
+                        status.apply(effect.from_source(member));
+                    }
+                },
+            }
+            inventory.items.remove(index);
+            true
+        },
+        ItemKind::Key(_) => {
+            println!("keys are used by walking up to what they open.");
+            false
+        },
+    }
+}
+
+// Makes the gear changes a replay played back.
+pub struct GearSystem;
+
+impl<'s> System<'s> for GearSystem {
+    type SystemData = (
+        Write<'s, PartyOrders>,
+        Write<'s, Inventory>,
+        GearStorages<'s>,
+    );
+
+    fn run(&mut self, (mut orders, mut inventory, mut gear): Self::SystemData) {
+        for (member, change) in orders.gear.drain(..) {
+            change_gear(member, change, &mut inventory, &mut gear);
+        }
+    }
+}
+
+// The inventory screen's background and header.
+pub struct InventoryHeader;
+
+impl Component for InventoryHeader {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A button showing what the selected character has in one slot.
+pub struct EquipmentRow {
+    pub slot: EquipSlot,
+}
+
+impl Component for EquipmentRow {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A button showing one item in the party's inventory.
+pub struct InventoryRow {
+    pub index: usize,
+}
+
+impl Component for InventoryRow {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn initialize_inventory_screen(world: &mut World) {
+    let font = world.read_resource::<UiFont>().0.clone();
+
+    let panel_height = HEADER_HEIGHT + INVENTORY_CAPACITY as f32 * (ROW_HEIGHT + ROW_GAP) + ROW_GAP;
+    let top = panel_height * 0.5;
+    let row_y = |index: usize| top - HEADER_HEIGHT - ROW_HEIGHT * 0.5 - index as f32 * (ROW_HEIGHT + ROW_GAP);
+    let column_x = PANEL_WIDTH * 0.25;
+
+    world
+        .create_entity()
+        .with(UiTransform::new(
+            "inventory_header".to_string(),
+            Anchor::Middle,
+            Anchor::Middle,
+            0.0,
+            0.0,
+            10.0,
+            PANEL_WIDTH,
+            panel_height,
+        ))
+        .with(UiImage::SolidColor(PANEL_COLOR))
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            TEXT_COLOR,
+            18.0,
+            LineMode::Single,
+            Anchor::TopMiddle,
+        ))
+        .with(InventoryHeader)
+        .with(Hidden)
+        .build();
+
+    for (index, slot) in EQUIP_SLOTS.iter().enumerate() {
+        world
+            .create_entity()
+            .with(UiTransform::new(
+                format!("equipment_row_{}", index),
+                Anchor::Middle,
+                Anchor::Middle,
+                -column_x,
+                row_y(index),
+                11.0,
+                COLUMN_WIDTH,
+                ROW_HEIGHT,
+            ))
+            .with(UiImage::SolidColor(ROW_COLOR))
+            .with(UiText::new(
+                font.clone(),
+                String::new(),
+                TEXT_COLOR,
+                14.0,
+                LineMode::Single,
+                Anchor::Middle,
+            ))
+            .with(Interactable)
+            .with(EquipmentRow { slot: *slot })
+            .with(Hidden)
+            .build();
+    }
+
+    for index in 0..INVENTORY_CAPACITY {
+        world
+            .create_entity()
+            .with(UiTransform::new(
+                format!("inventory_row_{}", index),
+                Anchor::Middle,
+                Anchor::Middle,
+                column_x,
+                row_y(index),
+                11.0,
+                COLUMN_WIDTH,
+                ROW_HEIGHT,
+            ))
+            .with(UiImage::SolidColor(ROW_COLOR))
+            .with(UiText::new(
+                font.clone(),
+                String::new(),
+                TEXT_COLOR,
+                14.0,
+                LineMode::Single,
+                Anchor::Middle,
+            ))
+            .with(Interactable)
+            .with(InventoryRow { index })
+            .with(Hidden)
+            .build();
+    }
+}
+
+// Shows the inventory screen while the inventory state has it open.
+// Clicking an item in the party's inventory equips or uses it on the first selected party
+// member, clicking one of their slots puts the item back.
+#[derive(SystemDesc)]
+#[system_desc(name(InventoryScreenSystemDesc))]
+pub struct InventoryScreenSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<UiEvent>,
+}
+
+impl InventoryScreenSystem {
+    pub fn new(reader_id: ReaderId<UiEvent>) -> Self {
+        InventoryScreenSystem { reader_id }
+    }
+}
+
+impl<'s> System<'s> for InventoryScreenSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<UiEvent>>,
+        Read<'s, OpenScreen>,
+        Write<'s, Inventory>,
+        ReadStorage<'s, InventoryHeader>,
+        ReadStorage<'s, EquipmentRow>,
+        ReadStorage<'s, InventoryRow>,
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, Hidden>,
+        ReadStorage<'s, Npc>,
+        ReadStorage<'s, PlayerControlled>,
+        GearStorages<'s>,
+        Write<'s, Replay>,
+        Read<'s, FixedStep>,
+        ReadStorage<'s, Persistent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            ui_events,
+            screen,
+            mut inventory,
+            headers,
+            equipment_rows,
+            inventory_rows,
+            mut texts,
+            mut hiddens,
+            npcs,
+            pcs,
+            mut gear,
+            mut replay,
+            step,
+            markers,
+        ): Self::SystemData
+    ) {
+        let open = screen.is_open(Screen::Inventory);
+
+        let member = (&entities, &npcs, &pcs, &gear.0)
+            .join()
+            .find(|(_, npc, _, _)| npc.selected)
+            .map(|(e, _, _, _)| e);
+
+        for event in ui_events.read(&mut self.reader_id) {
+            // A replay makes its own changes.
+            if !open || event.event_type != UiEventType::Click || replay.is_playing() {
+                continue;
+            }
+            let member = match member {
+                Some(member) => member,
+                None => continue,
+            };
+            let change = if let Some(row) = equipment_rows.get(event.target) {
+                GearChange::TakeOff(row.slot)
+            } else if let Some(row) = inventory_rows.get(event.target) {
+                GearChange::Use(row.index)
+            } else {
+                continue;
+            };
+            if change_gear(member, change, &mut inventory, &mut gear) {
+                replay.record(RecordedCommand {
+                    tick: step.tick,
+                    units: vec![marker_id(&markers, member)],
+                    order: RecordedOrder::Gear(change),
+                });
+            }
+        }
+
+        let equipment = member.and_then(|m| gear.0.get(m));
+
+        for (entity, _) in (&entities, &headers).join() {
+            set_hidden(&mut hiddens, entity, !open);
+            if let Some(text) = texts.get_mut(entity) {
+                text.text = format!(
+                    "equipment    -    inventory ({}/{})    -    {} gold",
+                    inventory.items.len(),
+                    INVENTORY_CAPACITY,
+                    inventory.gold,
+                );
+            }
+        }
+
+        for (entity, row) in (&entities, &equipment_rows).join() {
+            set_hidden(&mut hiddens, entity, !open || equipment.is_none());
+            let item = equipment.and_then(|e| e.slots.get(&row.slot));
+            if let Some(text) = texts.get_mut(entity) {
+                match item {
+                    Some(item) => {
+                        text.text = format!("{:?}: {}", row.slot, item.display_name());
+                        text.color = item.rarity.color();
+                    },
+                    None => {
+                        text.text = format!("{:?}: -", row.slot);
+                        text.color = EMPTY_COLOR;
+                    },
+                }
+            }
+        }
+
+        for (entity, row) in (&entities, &inventory_rows).join() {
+            let item = inventory.items.get(row.index);
+            set_hidden(&mut hiddens, entity, !open || item.is_none());
+            if let (Some(text), Some(item)) = (texts.get_mut(entity), item) {
+                text.text = item.display_name();
+                text.color = item.rarity.color();
+            }
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use crate::components::items::Item;
+use crate::components::loot::{Chest, Collecting, LootSheet, Pickup, CHEST_OPEN_SPRITE};
+use crate::components::npc::{ArchetypeId, Npc, PlayerControlled, Downed};
+use crate::components::save::{Persistent, marker_id};
+use crate::resources::affixes::ItemGenerator;
+use crate::resources::archetypes::ArchetypeRegistry;
+use crate::resources::dungeon::Dungeon;
+use crate::resources::events::CombatEvent;
+use crate::resources::items::{Inventory, ItemDatabase};
+use crate::resources::loot::LootTables;
+use crate::resources::run::Run;
+use crate::resources::simulation::FixedStep;
+use crate::systems::commands::distance;
+
+use amethyst::core::Transform;
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{
+    prelude::{Entity, Entities, LazyUpdate},
+    saveload::MarkedBuilder,
+    Join, Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteStorage,
+};
+use amethyst::renderer::SpriteRender;
+use amethyst::shrev::{EventChannel, ReaderId};
+use rand::Rng;
+
+// Loot lies on the floor, under anything layered.
+const PICKUP_Z: f32 = 0.5;
+// How close a party member sent for a pickup has to get to pick it up.
+const PICKUP_RADIUS: f32 = 20.0;
+// How close a party member has to walk to open a chest.
+const CHEST_RADIUS: f32 = 30.0;
+// How far dropped loot is scattered around where it fell.
+const SCATTER_RADIUS: f32 = 14.0;
+
+// Drops a pickup at `at`. Meant to be called from inside a system,
+// the entity is only created once the world is maintained.
+pub fn spawn_pickup(
+    lazy: &LazyUpdate,
+    entities: &Entities,
+    sheet: &LootSheet,
+    pickup: Pickup,
+    at: [f32; 2],
+) -> Entity {
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(at[0], at[1], PICKUP_Z);
+
+    let render = SpriteRender {
+        sprite_sheet: sheet.0.clone(),
+        sprite_number: pickup.sprite_number(),
+    };
+
+    lazy.create_entity(entities)
+        .with(render)
+        .with(transform)
+        .with(pickup)
+        .marked::<Persistent>()
+        .build()
+}
+
+// Spills items and gold around `at`, one pickup each.
+fn scatter_loot<R: Rng>(
+    rng: &mut R,
+    lazy: &LazyUpdate,
+    entities: &Entities,
+    sheet: &LootSheet,
+    (items, gold): (Vec<Item>, u32),
+    at: [f32; 2],
+) {
+    let mut pickups: Vec<Pickup> = items
+        .into_iter()
+        .map(|item| Pickup { item: Some(item), gold: 0 })
+        .collect();
+    if gold > 0 {
+        pickups.push(Pickup { item: None, gold: gold });
+    }
+
+    for pickup in pickups {
+        let offset = [
+            rng.gen_range(-SCATTER_RADIUS, SCATTER_RADIUS),
+            rng.gen_range(-SCATTER_RADIUS, SCATTER_RADIUS),
+        ];
+        spawn_pickup(lazy, entities, sheet, pickup, [at[0] + offset[0], at[1] + offset[1]]);
+    }
+}
+
+// Rolls the loot table of everything the party kills and
+// drops the loot where it fell.
+#[derive(SystemDesc)]
+#[system_desc(name(LootDropSystemDesc))]
+pub struct LootDropSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<CombatEvent>,
+}
+
+impl LootDropSystem {
+    pub fn new(reader_id: ReaderId<CombatEvent>) -> Self {
+        LootDropSystem { reader_id }
+    }
+}
+
+impl<'s> System<'s> for LootDropSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<CombatEvent>>,
+        ReadExpect<'s, ArchetypeRegistry>,
+        ReadExpect<'s, LootTables>,
+        ReadExpect<'s, ItemDatabase>,
+        ReadExpect<'s, ItemGenerator>,
+        ReadExpect<'s, LootSheet>,
+        Read<'s, Dungeon>,
+        Read<'s, FixedStep>,
+        ReadStorage<'s, Persistent>,
+        ReadStorage<'s, ArchetypeId>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Transform>,
+        Read<'s, LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            combat_events,
+            registry,
+            loot_tables,
+            item_database,
+            generator,
+            loot_sheet,
+            dungeon,
+            step,
+            markers,
+            archetype_ids,
+            pcs,
+            transforms,
+            lazy,
+        ): Self::SystemData
+    ) {
+        for event in combat_events.read(&mut self.reader_id) {
+            let entity = match event {
+                CombatEvent::Death { entity, .. } if !pcs.contains(*entity) => *entity,
+                _ => continue,
+            };
+
+            let table_id = archetype_ids
+                .get(entity)
+                .and_then(|id| registry.get(&id.0))
+                .and_then(|a| a.loot_table.clone());
+            let table = match table_id {
+                Some(id) => match loot_tables.get(&id) {
+                    Some(table) => table,
+                    None => {
+                        println!("unknown loot table : {}", id);
+                        continue;
+                    },
+                },
+                None => continue,
+            };
+            let at = match transforms.get(entity) {
+                Some(t) => [t.translation().x, t.translation().y],
+                None => continue,
+            };
+
+            // Rolled for the fallen unit, so what two units killed on the
+            // same tick drop doesn't depend on which is gone through first.
+            let mut rng = dungeon.roll_rng(step.tick, marker_id(&markers, entity), 0);
+            let loot = table.roll(&mut rng, &item_database, &generator, dungeon.depth);
+            scatter_loot(&mut rng, &lazy, &entities, &loot_sheet, loot, at);
+        }
+    }
+}
+
+// Picks up whatever party members were sent for once they reach it,
+// into the shared inventory, and opens chests the party walks up to.
+#[derive(Default)]
+pub struct PickupSystem {
+    // The locked chest the party was last told about, so the
+    // message isn't repeated every frame they stand next to it.
+    locked_chest: Option<Entity>,
+}
+
+impl<'s> System<'s> for PickupSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Pickup>,
+        WriteStorage<'s, Chest>,
+        WriteStorage<'s, SpriteRender>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Downed>,
+        ReadExpect<'s, LootTables>,
+        ReadExpect<'s, ItemDatabase>,
+        ReadExpect<'s, ItemGenerator>,
+        ReadExpect<'s, LootSheet>,
+        Read<'s, Dungeon>,
+        Read<'s, FixedStep>,
+        ReadStorage<'s, Persistent>,
+        Write<'s, Inventory>,
+        Write<'s, Run>,
+        Read<'s, LazyUpdate>,
+        WriteStorage<'s, Collecting>,
+        ReadStorage<'s, Npc>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut pickups,
+            mut chests,
+            mut renders,
+            transforms,
+            pcs,
+            downeds,
+            loot_tables,
+            item_database,
+            generator,
+            loot_sheet,
+            dungeon,
+            step,
+            markers,
+            mut inventory,
+            mut run,
+            lazy,
+            mut collectings,
+            npcs,
+        ): Self::SystemData
+    ) {
+        let party: Vec<[f32; 2]> = (&pcs, &transforms, !&downeds)
+            .join()
+            .map(|(_, t, _)| [t.translation().x, t.translation().y])
+            .collect();
+        let near_party = |pos: [f32; 2], radius: f32| party.iter().any(|p| distance(*p, pos) <= radius);
+
+        // Who reached what they were sent for, and who has given up on it.
+        let mut reached: Vec<Entity> = vec![];
+        let mut done: Vec<Entity> = vec![];
+        for (unit, collecting, transform, npc, _) in (&entities, &collectings, &transforms, &npcs, !&downeds).join() {
+            let pickup_pos = match transforms.get(collecting.pickup) {
+                Some(t) if pickups.contains(collecting.pickup) => [t.translation().x, t.translation().y],
+                _ => {
+                    done.push(unit);
+                    continue;
+                },
+            };
+            if distance([transform.translation().x, transform.translation().y], pickup_pos) <= PICKUP_RADIUS {
+                if !reached.contains(&collecting.pickup) {
+                    reached.push(collecting.pickup);
+                }
+                done.push(unit);
+            } else if npc.velocity == [0.0, 0.0] {
+                // Stopped short, e.g. against a wall.
+                done.push(unit);
+            }
+        }
+        for unit in done {
+            collectings.remove(unit);
+        }
+
+        for entity in reached {
+            let pickup = match pickups.get_mut(entity) {
+                Some(pickup) => pickup,
+                None => continue,
+            };
+
+            if pickup.gold > 0 {
+                inventory.gold += pickup.gold;
+                run.stats.gold_earned += pickup.gold;
+                println!("picked up {} gold ({} total)", pickup.gold, inventory.gold);
+                pickup.gold = 0;
+            }
+            if let Some(item) = pickup.item.take() {
+                let name = item.display_name();
+                match inventory.add(item) {
+                    Ok(()) => println!("picked up {}", name),
+                    Err(item) => {
+                        // Leave it where it is, to be fetched once there's room.
+                        println!("no room in the inventory for {}.", name);
+                        pickup.item = Some(item);
+                        continue;
+                    },
+                }
+            }
+
+            if let Err(e) = entities.delete(entity) {
+                println!("error removing pickup : {}", e);
+            }
+        }
+
+        let mut near_locked = None;
+
+        for (entity, chest, transform) in (&entities, &mut chests, &transforms).join() {
+            let pos = [transform.translation().x, transform.translation().y];
+            if chest.opened || !near_party(pos, CHEST_RADIUS) {
+                continue;
+            }
+
+            if let Some(lock) = &chest.key {
+                match inventory.take_key(lock) {
+                    Some(key) => println!("the {} opens the chest.", key.display_name()),
+                    None => {
+                        if self.locked_chest != Some(entity) {
+                            println!("the chest is locked.");
+                        }
+                        near_locked = Some(entity);
+                        continue;
+                    },
+                }
+            }
+
+            chest.opened = true;
+            if let Some(render) = renders.get_mut(entity) {
+                render.sprite_number = CHEST_OPEN_SPRITE;
+            }
+
+            match loot_tables.get(&chest.loot_table) {
+                Some(table) => {
+                    let mut rng = dungeon.roll_rng(step.tick, marker_id(&markers, entity), 0);
+                    let loot = table.roll(&mut rng, &item_database, &generator, dungeon.depth);
+                    scatter_loot(&mut rng, &lazy, &entities, &loot_sheet, loot, pos);
+                },
+                None => println!("unknown loot table : {}", chest.loot_table),
+            }
+        }
+
+        self.locked_chest = near_locked;
+    }
+}
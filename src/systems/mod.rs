@@ -3,4 +3,25 @@ pub mod movement;
 pub mod combat;
 pub mod enemy_targeting;
 pub mod animation;
-pub mod layering;
\ No newline at end of file
+pub mod layering;
+pub mod combat_log;
+pub mod death;
+pub mod projectile;
+pub mod abilities;
+pub mod hotbar;
+pub mod status;
+pub mod healing;
+pub mod party;
+pub mod progression;
+pub mod ui;
+pub mod inventory;
+pub mod loot;
+pub mod shop;
+pub mod save;
+pub mod run;
+pub mod simulation;
+pub mod speed;
+pub mod turns;
+pub mod interpolation;
+pub mod feedback;
+pub mod exploration;
@@ -1,18 +1,29 @@
-use crate::components::npc::Npc;
-use crate::components::tile::Tile;
+use crate::components::npc::{Npc, Waiting};
 use crate::components::animated::WalkAnimation;
+use crate::components::status::StatusEffects;
+use crate::resources::map::{MapGrid, UNIT_HEIGHT, UNIT_WIDTH};
+use crate::resources::simulation::TICK_SECONDS;
+use crate::systems::commands::{calc_velocity_vec, distance};
+use crate::{TILE_WIDTH, calc_tile_center};
 
 use amethyst::core::{
     math::Vector3,
-    timing::Time,
     Transform,
 };
 use amethyst::renderer::SpriteRender;
-use amethyst::ecs::{Join, Read, System, WriteStorage, ReadStorage};
+use amethyst::ecs::{prelude::Entities, Join, ReadExpect, System, WriteStorage, ReadStorage};
+
+use std::cmp;
+use std::collections::VecDeque;
 
 const EQUAL_MARGIN: f32 = 2.0;
 const HIT_BOX_BUFFER_TOP: f32 = 20.0;
 const HIT_BOX_BUFFER_BOT: f32 = 10.0;
+// How far apart points are checked along a line walked in one go.
+const LINE_STEP: f32 = 2.0;
+// How many tiles along the way around units look for one they can
+// head straight for.
+const LOOK_AHEAD: usize = 8;
 
 pub struct MovementSystem;
 
@@ -20,55 +31,51 @@ impl<'s> System<'s> for MovementSystem {
     type SystemData = (
         WriteStorage<'s, Transform>,
         WriteStorage<'s, Npc>,
-        Read<'s, Time>,
-        ReadStorage<'s, Tile>,
+        ReadExpect<'s, MapGrid>,
         WriteStorage<'s, WalkAnimation>,
         WriteStorage<'s, SpriteRender>,
+        Entities<'s>,
+        ReadStorage<'s, StatusEffects>,
+        ReadStorage<'s, Waiting>,
     );
 
-    fn run(&mut self, (mut transforms, mut npcs, time, tiles, mut anims, mut renders): Self::SystemData) {
-        for (transform, npc, anim, render) in (&mut transforms, &mut npcs, &mut anims, &mut renders).join() {
+    fn run(&mut self, (mut transforms, mut npcs, grid, mut anims, mut renders, entities, statuses, waitings): Self::SystemData) {
+        // Units waiting for their turn keep their orders for when it comes.
+        for (entity, transform, npc, anim, render, _) in (&entities, &mut transforms, &mut npcs, &mut anims, &mut renders, !&waitings).join() {
             if npc.velocity == [0.0, 0.0] {
                 continue
             }
-            // Animate walking
-            anim.anim.animate(time.delta_seconds(), render);
 
-            let mut to_move = true;
+            // Slows scale the unit's move speed, stuns hold it in place.
+            let speed_factor = statuses.get(entity).map_or(1.0, |s| s.move_speed_multiplier());
+            if speed_factor <= 0.0 {
+                continue
+            }
+
+            // Walk around whatever is in the way of where the unit was sent.
+            let position = [transform.translation().x, transform.translation().y];
+            let waypoint = next_waypoint(&grid, position, npc.move_coords);
+            if distance(position, waypoint) > 0.0 {
+                npc.velocity = calc_velocity_vec(position, waypoint, npc.move_speed);
+            }
 
-            let delta_x = npc.velocity[0] * time.delta_seconds();
-            let delta_y = npc.velocity[1] * time.delta_seconds();
+            // Animate walking
+            anim.anim.animate(TICK_SECONDS, render);
+
+            let delta_x = npc.velocity[0] * speed_factor * TICK_SECONDS;
+            let delta_y = npc.velocity[1] * speed_factor * TICK_SECONDS;
             let new_x = transform.translation().x + delta_x;
             let new_y = transform.translation().y + delta_y;
 
-            // Find all the tiles that should potentially be blocking our movement.
-            for tile in (&tiles)
-                .join()
-                .filter(|t| t.blocking == true) 
-                .filter(|t| point_distance([new_x, new_y], t.center) <= 30.0)
-            {
-                let lower_left = tile.hit_box[0];
-                let upper_right = tile.hit_box[1];
-
-                // Check to see if we are going to collide with a blocking
-                // tile. also add some buffer space to the hit box to avoid clipping.
-                if new_x >= lower_left[0] - HIT_BOX_BUFFER_BOT
-                    && new_x <= upper_right[0] + HIT_BOX_BUFFER_BOT
-                    && new_y >= lower_left[1] - HIT_BOX_BUFFER_BOT
-                    && new_y <= upper_right[1] + HIT_BOX_BUFFER_TOP
-                {
-                    to_move = false;
-                    npc.velocity = [0.0, 0.0];
-                }
-            }
-
-            if to_move {
+            if collides(&grid, [new_x, new_y], 0.0) {
+                npc.velocity = [0.0, 0.0];
+            } else {
                 transform.prepend_translation_x(delta_x);
                 transform.prepend_translation_y(delta_y);
                 // make sure that sprite is facing correctly
                 let scale_x = transform.scale()[0];
                 if delta_x < 0.0 && scale_x > 0.0
-                    ||  delta_x > 0.0 && scale_x < 0.0 
+                    ||  delta_x > 0.0 && scale_x < 0.0
                 {
                     transform.set_scale(Vector3::new(scale_x * -1.0, 1.0, 1.0));
                 }
@@ -82,9 +89,116 @@ impl<'s> System<'s> for MovementSystem {
     }
 }
 
-fn point_distance(p1: [f32; 2], p2: [f32;2]) -> f32 {
-    let vector = [p2[0] - p1[0], p2[1] - p1[1]];
-    (vector[0].powf(2.0) + vector[1].powf(2.0)).sqrt()
+// Would a unit standing at `point` be inside a blocking tile? Hit boxes
+// get some buffer space to keep sprites from clipping into walls, and
+// `margin` more on top.
+fn collides(grid: &MapGrid, point: [f32; 2], margin: f32) -> bool {
+    let x = (point[0].max(0.0) / TILE_WIDTH) as usize;
+    let y = (point[1].max(0.0) / TILE_WIDTH) as usize;
+    // Buffers and tall tiles reach at most two tiles over.
+    for tile_x in x.saturating_sub(2)..=x + 2 {
+        for tile_y in y.saturating_sub(2)..=y + 2 {
+            if let Some([lower_left, upper_right]) = grid.hit_box(tile_x, tile_y) {
+                if point[0] >= lower_left[0] - HIT_BOX_BUFFER_BOT - margin
+                    && point[0] <= upper_right[0] + HIT_BOX_BUFFER_BOT + margin
+                    && point[1] >= lower_left[1] - HIT_BOX_BUFFER_BOT - margin
+                    && point[1] <= upper_right[1] + HIT_BOX_BUFFER_TOP + margin
+                {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+// Can a unit walk straight from `from` to `to` without running into
+// anything? Every point of the line is within half a step of one that
+// is checked, so that much margin covers the points in between.
+fn clear_line(grid: &MapGrid, from: [f32; 2], to: [f32; 2]) -> bool {
+    let steps = (distance(from, to) / LINE_STEP).ceil().max(1.0) as usize;
+    (1..=steps).all(|step| {
+        let t = step as f32 / steps as f32;
+        !collides(grid, [from[0] + (to[0] - from[0]) * t, from[1] + (to[1] - from[1]) * t], LINE_STEP / 2.0)
+    })
+}
+
+fn tile_of(point: [f32; 2]) -> [usize; 2] {
+    [(point[0].max(0.0) / TILE_WIDTH) as usize, (point[1].max(0.0) / TILE_WIDTH) as usize]
+}
+
+fn tile_center(tile: [usize; 2]) -> [f32; 2] {
+    [calc_tile_center(tile[0]), calc_tile_center(tile[1])]
+}
+
+// Where to head for next on the way from `from` to `to`: straight there
+// if nothing is in the way, otherwise the furthest of the next few tiles
+// along the shortest way around that can be walked to in a straight line. With no
+// way around, units walk straight at it until they bump into something.
+fn next_waypoint(grid: &MapGrid, from: [f32; 2], to: [f32; 2]) -> [f32; 2] {
+    if clear_line(grid, from, to) {
+        return to;
+    }
+    let path = match find_path(grid, tile_of(from), tile_of(to)) {
+        Some(path) => path,
+        None => return to,
+    };
+    let ahead = &path[1..cmp::min(path.len(), LOOK_AHEAD + 1)];
+    ahead
+        .iter()
+        .rev()
+        .map(|tile| if *tile == tile_of(to) { to } else { tile_center(*tile) })
+        .find(|point| clear_line(grid, from, *point))
+        .or_else(|| ahead.first().map(|tile| tile_center(*tile)))
+        .filter(|point| distance(from, *point) >= EQUAL_MARGIN)
+        .unwrap_or(to)
+}
+
+// The tiles on the shortest way from `start` to `goal`, both included,
+// only going through tiles a unit fits in. Diagonal steps can't cut
+// corners.
+fn find_path(grid: &MapGrid, start: [usize; 2], goal: [usize; 2]) -> Option<Vec<[usize; 2]>> {
+    if start[0] >= UNIT_WIDTH || start[1] >= UNIT_HEIGHT || grid.is_blocking(goal[0], goal[1]) {
+        return None;
+    }
+    let index = |tile: [usize; 2]| tile[0] * UNIT_HEIGHT + tile[1];
+    let fits = |tile: [usize; 2]| tile == goal || !collides(grid, tile_center(tile), 0.0);
+    let mut came_from: Vec<Option<[usize; 2]>> = vec![None; UNIT_WIDTH * UNIT_HEIGHT];
+    let mut queue = VecDeque::new();
+    came_from[index(start)] = Some(start);
+    queue.push_back(start);
+
+    while let Some(tile) = queue.pop_front() {
+        if tile == goal {
+            let mut path = vec![goal];
+            let mut at = goal;
+            while at != start {
+                at = came_from[index(at)]?;
+                path.push(at);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)].iter() {
+            let x = tile[0] as i64 + dx;
+            let y = tile[1] as i64 + dy;
+            if x < 0 || y < 0 || x >= UNIT_WIDTH as i64 || y >= UNIT_HEIGHT as i64 {
+                continue;
+            }
+            let next = [x as usize, y as usize];
+            if came_from[index(next)].is_some() || !fits(next) {
+                continue;
+            }
+            if *dx != 0 && *dy != 0
+                && (!fits([next[0], tile[1]]) || !fits([tile[0], next[1]]))
+            {
+                continue;
+            }
+            came_from[index(next)] = Some(tile);
+            queue.push_back(next);
+        }
+    }
+    None
 }
 
 // Calcs if a point is "close enough" to a given target.
@@ -93,4 +207,4 @@ fn point_within(target: [f32; 2], point: [f32;2]) -> bool {
     let within_x = point[0] >= target[0] - EQUAL_MARGIN && point[0] <= target[0] + EQUAL_MARGIN;
     let within_y = point[1] >= target[1] - EQUAL_MARGIN && point[1] <= target[1] + EQUAL_MARGIN;
     within_x && within_y
-}
\ No newline at end of file
+}
@@ -0,0 +1,239 @@
+use crate::components::abilities::Energy;
+use crate::components::items::Equipment;
+use crate::components::npc::{Npc, Attackable, Attacker, ArchetypeId, CanTarget, PlayerControlled, Recruitable, Recruiting, SelectAura};
+use crate::components::progression::Experience;
+use crate::components::stats::{Resistances, Stats};
+use crate::resources::party::{PartyRoster, SavedMember, MAX_PARTY_SIZE};
+use crate::resources::replay::Replay;
+use crate::systems::commands::{calc_velocity_vec, distance};
+
+use amethyst::core::{
+    Hidden,
+    Parent,
+    Transform,
+};
+use amethyst::ecs::{
+    prelude::{Entity, Entities},
+    Join, Read, ReadStorage, System, Write, WriteStorage,
+};
+use amethyst::input::{InputHandler, StringBindings};
+
+const SELECT_ACTIONS: [&str; MAX_PARTY_SIZE] = ["select_member_1", "select_member_2", "select_member_3", "select_member_4"];
+// How close a party member has to get to someone to recruit them.
+const RECRUIT_RADIUS: f32 = 40.0;
+
+// Picks which party members orders go to, shows the selection auras,
+// and keeps the roster up to date with each member's current state.
+#[derive(Default)]
+pub struct PartySystem {
+    select_keys_were_down: [bool; MAX_PARTY_SIZE],
+    select_party_key_was_down: bool,
+}
+
+impl<'s> System<'s> for PartySystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, Replay>,
+        Write<'s, PartyRoster>,
+        WriteStorage<'s, Npc>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Attackable>,
+        ReadStorage<'s, Stats>,
+        ReadStorage<'s, Energy>,
+        ReadStorage<'s, Experience>,
+        ReadStorage<'s, Attacker>,
+        ReadStorage<'s, Resistances>,
+        ReadStorage<'s, Equipment>,
+        ReadStorage<'s, SelectAura>,
+        ReadStorage<'s, Parent>,
+        WriteStorage<'s, Hidden>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            input,
+            replay,
+            mut roster,
+            mut npcs,
+            pcs,
+            attackables,
+            stats,
+            energies,
+            experiences,
+            attackers,
+            resistances,
+            equipments,
+            auras,
+            parents,
+            mut hiddens,
+        ): Self::SystemData
+    ) {
+        let mut selection: Option<Option<Entity>> = None;
+        // A replay gives its orders to whoever they were given to.
+        let keys_work = !replay.is_playing();
+        for (slot, action) in SELECT_ACTIONS.iter().enumerate() {
+            let is_down = keys_work && input.action_is_down(action).unwrap_or(false);
+            if is_down && !self.select_keys_were_down[slot] {
+                if let Some(member) = roster.members.get(slot) {
+                    selection = Some(member.entity);
+                }
+            }
+            self.select_keys_were_down[slot] = is_down;
+        }
+
+        let is_down = keys_work && input.action_is_down("select_party").unwrap_or(false);
+        if is_down && !self.select_party_key_was_down {
+            selection = Some(None);
+        }
+        self.select_party_key_was_down = is_down;
+
+        // `Some(None)` selects the whole party.
+        if let Some(selected) = selection {
+            for (entity, npc, _) in (&entities, &mut npcs, &pcs).join() {
+                npc.selected = selected.map_or(true, |s| s == entity);
+            }
+        }
+
+        for (entity, _, parent) in (&entities, &auras, &parents).join() {
+            let selected = pcs.contains(parent.entity) && npcs.get(parent.entity).map_or(false, |n| n.selected);
+            if selected {
+                hiddens.remove(entity);
+            } else if !hiddens.contains(entity) {
+                if let Err(e) = hiddens.insert(entity, Hidden) {
+                    println!("error hiding selection aura : {}", e);
+                }
+            }
+        }
+
+        for member in roster.members.iter_mut() {
+            let entity = match member.entity {
+                Some(entity) if entities.is_alive(entity) => entity,
+                _ => continue,
+            };
+            let attackable = match attackables.get(entity) {
+                Some(attackable) => attackable,
+                None => continue,
+            };
+            let energy = energies.get(entity);
+            member.saved = Some(SavedMember {
+                health: attackable.health,
+                total_health: attackable.total_health,
+                stats: stats.get(entity).cloned().unwrap_or_default(),
+                mana: energy.map_or(0.0, |e| e.mana),
+                stamina: energy.map_or(0.0, |e| e.stamina),
+                attack: attackers.get(entity).map_or(0.0, |a| a.attack),
+                resistances: resistances.get(entity).cloned().unwrap_or_default(),
+                experience: experiences.get(entity).cloned().unwrap_or_default(),
+                equipment: equipments.get(entity).cloned().unwrap_or_default(),
+            });
+        }
+    }
+}
+
+// Sends party members over to anyone recruitable they are ordered to
+// target, and adds the recruit to the party once they get there.
+pub struct RecruitSystem;
+
+impl<'s> System<'s> for RecruitSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, CanTarget>,
+        WriteStorage<'s, Npc>,
+        WriteStorage<'s, PlayerControlled>,
+        WriteStorage<'s, Recruitable>,
+        WriteStorage<'s, Recruiting>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, ArchetypeId>,
+        Write<'s, PartyRoster>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut targeters,
+            mut npcs,
+            mut pcs,
+            mut recruitables,
+            mut recruitings,
+            transforms,
+            archetype_ids,
+            mut roster,
+        ): Self::SystemData
+    ) {
+        // Targeting a recruit means walking over to talk, not attacking.
+        let mut new_recruiters: Vec<(Entity, Recruiting)> = vec![];
+        for (entity, targeter, npc, transform, _) in (&entities, &mut targeters, &mut npcs, &transforms, &pcs).join() {
+            let target = match targeter.target {
+                Some(target) if recruitables.contains(target) => target,
+                _ => continue,
+            };
+            targeter.target = None;
+
+            let target_pos = match transforms.get(target) {
+                Some(t) => [t.translation().x, t.translation().y],
+                None => continue,
+            };
+            npc.velocity = calc_velocity_vec([transform.translation().x, transform.translation().y], target_pos, npc.move_speed);
+            npc.move_coords = target_pos;
+            new_recruiters.push((entity, Recruiting {
+                target: target,
+                move_coords: target_pos,
+            }));
+        }
+
+        for (entity, recruiting) in new_recruiters {
+            if let Err(e) = recruitings.insert(entity, recruiting) {
+                println!("error starting recruitment : {}", e);
+            }
+        }
+
+        let mut done: Vec<Entity> = vec![];
+        let mut recruited: Vec<Entity> = vec![];
+        for (entity, recruiting, npc, transform) in (&entities, &recruitings, &mut npcs, &transforms).join() {
+            // Given another order, or someone else got there first.
+            if npc.move_coords != recruiting.move_coords || !recruitables.contains(recruiting.target) {
+                done.push(entity);
+                continue;
+            }
+
+            let curr = [transform.translation().x, transform.translation().y];
+            if distance(curr, recruiting.move_coords) > RECRUIT_RADIUS {
+                continue;
+            }
+
+            npc.velocity = [0.0, 0.0];
+            done.push(entity);
+            if !recruited.contains(&recruiting.target) {
+                recruited.push(recruiting.target);
+            }
+        }
+
+        for entity in done {
+            recruitings.remove(entity);
+        }
+
+        for recruit in recruited {
+            let archetype = match archetype_ids.get(recruit) {
+                Some(id) => id.0.clone(),
+                None => continue,
+            };
+            if !roster.recruit(&archetype, recruit) {
+                println!("the party is full!");
+                continue;
+            }
+
+            println!("{} joins the party!", archetype);
+            recruitables.remove(recruit);
+            if let Err(e) = pcs.insert(recruit, PlayerControlled) {
+                println!("error adding party member : {}", e);
+            }
+            if let Some(npc) = npcs.get_mut(recruit) {
+                npc.selected = true;
+            }
+        }
+    }
+}
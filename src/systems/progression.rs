@@ -0,0 +1,448 @@
+use crate::components::abilities::{Abilities, HOTBAR_SLOTS};
+use crate::components::npc::{Npc, Attackable, ArchetypeId, PlayerControlled, Downed};
+use crate::components::progression::{Experience, StatGrowth, Talent, TalentEffect, xp_to_next_level};
+use crate::components::save::{Persistent, marker_id};
+use crate::components::stats::Stats;
+use crate::resources::archetypes::ArchetypeRegistry;
+use crate::resources::events::CombatEvent;
+use crate::resources::orders::PartyOrders;
+use crate::resources::replay::{RecordedCommand, RecordedOrder, Replay};
+use crate::resources::simulation::{FixedStep, PlayerInput};
+use crate::systems::ui::{OpenScreen, Screen, UiFont, set_hidden};
+
+use amethyst::{
+    core::Hidden,
+    derive::SystemDesc,
+    ecs::{
+        prelude::{Component, DenseVecStorage, Entity, Entities},
+        Join, Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteStorage,
+    },
+    input::{InputHandler, StringBindings},
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, Interactable, LineMode, UiEvent, UiEventType, UiImage, UiText, UiTransform},
+};
+
+// Rows on the talent screen, and so the most talents a class can have.
+pub const TALENT_ROWS: usize = 8;
+
+const PANEL_WIDTH: f32 = 520.0;
+const ROW_HEIGHT: f32 = 32.0;
+const ROW_GAP: f32 = 4.0;
+const HEADER_HEIGHT: f32 = 48.0;
+
+const PANEL_COLOR: [f32; 4] = [0.05, 0.05, 0.1, 0.9];
+const ROW_COLOR: [f32; 4] = [0.1, 0.1, 0.15, 0.9];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const UNLOCKED_COLOR: [f32; 4] = [0.4, 0.9, 0.4, 1.0];
+const AVAILABLE_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const LOCKED_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+// Hands out experience for kills, split between the party members
+// still standing, and levels them up.
+#[derive(SystemDesc)]
+#[system_desc(name(ExperienceSystemDesc))]
+pub struct ExperienceSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<CombatEvent>,
+}
+
+impl ExperienceSystem {
+    pub fn new(reader_id: ReaderId<CombatEvent>) -> Self {
+        ExperienceSystem { reader_id }
+    }
+}
+
+impl<'s> System<'s> for ExperienceSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<CombatEvent>>,
+        ReadExpect<'s, ArchetypeRegistry>,
+        ReadStorage<'s, ArchetypeId>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Downed>,
+        WriteStorage<'s, Experience>,
+        WriteStorage<'s, Attackable>,
+        WriteStorage<'s, Stats>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            combat_events,
+            registry,
+            archetype_ids,
+            pcs,
+            downeds,
+            mut experiences,
+            mut attackables,
+            mut stats,
+        ): Self::SystemData
+    ) {
+        let mut earned = 0;
+        for event in combat_events.read(&mut self.reader_id) {
+            if let CombatEvent::Death { entity, .. } = event {
+                if pcs.contains(*entity) {
+                    continue;
+                }
+                earned += archetype_ids
+                    .get(*entity)
+                    .and_then(|id| registry.get(&id.0))
+                    .map_or(0, |a| a.experience);
+            }
+        }
+        if earned == 0 {
+            return;
+        }
+
+        let standing: Vec<Entity> = (&entities, &pcs, &experiences, !&downeds)
+            .join()
+            .map(|(e, _, _, _)| e)
+            .collect();
+        if standing.is_empty() {
+            return;
+        }
+        let shares = split_experience(earned, standing.len());
+
+        for (entity, share) in standing.into_iter().zip(shares) {
+            let experience = experiences.get_mut(entity).unwrap();
+            let levels = experience.add_xp(share);
+            if levels == 0 {
+                continue;
+            }
+
+            let growth = archetype_ids
+                .get(entity)
+                .and_then(|id| registry.get(&id.0))
+                .map(|a| a.growth.clone())
+                .unwrap_or_default();
+            for _ in 0..levels {
+                apply_growth(&growth, attackables.get_mut(entity), stats.get_mut(entity));
+            }
+            println!("level up! now level {}", experience.level);
+        }
+    }
+}
+
+// Splits `earned` as evenly as it goes between `members`, handing
+// what's left over out a point at a time so none of it is lost.
+fn split_experience(earned: u32, members: usize) -> Vec<u32> {
+    let members = members as u32;
+    (0..members)
+        .map(|i| earned / members + (i < earned % members) as u32)
+        .collect()
+}
+
+pub fn apply_growth(growth: &StatGrowth, attackable: Option<&mut Attackable>, stats: Option<&mut Stats>) {
+    if let Some(attackable) = attackable {
+        attackable.total_health += growth.health;
+        if attackable.health > 0.0 {
+            attackable.health += growth.health;
+        }
+    }
+    if let Some(stats) = stats {
+        growth.stats.apply(stats, 1.0);
+    }
+}
+
+pub fn apply_talent(
+    talent: &Talent,
+    attackable: Option<&mut Attackable>,
+    stats: Option<&mut Stats>,
+    abilities: Option<&mut Abilities>,
+) {
+    match &talent.effect {
+        TalentEffect::Ability(ability) => {
+            if let Some(abilities) = abilities {
+                if abilities.slots.len() < HOTBAR_SLOTS {
+                    abilities.slots.push(ability.clone());
+                } else {
+                    println!("no room on the hotbar for {}", ability.name);
+                }
+            }
+        },
+        TalentEffect::Passive(modifier) => {
+            if let Some(stats) = stats {
+                modifier.apply(stats, 1.0);
+            }
+        },
+        TalentEffect::MaxHealth(amount) => {
+            if let Some(attackable) = attackable {
+                attackable.total_health += amount;
+                if attackable.health > 0.0 {
+                    attackable.health += amount;
+                }
+            }
+        },
+    }
+}
+
+// Everything about a party member that learning a talent touches.
+pub type TalentStorages<'s> = (
+    ReadStorage<'s, ArchetypeId>,
+    WriteStorage<'s, Experience>,
+    WriteStorage<'s, Attackable>,
+    WriteStorage<'s, Stats>,
+    WriteStorage<'s, Abilities>,
+);
+
+// Learns the talent in `row` of `member`'s class, if they can.
+// Returns false if they couldn't.
+pub fn learn_talent(member: Entity, row: usize, registry: &ArchetypeRegistry, talents: &mut TalentStorages) -> bool {
+    let (archetype_ids, experiences, attackables, stats, abilities) = talents;
+    let talent = match archetype_ids.get(member).and_then(|id| registry.get(&id.0)).and_then(|a| a.talents.get(row)) {
+        Some(talent) => talent,
+        None => return false,
+    };
+    let experience = match experiences.get_mut(member) {
+        Some(experience) if experience.can_unlock(talent) => experience,
+        _ => return false,
+    };
+
+    experience.talent_points -= 1;
+    experience.talents.push(talent.id.clone());
+    if abilities.get(member).is_none() {
+        if let Err(e) = abilities.insert(member, Abilities::default()) {
+            println!("error adding abilities : {}", e);
+        }
+    }
+    apply_talent(talent, attackables.get_mut(member), stats.get_mut(member), abilities.get_mut(member));
+    println!("learned {}", talent.name);
+    true
+}
+
+// Teaches the talents a replay played back.
+pub struct TalentSystem;
+
+impl<'s> System<'s> for TalentSystem {
+    type SystemData = (
+        Write<'s, PartyOrders>,
+        ReadExpect<'s, ArchetypeRegistry>,
+        TalentStorages<'s>,
+    );
+
+    fn run(&mut self, (mut orders, registry, mut talents): Self::SystemData) {
+        for (member, row) in orders.talents.drain(..) {
+            learn_talent(member, row, &registry, &mut talents);
+        }
+    }
+}
+
+// The talent screen's background and header.
+pub struct TalentHeader;
+
+impl Component for TalentHeader {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A button on the talent screen, showing one talent of the
+// selected character's class.
+pub struct TalentRow {
+    pub index: usize,
+}
+
+impl Component for TalentRow {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn initialize_talent_screen(world: &mut World) {
+    let font = world.read_resource::<UiFont>().0.clone();
+
+    let panel_height = HEADER_HEIGHT + TALENT_ROWS as f32 * (ROW_HEIGHT + ROW_GAP) + ROW_GAP;
+    let top = panel_height * 0.5;
+
+    world
+        .create_entity()
+        .with(UiTransform::new(
+            "talent_header".to_string(),
+            Anchor::Middle,
+            Anchor::Middle,
+            0.0,
+            0.0,
+            10.0,
+            PANEL_WIDTH,
+            panel_height,
+        ))
+        .with(UiImage::SolidColor(PANEL_COLOR))
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            TEXT_COLOR,
+            18.0,
+            LineMode::Single,
+            Anchor::TopMiddle,
+        ))
+        .with(TalentHeader)
+        .with(Hidden)
+        .build();
+
+    for index in 0..TALENT_ROWS {
+        let y = top - HEADER_HEIGHT - ROW_HEIGHT * 0.5 - index as f32 * (ROW_HEIGHT + ROW_GAP);
+        world
+            .create_entity()
+            .with(UiTransform::new(
+                format!("talent_row_{}", index),
+                Anchor::Middle,
+                Anchor::Middle,
+                0.0,
+                y,
+                11.0,
+                PANEL_WIDTH - ROW_GAP * 2.0,
+                ROW_HEIGHT,
+            ))
+            .with(UiImage::SolidColor(ROW_COLOR))
+            .with(UiText::new(
+                font.clone(),
+                String::new(),
+                TEXT_COLOR,
+                14.0,
+                LineMode::Single,
+                Anchor::Middle,
+            ))
+            .with(Interactable)
+            .with(TalentRow { index })
+            .with(Hidden)
+            .build();
+    }
+}
+
+// Opens and closes the talent screen, fills it in for the first
+// selected party member and unlocks talents when they are clicked.
+#[derive(SystemDesc)]
+#[system_desc(name(TalentScreenSystemDesc))]
+pub struct TalentScreenSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<UiEvent>,
+    #[system_desc(skip)]
+    key_was_down: bool,
+}
+
+impl TalentScreenSystem {
+    pub fn new(reader_id: ReaderId<UiEvent>) -> Self {
+        TalentScreenSystem {
+            reader_id,
+            key_was_down: false,
+        }
+    }
+}
+
+impl<'s> System<'s> for TalentScreenSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<UiEvent>>,
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, PlayerInput>,
+        Write<'s, OpenScreen>,
+        ReadExpect<'s, ArchetypeRegistry>,
+        ReadStorage<'s, TalentHeader>,
+        ReadStorage<'s, TalentRow>,
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, Hidden>,
+        ReadStorage<'s, Npc>,
+        ReadStorage<'s, PlayerControlled>,
+        TalentStorages<'s>,
+        Write<'s, Replay>,
+        Read<'s, FixedStep>,
+        ReadStorage<'s, Persistent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            ui_events,
+            input,
+            player_input,
+            mut screen,
+            registry,
+            headers,
+            rows,
+            mut texts,
+            mut hiddens,
+            npcs,
+            pcs,
+            mut talents,
+            mut replay,
+            step,
+            markers,
+        ): Self::SystemData
+    ) {
+        // Only while a run is being played, not from its menus.
+        let is_down = *player_input == PlayerInput::Enabled
+            && input.action_is_down("talents").unwrap_or(false);
+        if is_down && !self.key_was_down {
+            screen.toggle(Screen::Talents);
+        }
+        self.key_was_down = is_down;
+        let open = screen.is_open(Screen::Talents);
+
+        let member = (&entities, &npcs, &pcs, &talents.0, &talents.1)
+            .join()
+            .find(|(_, npc, _, _, _)| npc.selected)
+            .map(|(e, _, _, id, _)| (e, id.0.clone()));
+        let archetype = member.as_ref().and_then(|(_, id)| registry.get(id));
+
+        for event in ui_events.read(&mut self.reader_id) {
+            // A replay learns its own talents.
+            if !open || event.event_type != UiEventType::Click || replay.is_playing() {
+                continue;
+            }
+            let (row, entity) = match (rows.get(event.target), member.as_ref()) {
+                (Some(row), Some((entity, _))) => (row.index, *entity),
+                _ => continue,
+            };
+            if learn_talent(entity, row, &registry, &mut talents) {
+                replay.record(RecordedCommand {
+                    tick: step.tick,
+                    units: vec![marker_id(&markers, entity)],
+                    order: RecordedOrder::LearnTalent(row),
+                });
+            }
+        }
+
+        let experience = member.as_ref().and_then(|(e, _)| talents.1.get(*e));
+
+        for (entity, _) in (&entities, &headers).join() {
+            set_hidden(&mut hiddens, entity, !open);
+            if let (Some(text), Some(experience), Some(archetype)) = (texts.get_mut(entity), experience, archetype) {
+                text.text = format!(
+                    "{} - level {} ({}/{} xp) - {} talent points",
+                    archetype.name,
+                    experience.level,
+                    experience.xp,
+                    xp_to_next_level(experience.level),
+                    experience.talent_points,
+                );
+            }
+        }
+
+        for (entity, row) in (&entities, &rows).join() {
+            let talent = archetype.and_then(|a| a.talents.get(row.index));
+            set_hidden(&mut hiddens, entity, !open || talent.is_none());
+
+            if let (Some(text), Some(talent), Some(experience)) = (texts.get_mut(entity), talent, experience) {
+                let (state, color) = if experience.has_talent(&talent.id) {
+                    ("learned", UNLOCKED_COLOR)
+                } else if experience.can_unlock(talent) {
+                    ("available", AVAILABLE_COLOR)
+                } else {
+                    ("locked", LOCKED_COLOR)
+                };
+                text.text = format!("{} (level {}, {}) - {}", talent.name, talent.level, state, talent.description);
+                text.color = color;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn experience_is_split_without_losing_any() {
+        assert_eq!(split_experience(2, 4), vec![1, 1, 0, 0]);
+        assert_eq!(split_experience(20, 3), vec![7, 7, 6]);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::components::npc::{Attackable, Enemy, PlayerControlled, Downed};
+use crate::components::interpolation::Interpolation;
+use crate::components::projectile::{Projectile, ProjectileSheet, PROJECTILE_HIT_RADIUS};
+use crate::components::stats::{Stats, Resistances, resolve_damage};
+use crate::components::status::{StatusEffects, effective_stats};
+use crate::resources::events::CombatEvent;
+use crate::resources::dungeon::Dungeon;
+use crate::resources::map::MapGrid;
+use crate::systems::combat::apply_outcome;
+use crate::systems::commands::distance;
+use crate::resources::simulation::{FixedStep, TICK_SECONDS};
+use crate::components::save::{Persistent, marker_id};
+
+use amethyst::core::{
+    Transform,
+};
+use amethyst::ecs::{
+    prelude::{Entity, Entities, LazyUpdate},
+    Join, Read, ReadExpect, ReadStorage, System, Write, WriteStorage,
+};
+use amethyst::renderer::SpriteRender;
+use amethyst::shrev::EventChannel;
+
+// Projectiles fly above everything layered on the floor.
+const PROJECTILE_Z: f32 = 50.0;
+
+// Spawns a projectile at `from`. Meant to be called from inside a
+// system, the entity is only created once the world is maintained.
+pub fn fire_projectile(
+    lazy: &LazyUpdate,
+    entities: &Entities,
+    sheet: &ProjectileSheet,
+    projectile: Projectile,
+    from: [f32; 2],
+) {
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(from[0], from[1], PROJECTILE_Z);
+    transform.set_rotation_2d(projectile.angle());
+
+    let render = SpriteRender {
+        sprite_sheet: sheet.0.clone(),
+        sprite_number: projectile.sprite_number(),
+    };
+
+    lazy.create_entity(entities)
+        .with(render)
+        .with(transform)
+        .with(Interpolation::at(from))
+        .with(projectile)
+        .build();
+}
+
+// Moves projectiles along, removing them when they run into a wall or
+// out of range, and resolves their damage against the first hostile
+// unit they come close enough to.
+pub struct ProjectileSystem;
+
+impl<'s> System<'s> for ProjectileSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, Projectile>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, Attackable>,
+        ReadStorage<'s, Stats>,
+        ReadStorage<'s, Resistances>,
+        ReadStorage<'s, Enemy>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Downed>,
+        ReadExpect<'s, MapGrid>,
+        Write<'s, EventChannel<CombatEvent>>,
+        WriteStorage<'s, StatusEffects>,
+        Read<'s, Dungeon>,
+        Read<'s, FixedStep>,
+        ReadStorage<'s, Persistent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut projectiles,
+            mut transforms,
+            mut attackables,
+            stats,
+            resistances,
+            enemies,
+            pcs,
+            downeds,
+            grid,
+            mut combat_events,
+            mut statuses,
+            dungeon,
+            step,
+            markers,
+        ): Self::SystemData
+    ) {
+        let delta = TICK_SECONDS;
+        let mut in_flight: Vec<(Entity, [f32; 2])> = vec![];
+
+        for (entity, projectile, transform) in (&entities, &mut projectiles, &mut transforms).join() {
+            projectile.time_to_live -= delta;
+            transform.prepend_translation_x(projectile.velocity[0] * delta);
+            transform.prepend_translation_y(projectile.velocity[1] * delta);
+            let position = [transform.translation().x, transform.translation().y];
+
+            if projectile.time_to_live <= 0.0 || grid.is_blocking_at(position) {
+                if let Err(e) = entities.delete(entity) {
+                    println!("error deleting entity : {}", e);
+                }
+                continue;
+            }
+
+            in_flight.push((entity, position));
+        }
+
+        let default_resistances = Resistances::default();
+
+        for (entity, position) in in_flight {
+            let projectile = projectiles.get(entity).unwrap();
+
+            let hit = (&entities, &attackables, &transforms, !&downeds)
+                .join()
+                .filter(|(target, _, _, _)| match projectile.fired_by_enemy {
+                    true => pcs.contains(*target),
+                    false => enemies.contains(*target),
+                })
+                .filter(|(_, attackable, _, _)| attackable.health > 0.0)
+                .find(|(_, _, transform, _)| {
+                    distance([transform.translation().x, transform.translation().y], position) <= PROJECTILE_HIT_RADIUS
+                })
+                .map(|(target, _, _, _)| target);
+
+            if let Some(target) = hit {
+                let mut rng = dungeon.roll_rng(step.tick, marker_id(&markers, projectile.source), marker_id(&markers, target));
+                let outcome = resolve_damage(
+                    &mut rng,
+                    projectile.damage,
+                    &projectile.source_stats,
+                    &effective_stats(stats.get(target), statuses.get(target)),
+                    resistances.get(target).unwrap_or(&default_resistances),
+                );
+                let attackable = attackables.get_mut(target).unwrap();
+                apply_outcome(
+                    &mut combat_events,
+                    projectile.source,
+                    target,
+                    projectile.damage.damage_type,
+                    outcome,
+                    attackable,
+                    statuses.get_mut(target),
+                );
+
+                if let Err(e) = entities.delete(entity) {
+                    println!("error deleting entity : {}", e);
+                }
+            }
+        }
+    }
+}
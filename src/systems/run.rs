@@ -0,0 +1,128 @@
+use crate::components::npc::{Boss, Dead, Downed, PlayerControlled};
+use crate::components::scenary::Stairs;
+use crate::resources::dungeon::Dungeon;
+use crate::resources::events::CombatEvent;
+use crate::resources::run::{Run, RunConfig, RunOutcome};
+use crate::resources::simulation::TICK_SECONDS;
+use crate::systems::commands::distance;
+
+use amethyst::{
+    core::Transform,
+    derive::SystemDesc,
+    ecs::{Join, Read, ReadExpect, ReadStorage, System, SystemData, Write},
+    shrev::{EventChannel, ReaderId},
+};
+
+// How close a party member has to walk to the stairs to take them.
+const STAIRS_RADIUS: f32 = 20.0;
+
+// Keeps the run's statistics and decides when it is over: the party
+// wins by killing the boss of the last floor and loses when everyone
+// in it is down at once. Walking onto the stairs once the floor's
+// boss is dead takes the party down a floor.
+#[derive(SystemDesc)]
+#[system_desc(name(RunSystemDesc))]
+pub struct RunSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<CombatEvent>,
+    // So the party is only told once that the stairs are blocked.
+    #[system_desc(skip)]
+    told_blocked: bool,
+}
+
+impl RunSystem {
+    pub fn new(reader_id: ReaderId<CombatEvent>) -> Self {
+        RunSystem {
+            reader_id,
+            told_blocked: false,
+        }
+    }
+}
+
+impl<'s> System<'s> for RunSystem {
+    type SystemData = (
+        Read<'s, EventChannel<CombatEvent>>,
+        Write<'s, Run>,
+        ReadExpect<'s, RunConfig>,
+        Read<'s, Dungeon>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Downed>,
+        ReadStorage<'s, Dead>,
+        ReadStorage<'s, Boss>,
+        ReadStorage<'s, Stairs>,
+        ReadStorage<'s, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            combat_events,
+            mut run,
+            config,
+            dungeon,
+            pcs,
+            downeds,
+            deads,
+            bosses,
+            stairs,
+            transforms,
+        ): Self::SystemData
+    ) {
+        let over = run.outcome.is_some();
+
+        for event in combat_events.read(&mut self.reader_id) {
+            if over {
+                continue;
+            }
+            match event {
+                CombatEvent::Damage { source, target, amount, .. } => {
+                    if source.map_or(false, |s| pcs.contains(s)) {
+                        run.stats.damage_dealt += amount;
+                    }
+                    if pcs.contains(*target) {
+                        run.stats.damage_taken += amount;
+                    }
+                },
+                CombatEvent::Death { entity, .. } if !pcs.contains(*entity) => {
+                    run.stats.kills += 1;
+                    if bosses.contains(*entity) && dungeon.depth >= config.floors {
+                        run.finish(RunOutcome::Victory);
+                    }
+                },
+                _ => {},
+            }
+        }
+        if over {
+            return;
+        }
+
+        run.stats.time += TICK_SECONDS;
+
+        let party = (&pcs).join().count();
+        let standing: Vec<[f32; 2]> = (&pcs, &transforms, !&downeds)
+            .join()
+            .map(|(_, t, _)| [t.translation().x, t.translation().y])
+            .collect();
+        if party > 0 && standing.is_empty() {
+            run.finish(RunOutcome::Defeat);
+            return;
+        }
+
+        let on_stairs = (&stairs, &transforms)
+            .join()
+            .map(|(_, t)| [t.translation().x, t.translation().y])
+            .any(|pos| standing.iter().any(|p| distance(*p, pos) <= STAIRS_RADIUS));
+        let boss_alive = (&bosses, !&deads).join().next().is_some();
+
+        if on_stairs && boss_alive {
+            if !self.told_blocked {
+                println!("the way down is barred while the chief still stands.");
+            }
+            self.told_blocked = true;
+        } else if on_stairs {
+            run.descend = true;
+        } else {
+            self.told_blocked = false;
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use crate::resources::save::{describe_slot, SAVE_SLOTS};
+use crate::resources::simulation::PlayerInput;
+use crate::systems::ui::{OpenScreen, Screen, UiFont, set_hidden};
+
+use amethyst::{
+    core::Hidden,
+    derive::SystemDesc,
+    ecs::{
+        prelude::{Component, DenseVecStorage, Entities},
+        Join, Read, ReadStorage, System, SystemData, Write, WriteStorage,
+    },
+    input::{InputHandler, StringBindings},
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, Interactable, LineMode, UiEvent, UiEventType, UiImage, UiText, UiTransform},
+};
+
+const PANEL_WIDTH: f32 = 420.0;
+const ROW_WIDTH: f32 = 380.0;
+const ROW_HEIGHT: f32 = 32.0;
+const ROW_GAP: f32 = 6.0;
+const HEADER_HEIGHT: f32 = 48.0;
+
+const PANEL_COLOR: [f32; 4] = [0.05, 0.05, 0.1, 0.9];
+const ROW_COLOR: [f32; 4] = [0.1, 0.1, 0.15, 0.9];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const EMPTY_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+// A save or load the player picked from the menu. Both need the whole
+// world, so they are carried out by the game state rather than a system.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SaveRequest {
+    Save(usize),
+    Load(usize),
+}
+
+#[derive(Default)]
+pub struct SaveMenu {
+    // Whether picking a slot saves to it or loads from it.
+    pub saving: bool,
+    // What is in each slot, read when the menu is opened.
+    pub slots: Vec<Option<String>>,
+    pub request: Option<SaveRequest>,
+}
+
+impl SaveMenu {
+    pub fn refresh(&mut self) {
+        self.slots = (0..SAVE_SLOTS).map(describe_slot).collect();
+    }
+}
+
+// The save menu's background and header.
+pub struct SaveMenuHeader;
+
+impl Component for SaveMenuHeader {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A button for one save slot.
+pub struct SaveSlotRow {
+    pub slot: usize,
+}
+
+impl Component for SaveSlotRow {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn initialize_save_menu(world: &mut World) {
+    let font = world.read_resource::<UiFont>().0.clone();
+
+    let panel_height = HEADER_HEIGHT + SAVE_SLOTS as f32 * (ROW_HEIGHT + ROW_GAP) + ROW_GAP;
+    let top = panel_height * 0.5;
+
+    world
+        .create_entity()
+        .with(UiTransform::new(
+            "save_menu_header".to_string(),
+            Anchor::Middle,
+            Anchor::Middle,
+            0.0,
+            0.0,
+            10.0,
+            PANEL_WIDTH,
+            panel_height,
+        ))
+        .with(UiImage::SolidColor(PANEL_COLOR))
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            TEXT_COLOR,
+            18.0,
+            LineMode::Single,
+            Anchor::TopMiddle,
+        ))
+        .with(SaveMenuHeader)
+        .with(Hidden)
+        .build();
+
+    for slot in 0..SAVE_SLOTS {
+        world
+            .create_entity()
+            .with(UiTransform::new(
+                format!("save_slot_{}", slot),
+                Anchor::Middle,
+                Anchor::Middle,
+                0.0,
+                top - HEADER_HEIGHT - ROW_HEIGHT * 0.5 - slot as f32 * (ROW_HEIGHT + ROW_GAP),
+                11.0,
+                ROW_WIDTH,
+                ROW_HEIGHT,
+            ))
+            .with(UiImage::SolidColor(ROW_COLOR))
+            .with(UiText::new(
+                font.clone(),
+                String::new(),
+                TEXT_COLOR,
+                14.0,
+                LineMode::Single,
+                Anchor::Middle,
+            ))
+            .with(Interactable)
+            .with(SaveSlotRow { slot })
+            .with(Hidden)
+            .build();
+    }
+}
+
+// Opens the save menu to save or to load, and turns a click on
+// a slot into a `SaveRequest`.
+#[derive(SystemDesc)]
+#[system_desc(name(SaveMenuSystemDesc))]
+pub struct SaveMenuSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<UiEvent>,
+    #[system_desc(skip)]
+    save_key_was_down: bool,
+    #[system_desc(skip)]
+    load_key_was_down: bool,
+}
+
+impl SaveMenuSystem {
+    pub fn new(reader_id: ReaderId<UiEvent>) -> Self {
+        SaveMenuSystem {
+            reader_id,
+            save_key_was_down: false,
+            load_key_was_down: false,
+        }
+    }
+}
+
+impl<'s> System<'s> for SaveMenuSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<UiEvent>>,
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, PlayerInput>,
+        Write<'s, OpenScreen>,
+        Write<'s, SaveMenu>,
+        ReadStorage<'s, SaveMenuHeader>,
+        ReadStorage<'s, SaveSlotRow>,
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, Hidden>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            ui_events,
+            input,
+            player_input,
+            mut screen,
+            mut menu,
+            headers,
+            rows,
+            mut texts,
+            mut hiddens,
+        ): Self::SystemData
+    ) {
+        // The keys only work while a run is being played, the menus
+        // open the save menu themselves when there is something to save.
+        let playing = *player_input == PlayerInput::Enabled;
+        let mut pressed = None;
+        let is_down = playing && input.action_is_down("save_menu").unwrap_or(false);
+        if is_down && !self.save_key_was_down {
+            pressed = Some(true);
+        }
+        self.save_key_was_down = is_down;
+        let is_down = playing && input.action_is_down("load_menu").unwrap_or(false);
+        if is_down && !self.load_key_was_down {
+            pressed = Some(false);
+        }
+        self.load_key_was_down = is_down;
+
+        if let Some(saving) = pressed {
+            if screen.is_open(Screen::SaveSlots) && menu.saving == saving {
+                screen.screen = None;
+            } else {
+                menu.saving = saving;
+                menu.refresh();
+                screen.screen = Some(Screen::SaveSlots);
+            }
+        }
+        let open = screen.is_open(Screen::SaveSlots);
+
+        for event in ui_events.read(&mut self.reader_id) {
+            if !open || event.event_type != UiEventType::Click {
+                continue;
+            }
+            let slot = match rows.get(event.target) {
+                Some(row) => row.slot,
+                None => continue,
+            };
+
+            if menu.saving {
+                menu.request = Some(SaveRequest::Save(slot));
+            } else if menu.slots.get(slot).map_or(false, |s| s.is_some()) {
+                menu.request = Some(SaveRequest::Load(slot));
+            } else {
+                println!("there is nothing saved in slot {}.", slot + 1);
+                continue;
+            }
+            screen.screen = None;
+        }
+        let open = screen.is_open(Screen::SaveSlots);
+
+        for (entity, _) in (&entities, &headers).join() {
+            set_hidden(&mut hiddens, entity, !open);
+            if let Some(text) = texts.get_mut(entity) {
+                text.text = if menu.saving { "save game" } else { "load game" }.to_string();
+            }
+        }
+
+        for (entity, row) in (&entities, &rows).join() {
+            set_hidden(&mut hiddens, entity, !open);
+            if let Some(text) = texts.get_mut(entity) {
+                match menu.slots.get(row.slot).cloned().flatten() {
+                    Some(description) => {
+                        text.text = format!("slot {} - {}", row.slot + 1, description);
+                        text.color = TEXT_COLOR;
+                    },
+                    None => {
+                        text.text = format!("slot {} - empty", row.slot + 1);
+                        text.color = EMPTY_COLOR;
+                    },
+                }
+            }
+        }
+    }
+}
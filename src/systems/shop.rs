@@ -0,0 +1,367 @@
+use crate::components::npc::{Npc, PlayerControlled, Downed};
+use crate::components::scenary::Merchant;
+use crate::resources::affixes::ItemGenerator;
+use crate::resources::dungeon::Dungeon;
+use crate::resources::items::{Inventory, ItemDatabase, INVENTORY_CAPACITY};
+use crate::resources::orders::{PartyOrders, Trade};
+use crate::resources::replay::{RecordedCommand, RecordedOrder, Replay};
+use crate::resources::shop::{Shop, SHOP_CAPACITY};
+use crate::resources::simulation::FixedStep;
+use crate::systems::ui::{OpenScreen, Screen, UiFont, set_hidden};
+use crate::systems::commands::distance;
+
+use amethyst::{
+    core::{Hidden, Transform},
+    derive::SystemDesc,
+    ecs::{
+        prelude::{Component, DenseVecStorage, Entities},
+        Join, Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteStorage,
+    },
+    input::{InputHandler, StringBindings},
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, Interactable, LineMode, UiEvent, UiEventType, UiImage, UiText, UiTransform},
+};
+
+// How close a selected party member has to be to the merchant to trade.
+const TRADE_RADIUS: f32 = 50.0;
+
+const PANEL_WIDTH: f32 = 720.0;
+const COLUMN_WIDTH: f32 = 340.0;
+const ROW_HEIGHT: f32 = 28.0;
+const ROW_GAP: f32 = 4.0;
+const HEADER_HEIGHT: f32 = 48.0;
+
+const PANEL_COLOR: [f32; 4] = [0.05, 0.05, 0.1, 0.9];
+const ROW_COLOR: [f32; 4] = [0.1, 0.1, 0.15, 0.9];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// The shop screen's background and header.
+pub struct ShopHeader;
+
+impl Component for ShopHeader {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A button showing one item the merchant has for sale.
+pub struct StockRow {
+    pub index: usize,
+}
+
+impl Component for StockRow {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// A button showing one item the party could sell.
+pub struct SellRow {
+    pub index: usize,
+}
+
+impl Component for SellRow {
+    type Storage = DenseVecStorage<Self>;
+}
+
+// Stocks the shop for this floor if it hasn't been yet.
+fn stock_shop(shop: &mut Shop, dungeon: &Dungeon, item_database: &ItemDatabase, generator: &ItemGenerator) {
+    if shop.needs_restock(dungeon.depth) {
+        shop.restock(&mut dungeon.shop_rng(), dungeon.depth, item_database, generator);
+    }
+}
+
+// Buys or sells an item. A played back trade may be the first
+// time the shop is needed on a floor, so it's stocked first.
+pub fn trade(
+    trade: Trade,
+    shop: &mut Shop,
+    inventory: &mut Inventory,
+    dungeon: &Dungeon,
+    item_database: &ItemDatabase,
+    generator: &ItemGenerator,
+) -> bool {
+    stock_shop(shop, dungeon, item_database, generator);
+    match trade {
+        Trade::Buy(index) => match shop.buy(index, inventory) {
+            Ok(item) => {
+                println!("bought {} for {} gold.", item.display_name(), item.price());
+                true
+            },
+            Err(e) => {
+                println!("can't buy that : {}", e);
+                false
+            },
+        },
+        Trade::Sell(index) => {
+            let name = inventory.items.get(index).map(|i| i.display_name());
+            match shop.sell(index, inventory) {
+                Ok(price) => {
+                    println!("sold {} for {} gold.", name.unwrap_or_default(), price);
+                    true
+                },
+                Err(e) => {
+                    println!("can't sell that : {}", e);
+                    false
+                },
+            }
+        },
+    }
+}
+
+// Makes the trades a replay played back.
+pub struct TradeSystem;
+
+impl<'s> System<'s> for TradeSystem {
+    type SystemData = (
+        Write<'s, PartyOrders>,
+        Write<'s, Shop>,
+        Write<'s, Inventory>,
+        Read<'s, Dungeon>,
+        ReadExpect<'s, ItemDatabase>,
+        ReadExpect<'s, ItemGenerator>,
+    );
+
+    fn run(&mut self, (mut orders, mut shop, mut inventory, dungeon, item_database, generator): Self::SystemData) {
+        for order in orders.trades.drain(..) {
+            trade(order, &mut shop, &mut inventory, &dungeon, &item_database, &generator);
+        }
+    }
+}
+
+pub fn initialize_shop_screen(world: &mut World) {
+    let font = world.read_resource::<UiFont>().0.clone();
+
+    let rows = SHOP_CAPACITY.max(INVENTORY_CAPACITY);
+    let panel_height = HEADER_HEIGHT + rows as f32 * (ROW_HEIGHT + ROW_GAP) + ROW_GAP;
+    let top = panel_height * 0.5;
+    let row_y = |index: usize| top - HEADER_HEIGHT - ROW_HEIGHT * 0.5 - index as f32 * (ROW_HEIGHT + ROW_GAP);
+    let column_x = PANEL_WIDTH * 0.25;
+
+    world
+        .create_entity()
+        .with(UiTransform::new(
+            "shop_header".to_string(),
+            Anchor::Middle,
+            Anchor::Middle,
+            0.0,
+            0.0,
+            10.0,
+            PANEL_WIDTH,
+            panel_height,
+        ))
+        .with(UiImage::SolidColor(PANEL_COLOR))
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            TEXT_COLOR,
+            18.0,
+            LineMode::Single,
+            Anchor::TopMiddle,
+        ))
+        .with(ShopHeader)
+        .with(Hidden)
+        .build();
+
+    for index in 0..SHOP_CAPACITY {
+        world
+            .create_entity()
+            .with(UiTransform::new(
+                format!("stock_row_{}", index),
+                Anchor::Middle,
+                Anchor::Middle,
+                -column_x,
+                row_y(index),
+                11.0,
+                COLUMN_WIDTH,
+                ROW_HEIGHT,
+            ))
+            .with(UiImage::SolidColor(ROW_COLOR))
+            .with(UiText::new(
+                font.clone(),
+                String::new(),
+                TEXT_COLOR,
+                14.0,
+                LineMode::Single,
+                Anchor::Middle,
+            ))
+            .with(Interactable)
+            .with(StockRow { index })
+            .with(Hidden)
+            .build();
+    }
+
+    for index in 0..INVENTORY_CAPACITY {
+        world
+            .create_entity()
+            .with(UiTransform::new(
+                format!("sell_row_{}", index),
+                Anchor::Middle,
+                Anchor::Middle,
+                column_x,
+                row_y(index),
+                11.0,
+                COLUMN_WIDTH,
+                ROW_HEIGHT,
+            ))
+            .with(UiImage::SolidColor(ROW_COLOR))
+            .with(UiText::new(
+                font.clone(),
+                String::new(),
+                TEXT_COLOR,
+                14.0,
+                LineMode::Single,
+                Anchor::Middle,
+            ))
+            .with(Interactable)
+            .with(SellRow { index })
+            .with(Hidden)
+            .build();
+    }
+}
+
+// Opens the shop when the party is next to the merchant. Clicking
+// the merchant's stock buys it, clicking the party's items sells them.
+// The shop closes by itself if the party walks away.
+#[derive(SystemDesc)]
+#[system_desc(name(ShopScreenSystemDesc))]
+pub struct ShopScreenSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<UiEvent>,
+    #[system_desc(skip)]
+    key_was_down: bool,
+}
+
+impl ShopScreenSystem {
+    pub fn new(reader_id: ReaderId<UiEvent>) -> Self {
+        ShopScreenSystem {
+            reader_id,
+            key_was_down: false,
+        }
+    }
+}
+
+impl<'s> System<'s> for ShopScreenSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<UiEvent>>,
+        Read<'s, InputHandler<StringBindings>>,
+        Write<'s, OpenScreen>,
+        Write<'s, Inventory>,
+        Write<'s, Shop>,
+        Read<'s, Dungeon>,
+        ReadExpect<'s, ItemDatabase>,
+        ReadExpect<'s, ItemGenerator>,
+        ReadStorage<'s, ShopHeader>,
+        ReadStorage<'s, StockRow>,
+        ReadStorage<'s, SellRow>,
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, Hidden>,
+        ReadStorage<'s, Merchant>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, Npc>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Downed>,
+        Write<'s, Replay>,
+        Read<'s, FixedStep>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            ui_events,
+            input,
+            mut screen,
+            mut inventory,
+            mut shop,
+            dungeon,
+            item_database,
+            generator,
+            headers,
+            stock_rows,
+            sell_rows,
+            mut texts,
+            mut hiddens,
+            merchants,
+            transforms,
+            npcs,
+            pcs,
+            downeds,
+            mut replay,
+            step,
+        ): Self::SystemData
+    ) {
+        let traders: Vec<[f32; 2]> = (&npcs, &pcs, &transforms, !&downeds)
+            .join()
+            .filter(|(npc, _, _, _)| npc.selected)
+            .map(|(_, _, t, _)| [t.translation().x, t.translation().y])
+            .collect();
+        let near_merchant = (&merchants, &transforms)
+            .join()
+            .map(|(_, t)| [t.translation().x, t.translation().y])
+            .any(|pos| traders.iter().any(|t| distance(*t, pos) <= TRADE_RADIUS));
+
+        let is_down = input.action_is_down("trade").unwrap_or(false);
+        if is_down && !self.key_was_down {
+            if screen.is_open(Screen::Shop) {
+                screen.screen = None;
+            } else if near_merchant {
+                stock_shop(&mut shop, &dungeon, &item_database, &generator);
+                screen.screen = Some(Screen::Shop);
+            } else {
+                println!("there is no one to trade with here.");
+            }
+        }
+        self.key_was_down = is_down;
+
+        if screen.is_open(Screen::Shop) && !near_merchant {
+            screen.screen = None;
+        }
+        let open = screen.is_open(Screen::Shop);
+
+        for event in ui_events.read(&mut self.reader_id) {
+            // A replay makes its own trades.
+            if !open || event.event_type != UiEventType::Click || replay.is_playing() {
+                continue;
+            }
+
+            let order = if let Some(row) = stock_rows.get(event.target) {
+                Trade::Buy(row.index)
+            } else if let Some(row) = sell_rows.get(event.target) {
+                Trade::Sell(row.index)
+            } else {
+                continue;
+            };
+            if trade(order, &mut shop, &mut inventory, &dungeon, &item_database, &generator) {
+                replay.record(RecordedCommand {
+                    tick: step.tick,
+                    units: vec![],
+                    order: RecordedOrder::Trade(order),
+                });
+            }
+        }
+
+        for (entity, _) in (&entities, &headers).join() {
+            set_hidden(&mut hiddens, entity, !open);
+            if let Some(text) = texts.get_mut(entity) {
+                text.text = format!("buy    -    sell    -    {} gold", inventory.gold);
+            }
+        }
+
+        for (entity, row) in (&entities, &stock_rows).join() {
+            let item = shop.stock.get(row.index);
+            set_hidden(&mut hiddens, entity, !open || item.is_none());
+            if let (Some(text), Some(item)) = (texts.get_mut(entity), item) {
+                text.text = format!("{} - {}g", item.display_name(), item.price());
+                text.color = item.rarity.color();
+            }
+        }
+
+        for (entity, row) in (&entities, &sell_rows).join() {
+            let item = inventory.items.get(row.index);
+            set_hidden(&mut hiddens, entity, !open || item.is_none());
+            if let (Some(text), Some(item)) = (texts.get_mut(entity), item) {
+                text.text = format!("{} - {}g", item.display_name(), shop.sell_price(item));
+                text.color = item.rarity.color();
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::resources::run::Run;
+use crate::resources::simulation::{FixedStep, PlayerInput, Simulation};
+use crate::systems::{
+    abilities::AbilitySystem,
+    animation::IdleAnimationSystem,
+    combat::CombatSystem,
+    combat_log::CombatLogSystemDesc,
+    commands::CommandSystem,
+    death::DeathSystemDesc,
+    enemy_targeting::EnemyTargetingSystem,
+    exploration::ExplorationSystem,
+    healing::{PotionSystem, RecoverySystemDesc, RestSystem},
+    interpolation::{TickEndSystem, TickStartSystem},
+    inventory::GearSystem,
+    loot::{LootDropSystemDesc, PickupSystem},
+    movement::MovementSystem,
+    party::{PartySystem, RecruitSystem},
+    progression::{ExperienceSystemDesc, TalentSystem},
+    projectile::ProjectileSystem,
+    run::RunSystemDesc,
+    shop::TradeSystem,
+    status::StatusSystem,
+    turns::TurnSystemDesc,
+};
+
+use amethyst::{
+    core::{ArcThreadPool, SystemDesc, SystemExt},
+    ecs::{Dispatcher, DispatcherBuilder, World},
+};
+
+// The gameplay systems, dispatched by `GameplayState` once per tick
+// rather than once per frame. For a seed and the orders given to
+// always play out the same way, systems in here:
+// - advance by `TICK_SECONDS`, never by `Time`,
+// - roll on `Dungeon::roll_rng`, keyed by who is rolling, so rolls
+//   don't depend on which order systems or units are run in,
+// - only move things that have an `Interpolation` through their
+//   transform, between the tick start and end systems.
+pub fn build_simulation(world: &mut World) -> Dispatcher<'static, 'static> {
+    let pool = world.read_resource::<ArcThreadPool>().clone();
+
+    let mut dispatcher = DispatcherBuilder::new()
+        .with_pool(pool)
+        .with(TickStartSystem, "tick_start_system", &[])
+        .with_barrier()
+        // Selection and orders work whenever the dungeon is being played,
+        // even while it is paused.
+        .with(PartySystem::default().pausable(PlayerInput::Enabled), "party_system", &[])
+        .with(CommandSystem::default().pausable(PlayerInput::Enabled), "command_system", &["party_system"])
+        // What was done on the screens when a replay was recorded, done
+        // before anything else on the tick since it was done between ticks.
+        .with(TradeSystem.pausable(PlayerInput::Enabled), "trade_system", &["command_system"])
+        .with(GearSystem.pausable(PlayerInput::Enabled), "gear_system", &["command_system"])
+        .with(TalentSystem.pausable(PlayerInput::Enabled), "talent_system", &["command_system"])
+        .with_barrier()
+        // Everything else stands still while paused or a menu is up.
+        .with(RecruitSystem.pausable(Simulation::Running), "recruit_system", &["command_system"])
+        .with(TurnSystemDesc::default().build(world).pausable(Simulation::Running), "turn_system", &["command_system", "recruit_system"])
+        .with(AbilitySystem.pausable(Simulation::Running), "ability_system", &["command_system", "turn_system"])
+        .with(MovementSystem.pausable(Simulation::Running), "movement_system", &["command_system", "recruit_system", "ability_system"])
+        .with(ExplorationSystem.pausable(Simulation::Running), "exploration_system", &["movement_system"])
+        .with(EnemyTargetingSystem.pausable(Simulation::Running), "enemy_targeting_system", &["movement_system"])
+        .with(CombatSystem.pausable(Simulation::Running), "combat_system", &["movement_system", "enemy_targeting_system"])
+        .with(ProjectileSystem.pausable(Simulation::Running), "projectile_system", &["combat_system"])
+        .with(StatusSystem.pausable(Simulation::Running), "status_system", &["ability_system", "projectile_system"])
+        .with(RestSystem.pausable(Simulation::Running), "rest_system", &["status_system"])
+        .with(PotionSystem.pausable(Simulation::Running), "potion_system", &["status_system"])
+        .with(RecoverySystemDesc::default().build(world).pausable(Simulation::Running), "recovery_system", &["rest_system", "potion_system"])
+        .with(CombatLogSystemDesc::default().build(world).pausable(Simulation::Running), "combat_log_system", &["combat_system", "projectile_system", "ability_system", "status_system", "recovery_system"])
+        .with(DeathSystemDesc::default().build(world).pausable(Simulation::Running), "death_system", &["combat_system", "projectile_system", "ability_system", "status_system"])
+        .with(ExperienceSystemDesc::default().build(world).pausable(Simulation::Running), "experience_system", &["combat_system", "projectile_system", "ability_system", "status_system"])
+        .with(LootDropSystemDesc::default().build(world).pausable(Simulation::Running), "loot_drop_system", &["combat_system", "projectile_system", "ability_system", "status_system"])
+        .with(PickupSystem::default().pausable(Simulation::Running), "pickup_system", &["movement_system", "loot_drop_system"])
+        .with(RunSystemDesc::default().build(world).pausable(Simulation::Running), "run_system", &["death_system", "pickup_system"])
+        .with(IdleAnimationSystem::default().pausable(Simulation::Running), "anim_system", &["movement_system", "combat_system", "enemy_targeting_system"])
+        .with_barrier()
+        .with(TickEndSystem, "tick_end_system", &[])
+        .build();
+    dispatcher.setup(world);
+    dispatcher
+}
+
+// Runs the simulation for one tick. Entities created or deleted during
+// a tick exist or are gone by the next one, at any frame rate.
+pub fn run_tick(simulation: &mut Dispatcher<'static, 'static>, world: &mut World) {
+    simulation.dispatch(world);
+    world.maintain();
+    // Only ticks where anything happened count, so replays line
+    // up no matter how long the game was paused for.
+    if *world.read_resource::<Simulation>() == Simulation::Running {
+        world.write_resource::<FixedStep>().tick += 1;
+    }
+}
+
+// Runs up to `ticks` ticks, stopping at the one where the party took
+// the stairs or the run ended. Those need the whole world so they are
+// handled between ticks, and stopping here makes them happen on the
+// same tick however many ticks a frame had to run.
+pub fn run_ticks(simulation: &mut Dispatcher<'static, 'static>, world: &mut World, ticks: u32) {
+    for _ in 0..ticks {
+        run_tick(simulation, world);
+        let run = world.read_resource::<Run>();
+        if run.descend || run.outcome.is_some() {
+            break;
+        }
+    }
+}
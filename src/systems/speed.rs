@@ -0,0 +1,122 @@
+use crate::resources::simulation::{GameSpeed, PlayerInput, Simulation};
+use crate::systems::ui::{UiFont, set_hidden};
+
+use amethyst::{
+    core::{timing::Time, Hidden},
+    ecs::{
+        prelude::{Component, DenseVecStorage, Entities},
+        Join, Read, ReadStorage, System, Write, WriteStorage,
+    },
+    input::{InputHandler, StringBindings},
+    prelude::*,
+    ui::{Anchor, LineMode, UiText, UiTransform},
+};
+
+const INDICATOR_WIDTH: f32 = 200.0;
+const INDICATOR_HEIGHT: f32 = 28.0;
+const INDICATOR_COLOR: [f32; 4] = [1.0, 0.85, 0.4, 1.0];
+
+// Shows when the game is paused or not running at normal speed.
+pub struct SpeedIndicator;
+
+impl Component for SpeedIndicator {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn initialize_speed_indicator(world: &mut World) {
+    let font = world.read_resource::<UiFont>().0.clone();
+
+    world
+        .create_entity()
+        .with(UiTransform::new(
+            "speed_indicator".to_string(),
+            Anchor::TopMiddle,
+            Anchor::TopMiddle,
+            0.0,
+            -8.0,
+            1.0,
+            INDICATOR_WIDTH,
+            INDICATOR_HEIGHT,
+        ))
+        .with(UiText::new(
+            font,
+            String::new(),
+            INDICATOR_COLOR,
+            18.0,
+            LineMode::Single,
+            Anchor::Middle,
+        ))
+        .with(SpeedIndicator)
+        .with(Hidden)
+        .build();
+}
+
+// Pauses and unpauses the game with "tactical_pause", and makes it run
+// slower or faster with "slower" and "faster". While paused only the
+// simulation stops, the party can still be selected and given orders.
+// Out of the dungeon, in menus and overlays, it leaves everything be.
+#[derive(Default)]
+pub struct GameSpeedSystem {
+    pause_key_was_down: bool,
+    slower_key_was_down: bool,
+    faster_key_was_down: bool,
+}
+
+impl<'s> System<'s> for GameSpeedSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, PlayerInput>,
+        Write<'s, GameSpeed>,
+        Write<'s, Simulation>,
+        Write<'s, Time>,
+        ReadStorage<'s, SpeedIndicator>,
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, Hidden>,
+    );
+
+    fn run(&mut self, (entities, input, player_input, mut speed, mut simulation, mut time, indicators, mut texts, mut hiddens): Self::SystemData) {
+        if *player_input == PlayerInput::Disabled {
+            for (entity, _) in (&entities, &indicators).join() {
+                set_hidden(&mut hiddens, entity, true);
+            }
+            return;
+        }
+
+        let is_down = input.action_is_down("tactical_pause").unwrap_or(false);
+        if is_down && !self.pause_key_was_down {
+            speed.paused = !speed.paused;
+        }
+        self.pause_key_was_down = is_down;
+
+        let is_down = input.action_is_down("slower").unwrap_or(false);
+        if is_down && !self.slower_key_was_down {
+            speed.slower();
+        }
+        self.slower_key_was_down = is_down;
+
+        let is_down = input.action_is_down("faster").unwrap_or(false);
+        if is_down && !self.faster_key_was_down {
+            speed.faster();
+        }
+        self.faster_key_was_down = is_down;
+
+        *simulation = speed.simulation();
+        if time.time_scale() != speed.time_scale() {
+            time.set_time_scale(speed.time_scale());
+        }
+
+        let text = if speed.paused {
+            "paused".to_string()
+        } else {
+            format!("{}x", speed.time_scale())
+        };
+        let normal = !speed.paused && speed.time_scale() == 1.0;
+        for (entity, _) in (&entities, &indicators).join() {
+            set_hidden(&mut hiddens, entity, normal);
+            if let Some(ui_text) = texts.get_mut(entity) {
+                ui_text.text = text.clone();
+            }
+        }
+    }
+}
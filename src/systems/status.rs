@@ -0,0 +1,126 @@
+use crate::components::npc::Attackable;
+use crate::components::stats::{Stats, Resistances, mitigated_damage};
+use crate::components::status::{StatusEffects, StatusIconSlot, StatusKind};
+use crate::resources::events::CombatEvent;
+use crate::systems::combat::{apply_damage, apply_heal};
+use crate::resources::simulation::TICK_SECONDS;
+
+use amethyst::core::{
+    Hidden,
+    Parent,
+};
+use amethyst::ecs::{
+    prelude::{Entity, Entities},
+    Join, ReadStorage, System, Write, WriteStorage,
+};
+use amethyst::renderer::SpriteRender;
+use amethyst::shrev::EventChannel;
+
+// Regeneration heals in whole ticks, like damage over time,
+// rather than a sliver every frame.
+const REGENERATION_INTERVAL: f32 = 1.0;
+
+// Counts down status effects, applies their ticking damage and
+// healing, and drops them once they run out.
+pub struct StatusSystem;
+
+impl<'s> System<'s> for StatusSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, StatusEffects>,
+        WriteStorage<'s, Attackable>,
+        ReadStorage<'s, Stats>,
+        ReadStorage<'s, Resistances>,
+        Write<'s, EventChannel<CombatEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut statuses, mut attackables, stats, resistances, mut combat_events): Self::SystemData
+    ) {
+        let delta = TICK_SECONDS;
+        let default_resistances = Resistances::default();
+        let no_attacker = Stats::default();
+
+        for (entity, status, attackable) in (&entities, &mut statuses, &mut attackables).join() {
+            // Dead units don't tick.
+            if attackable.health <= 0.0 {
+                status.effects.clear();
+                continue;
+            }
+
+            let defender = status.modify_stats(stats.get(entity).unwrap_or(&no_attacker));
+            let target_resistances = resistances.get(entity).unwrap_or(&default_resistances);
+
+            let mut ticks: Vec<(Option<Entity>, StatusKind, u32)> = vec![];
+            for effect in status.effects.iter_mut() {
+                effect.remaining -= delta;
+                let interval = match effect.kind {
+                    StatusKind::DamageOverTime { interval, .. } => interval,
+                    StatusKind::Regeneration { .. } => REGENERATION_INTERVAL,
+                    _ => continue,
+                };
+                effect.tick_timer += delta;
+                while effect.tick_timer >= interval {
+                    effect.tick_timer -= interval;
+                    ticks.push((effect.source, effect.kind, effect.stacks));
+                }
+            }
+
+            for (source, kind, stacks) in ticks {
+                match kind {
+                    StatusKind::DamageOverTime { mut damage, .. } => {
+                        damage.amount = mitigated_damage(damage, &no_attacker, &defender, target_resistances) * stacks as f32;
+                        if apply_damage(&mut combat_events, source, entity, damage, false, attackable, Some(&mut *status)) {
+                            break;
+                        }
+                    },
+                    StatusKind::Regeneration { per_second } => {
+                        let amount = per_second * REGENERATION_INTERVAL * stacks as f32;
+                        apply_heal(&mut combat_events, source, entity, amount, attackable);
+                    },
+                    _ => {},
+                }
+            }
+
+            status.effects.retain(|effect| !effect.is_expired());
+        }
+    }
+}
+
+// Shows one icon per active kind of status effect above
+// each unit's health bar.
+pub struct StatusIconSystem;
+
+impl<'s> System<'s> for StatusIconSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, StatusIconSlot>,
+        ReadStorage<'s, Parent>,
+        ReadStorage<'s, StatusEffects>,
+        WriteStorage<'s, SpriteRender>,
+        WriteStorage<'s, Hidden>,
+    );
+
+    fn run(&mut self, (entities, icon_slots, parents, statuses, mut renders, mut hiddens): Self::SystemData) {
+        for (entity, icon_slot, parent, render) in (&entities, &icon_slots, &parents, &mut renders).join() {
+            let icon = statuses
+                .get(parent.entity)
+                .and_then(|s| s.icons().get(icon_slot.slot).cloned());
+
+            match icon {
+                Some(icon) => {
+                    render.sprite_number = icon as usize;
+                    hiddens.remove(entity);
+                },
+                None => {
+                    if !hiddens.contains(entity) {
+                        if let Err(e) = hiddens.insert(entity, Hidden) {
+                            println!("error hiding status icon : {}", e);
+                        }
+                    }
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use crate::components::abilities::Abilities;
+use crate::components::npc::{ArchetypeId, Attacker, CanTarget, Dead, Downed, Enemy, Npc, PlayerControlled, Waiting};
+use crate::components::projectile::Projectile;
+use crate::components::stats::Stats;
+use crate::components::status::StatusEffects;
+use crate::resources::archetypes::ArchetypeRegistry;
+use crate::resources::events::CombatEvent;
+use crate::resources::orders::PartyOrders;
+use crate::resources::dungeon::Dungeon;
+use crate::resources::run::Run;
+use crate::resources::turns::{
+    CombatMode, Encounter, ABILITY_COST, ATTACK_COST, ENCOUNTER_RADIUS, TURN_SECONDS,
+    roll_initiative, snap_to_grid, tile_of,
+};
+use crate::systems::ui::set_hidden;
+use crate::resources::simulation::{FixedStep, TICK_SECONDS};
+use crate::components::save::{Persistent, marker_id};
+use crate::systems::commands::distance;
+use crate::systems::ui::UiFont;
+
+use amethyst::{
+    core::{Hidden, Transform},
+    derive::SystemDesc,
+    ecs::{
+        prelude::{Component, DenseVecStorage, Entities, Entity},
+        Join, Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteStorage,
+    },
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, LineMode, UiText, UiTransform},
+};
+
+// How long a party member's turn carries on once they are out of
+// points, so a last step or swing can finish.
+const TURN_END_DELAY: f32 = 0.3;
+// How long an enemy gets to do nothing before its turn is over.
+const ENEMY_IDLE_DELAY: f32 = 0.75;
+
+const INDICATOR_WIDTH: f32 = 360.0;
+const INDICATOR_HEIGHT: f32 = 28.0;
+const INDICATOR_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// Shows whose turn it is during a turn-based fight.
+pub struct TurnIndicator;
+
+impl Component for TurnIndicator {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub fn initialize_turn_indicator(world: &mut World) {
+    let font = world.read_resource::<UiFont>().0.clone();
+
+    world
+        .create_entity()
+        .with(UiTransform::new(
+            "turn_indicator".to_string(),
+            Anchor::TopMiddle,
+            Anchor::TopMiddle,
+            0.0,
+            -40.0,
+            1.0,
+            INDICATOR_WIDTH,
+            INDICATOR_HEIGHT,
+        ))
+        .with(UiText::new(
+            font,
+            String::new(),
+            INDICATOR_COLOR,
+            16.0,
+            LineMode::Single,
+            Anchor::Middle,
+        ))
+        .with(TurnIndicator)
+        .with(Hidden)
+        .build();
+}
+
+// Runs turn-based fights. Once someone picks a fight, everyone near
+// it takes turns in order of initiative. Whoever is acting is left to
+// the usual real-time systems, so orders, attacks, abilities and the
+// enemy ai all work the same as outside a fight, while everyone else
+// is held as `Waiting`. Walking and acting cost action points, and a
+// turn ends once they run out, when the player ends it, or when an
+// enemy has nothing left to do.
+#[derive(SystemDesc)]
+#[system_desc(name(TurnSystemDesc))]
+pub struct TurnSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<CombatEvent>,
+}
+
+impl TurnSystem {
+    pub fn new(reader_id: ReaderId<CombatEvent>) -> Self {
+        TurnSystem { reader_id }
+    }
+}
+
+impl<'s> System<'s> for TurnSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<CombatEvent>>,
+        Write<'s, PartyOrders>,
+        Read<'s, Run>,
+        Write<'s, Encounter>,
+        Read<'s, Dungeon>,
+        Read<'s, FixedStep>,
+        ReadStorage<'s, Persistent>,
+        ReadExpect<'s, ArchetypeRegistry>,
+        WriteStorage<'s, Npc>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, Attacker>,
+        WriteStorage<'s, Abilities>,
+        ReadStorage<'s, CanTarget>,
+        ReadStorage<'s, PlayerControlled>,
+        ReadStorage<'s, Enemy>,
+        ReadStorage<'s, Downed>,
+        ReadStorage<'s, Dead>,
+        WriteStorage<'s, Waiting>,
+        ReadStorage<'s, Stats>,
+        ReadStorage<'s, StatusEffects>,
+        ReadStorage<'s, Projectile>,
+        ReadStorage<'s, ArchetypeId>,
+        ReadStorage<'s, TurnIndicator>,
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, Hidden>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            combat_events,
+            mut orders,
+            run,
+            mut encounter,
+            dungeon,
+            step,
+            markers,
+            registry,
+            mut npcs,
+            mut transforms,
+            mut attackers,
+            mut abilities,
+            targeters,
+            pcs,
+            enemies,
+            downeds,
+            deads,
+            mut waitings,
+            stats,
+            statuses,
+            projectiles,
+            archetype_ids,
+            indicators,
+            mut texts,
+            mut hiddens,
+        ): Self::SystemData
+    ) {
+        let acting = encounter.acting();
+        let mut swings = 0;
+        for event in combat_events.read(&mut self.reader_id) {
+            if let CombatEvent::AttackStarted { attacker, .. } = event {
+                if Some(*attacker) == acting {
+                    swings += 1;
+                }
+            }
+        }
+
+        let end_turn = std::mem::replace(&mut orders.end_turn, false);
+        if end_turn && acting.map_or(false, |a| pcs.contains(a)) {
+            encounter.end_turn = true;
+        }
+
+        let turn_based = run.mode == CombatMode::TurnBased;
+        let party: Vec<[f32; 2]> = (&pcs, &transforms, !&downeds)
+            .join()
+            .map(|(_, t, _)| position(t))
+            .collect();
+        // Enemies close enough to the party to be drawn into a fight.
+        let nearby_enemies: Vec<Entity> = (&entities, &enemies, &transforms, &targeters, !&deads)
+            .join()
+            .filter(|(_, _, t, targeter, _)| {
+                targeter.target.is_some()
+                    || party.iter().any(|p| distance(*p, position(t)) <= ENCOUNTER_RADIUS)
+            })
+            .map(|(e, _, _, _, _)| e)
+            .collect();
+
+        let mut new_turn = false;
+
+        if !encounter.is_active() {
+            let fight = (&targeters, &enemies, !&deads)
+                .join()
+                .any(|(t, _, _)| t.target.map_or(false, |t| pcs.contains(t)))
+                || (&targeters, &pcs, !&downeds)
+                    .join()
+                    .any(|(t, _, _)| t.target.map_or(false, |t| enemies.contains(t)));
+            if !turn_based || !fight || party.is_empty() {
+                return;
+            }
+
+            // Downed party members sit the fight out until they are revived.
+            let mut rolls: Vec<(Entity, f32)> = (&entities, &pcs, !&downeds)
+                .join()
+                .map(|(e, _, _)| e)
+                .chain(nearby_enemies.iter().cloned())
+                .map(|e| {
+                    let mut rng = dungeon.roll_rng(step.tick, marker_id(&markers, e), 0);
+                    (e, roll_initiative(&mut rng, stats.get(e).map_or(0.0, |s| s.dexterity)))
+                })
+                .collect();
+            rolls.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+            *encounter = Encounter {
+                order: rolls.into_iter().map(|(e, _)| e).collect(),
+                round: 1,
+                ..Encounter::default()
+            };
+            new_turn = true;
+            println!("a fight breaks out!");
+        } else {
+            // The dead drop out of the turn order.
+            let before = encounter.acting();
+            encounter.order.retain(|e| entities.is_alive(*e) && !deads.contains(*e));
+            match before.and_then(|b| encounter.order.iter().position(|e| *e == b)) {
+                Some(index) => encounter.current = index,
+                None => {
+                    if encounter.current >= encounter.order.len() {
+                        encounter.current = 0;
+                        encounter.round += 1;
+                    }
+                    new_turn = true;
+                },
+            }
+
+            let enemies_left = encounter.order.iter().any(|e| enemies.contains(*e));
+            if !turn_based || !enemies_left || party.is_empty() {
+                let held: Vec<Entity> = (&entities, &waitings).join().map(|(e, _)| e).collect();
+                for entity in held {
+                    waitings.remove(entity);
+                }
+                *encounter = Encounter::default();
+                for (entity, _) in (&entities, &indicators).join() {
+                    set_hidden(&mut hiddens, entity, true);
+                }
+                println!("the fight is over.");
+                return;
+            }
+        }
+
+        // Everyone but the acting unit waits, whether they
+        // are in the fight or just nearby.
+        let actor = match encounter.acting() {
+            Some(actor) => actor,
+            None => return,
+        };
+        let units: Vec<Entity> = (&entities, &npcs).join().map(|(e, _)| e).collect();
+        for entity in units {
+            if entity == actor {
+                waitings.remove(entity);
+            } else if !waitings.contains(entity) {
+                if let Err(e) = waitings.insert(entity, Waiting) {
+                    println!("error holding a unit for its turn : {}", e);
+                }
+            }
+        }
+
+        if new_turn {
+            start_turn(actor, &mut encounter, &mut npcs, &transforms, &mut attackers, &mut abilities, &pcs);
+        }
+
+        // Pay for whatever the acting unit got up to since last frame.
+        if let Some(transform) = transforms.get(actor) {
+            encounter.pay_for_walking(tile_of(position(transform)));
+        }
+        for _ in 0..swings {
+            if !encounter.spend(ATTACK_COST) {
+                encounter.action_points = 0;
+            }
+        }
+        if let Some(caster) = abilities.get_mut(actor) {
+            if caster.casting.is_some() {
+                if !encounter.cast_paid && !encounter.spend(ABILITY_COST) {
+                    encounter.action_points = 0;
+                }
+                encounter.cast_paid = true;
+            } else {
+                encounter.cast_paid = false;
+                if caster.queued.is_some() && encounter.action_points < ABILITY_COST {
+                    caster.queued = None;
+                    println!("not enough action points left for that.");
+                }
+            }
+        }
+
+        // Units walk from tile to tile, no further than they have points
+        // for. Out of points, they only finish the step they are on.
+        if let (Some(npc), Some(transform)) = (npcs.get_mut(actor), transforms.get(actor)) {
+            if npc.velocity != [0.0, 0.0] {
+                npc.move_coords = encounter.step_limit(position(transform), npc.move_coords);
+            }
+        }
+        if let Some(attacker) = attackers.get_mut(actor) {
+            if !attacker.is_swinging() {
+                attacker.cooldown_remaining = if encounter.action_points >= ATTACK_COST { 0.0 } else { TURN_SECONDS };
+            }
+        }
+
+        let busy = npcs.get(actor).map_or(false, |n| n.velocity != [0.0, 0.0])
+            || attackers.get(actor).map_or(false, |a| a.is_swinging())
+            || abilities.get(actor).map_or(false, |a| a.is_busy())
+            || (&projectiles).join().next().is_some();
+        encounter.idle = if busy { 0.0 } else { encounter.idle + TICK_SECONDS };
+
+        let is_pc = pcs.contains(actor);
+        let cannot_act = downeds.contains(actor) || statuses.get(actor).map_or(false, |s| s.is_stunned());
+        let done = if is_pc {
+            encounter.end_turn || (encounter.action_points == 0 && encounter.idle >= TURN_END_DELAY)
+        } else {
+            encounter.idle >= ENEMY_IDLE_DELAY || (encounter.action_points == 0 && encounter.idle >= TURN_END_DELAY)
+        };
+
+        if cannot_act || done {
+            // Units end their turn standing in the middle of a tile.
+            if let Some(transform) = transforms.get_mut(actor) {
+                let snapped = snap_to_grid(position(transform));
+                transform.set_translation_x(snapped[0]);
+                transform.set_translation_y(snapped[1]);
+                if let Some(npc) = npcs.get_mut(actor) {
+                    npc.velocity = [0.0, 0.0];
+                    npc.move_coords = snapped;
+                }
+            }
+            if let Some(attacker) = attackers.get_mut(actor) {
+                attacker.interrupt();
+            }
+            if let Some(caster) = abilities.get_mut(actor) {
+                caster.cancel();
+            }
+            if let Err(e) = waitings.insert(actor, Waiting) {
+                println!("error holding a unit for its turn : {}", e);
+            }
+
+            encounter.advance();
+            // Enemies who have wandered close join in at the start of a round.
+            if encounter.current == 0 {
+                for enemy in nearby_enemies {
+                    if !encounter.order.contains(&enemy) {
+                        encounter.order.push(enemy);
+                    }
+                }
+            }
+            if let Some(next) = encounter.acting() {
+                waitings.remove(next);
+                start_turn(next, &mut encounter, &mut npcs, &transforms, &mut attackers, &mut abilities, &pcs);
+            }
+        }
+
+        let text = match encounter.acting() {
+            Some(actor) => {
+                let name = archetype_ids
+                    .get(actor)
+                    .and_then(|id| registry.get(&id.0))
+                    .map_or("someone".to_string(), |a| a.name.clone());
+                if pcs.contains(actor) {
+                    format!("round {} - {} - {} ap", encounter.round, name, encounter.action_points)
+                } else {
+                    format!("round {} - {}", encounter.round, name)
+                }
+            },
+            None => String::new(),
+        };
+        for (entity, _) in (&entities, &indicators).join() {
+            set_hidden(&mut hiddens, entity, false);
+            if let Some(ui_text) = texts.get_mut(entity) {
+                ui_text.text = text.clone();
+            }
+        }
+    }
+}
+
+// Hands out fresh action points. Cooldowns tick down once a turn, by
+// however much real time a turn stands in for, and party members
+// are selected on their turn so orders go to them.
+fn start_turn(
+    actor: Entity,
+    encounter: &mut Encounter,
+    npcs: &mut WriteStorage<Npc>,
+    transforms: &WriteStorage<Transform>,
+    attackers: &mut WriteStorage<Attacker>,
+    abilities: &mut WriteStorage<Abilities>,
+    pcs: &ReadStorage<PlayerControlled>,
+) {
+    encounter.begin_turn(transforms.get(actor).map_or([0.0, 0.0], position));
+    if let Some(attacker) = attackers.get_mut(actor) {
+        attacker.cooldown_remaining = 0.0;
+    }
+    if let Some(caster) = abilities.get_mut(actor) {
+        for ability in caster.slots.iter_mut() {
+            ability.cooldown_remaining = (ability.cooldown_remaining - TURN_SECONDS).max(0.0);
+        }
+    }
+    if pcs.contains(actor) {
+        for (npc, _) in (&mut *npcs, pcs).join() {
+            npc.selected = false;
+        }
+        if let Some(npc) = npcs.get_mut(actor) {
+            npc.selected = true;
+        }
+    }
+}
+
+fn position(transform: &Transform) -> [f32; 2] {
+    [transform.translation().x, transform.translation().y]
+}
@@ -0,0 +1,47 @@
+use amethyst::core::Hidden;
+use amethyst::ecs::{prelude::Entity, WriteStorage};
+use amethyst::ui::FontHandle;
+
+// The font all of the ui is written in, loaded once up front.
+pub struct UiFont(pub FontHandle);
+
+// The full screen menus that can be opened over the dungeon.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Screen {
+    Talents,
+    Inventory,
+    Shop,
+    SaveSlots,
+}
+
+// Which menu, if any, is open. Only one is shown at a time, and
+// clicks go to it rather than the dungeon while it is open.
+#[derive(Default)]
+pub struct OpenScreen {
+    pub screen: Option<Screen>,
+}
+
+impl OpenScreen {
+    pub fn is_open(&self, screen: Screen) -> bool {
+        self.screen == Some(screen)
+    }
+
+    // Opens `screen`, closing whatever else was open,
+    // or closes it if it was already showing.
+    pub fn toggle(&mut self, screen: Screen) {
+        self.screen = match self.screen {
+            Some(open) if open == screen => None,
+            _ => Some(screen),
+        };
+    }
+}
+
+pub fn set_hidden(hiddens: &mut WriteStorage<Hidden>, entity: Entity, hidden: bool) {
+    if !hidden {
+        hiddens.remove(entity);
+    } else if !hiddens.contains(entity) {
+        if let Err(e) = hiddens.insert(entity, Hidden) {
+            println!("error hiding ui element : {}", e);
+        }
+    }
+}